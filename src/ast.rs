@@ -0,0 +1,272 @@
+use crate::source_map::FileId;
+use std::fmt::{self, Write};
+use std::ops::Range;
+
+/// program ::= ( function_definition )* <EOF>
+#[derive(Debug, PartialEq, Clone)]
+pub struct Program {
+    pub functions: Vec<FunctionDefinition>,
+    /// The file of a source map that the spans refer to, if the program was parsed with
+    /// [`C1Parser::parse_file`](crate::parser::C1Parser::parse_file)
+    pub file: Option<FileId>,
+}
+
+/// function_definition ::= type <ID> "(" ")" "{" statement_list "}"
+#[derive(Debug, PartialEq, Clone)]
+pub struct FunctionDefinition {
+    pub return_type: Type,
+    pub name: Identifier,
+    pub body: Vec<Statement>,
+    /// Byte range from the return type up to and including the closing brace
+    pub span: Range<usize>,
+}
+
+/// type ::= <KW_BOOLEAN> | <KW_FLOAT> | <KW_INT> | <KW_VOID>
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+pub enum Type {
+    Boolean,
+    Float,
+    Int,
+    Void,
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Boolean => write!(f, "bool"),
+            Type::Float => write!(f, "float"),
+            Type::Int => write!(f, "int"),
+            Type::Void => write!(f, "void"),
+        }
+    }
+}
+
+/// The name of a function or variable together with its location
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Identifier {
+    pub name: String,
+    pub span: Range<usize>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Statement {
+    pub kind: StatementKind,
+    /// Byte range of the statement including the terminating semicolon
+    pub span: Range<usize>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum StatementKind {
+    /// "{" statement_list "}"
+    Block(Vec<Statement>),
+    /// <KW_IF> "(" assignment ")" block
+    If {
+        condition: Expression,
+        body: Box<Statement>,
+    },
+    /// <KW_RETURN> ( assignment )? ";"
+    Return(Option<Expression>),
+    /// <KW_PRINTF> "(" assignment ")" ";"
+    Printf(Expression),
+    /// <ID> "=" assignment ";"
+    Assignment {
+        target: Identifier,
+        value: Expression,
+    },
+    /// <ID> "(" ")" ";"
+    Call(Identifier),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Expression {
+    pub kind: ExpressionKind,
+    /// Byte range of the expression, without enclosing parentheses
+    pub span: Range<usize>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ExpressionKind {
+    /// <ID> "=" assignment
+    Assignment {
+        target: Identifier,
+        value: Box<Expression>,
+    },
+    /// Any of the binary operators of expr, simp_expr and term
+    Binary {
+        operator: BinaryOperator,
+        left: Box<Expression>,
+        right: Box<Expression>,
+    },
+    /// The optional "-" in front of the first term of a simp_expr
+    Negation(Box<Expression>),
+    Literal(Literal),
+    /// <ID> "(" ")"
+    Call(Identifier),
+    Variable(Identifier),
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+pub enum LiteralKind {
+    Int,
+    Float,
+    Boolean,
+}
+
+/// A constant, kept as it was written in the source text
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Literal {
+    pub kind: LiteralKind,
+    pub text: String,
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+pub enum BinaryOperator {
+    Equal,
+    NotEqual,
+    LessEqual,
+    GreaterEqual,
+    Less,
+    Greater,
+    Plus,
+    Minus,
+    Or,
+    Times,
+    Divide,
+    And,
+}
+
+impl BinaryOperator {
+    /// Return the operator as written in the source text
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            BinaryOperator::Equal => "==",
+            BinaryOperator::NotEqual => "!=",
+            BinaryOperator::LessEqual => "<=",
+            BinaryOperator::GreaterEqual => ">=",
+            BinaryOperator::Less => "<",
+            BinaryOperator::Greater => ">",
+            BinaryOperator::Plus => "+",
+            BinaryOperator::Minus => "-",
+            BinaryOperator::Or => "||",
+            BinaryOperator::Times => "*",
+            BinaryOperator::Divide => "/",
+            BinaryOperator::And => "&&",
+        }
+    }
+
+    /// Return how tightly the operator binds: comparisons (expr) bind loosest, the operators of
+    /// simp_expr bind tighter, and the operators of term bind tightest.
+    pub fn precedence(&self) -> Precedence {
+        match self {
+            BinaryOperator::Equal
+            | BinaryOperator::NotEqual
+            | BinaryOperator::LessEqual
+            | BinaryOperator::GreaterEqual
+            | BinaryOperator::Less
+            | BinaryOperator::Greater => Precedence::Comparison,
+            BinaryOperator::Plus | BinaryOperator::Minus | BinaryOperator::Or => Precedence::Sum,
+            BinaryOperator::Times | BinaryOperator::Divide | BinaryOperator::And => {
+                Precedence::Product
+            }
+        }
+    }
+}
+
+/// The grammar rule levels an expression can appear on, from loosest to tightest binding
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone)]
+pub enum Precedence {
+    /// assignment
+    Assignment,
+    /// expr
+    Comparison,
+    /// simp_expr
+    Sum,
+    /// term
+    Product,
+    /// factor
+    Factor,
+}
+
+impl Expression {
+    /// Return the grammar level of the expression. Parentheses are required whenever an expression
+    /// is used in a position that requires a tighter level.
+    pub fn precedence(&self) -> Precedence {
+        match &self.kind {
+            ExpressionKind::Assignment { .. } => Precedence::Assignment,
+            ExpressionKind::Binary { operator, .. } => operator.precedence(),
+            ExpressionKind::Negation(_) => Precedence::Sum,
+            ExpressionKind::Literal(_) | ExpressionKind::Call(_) | ExpressionKind::Variable(_) => {
+                Precedence::Factor
+            }
+        }
+    }
+}
+
+impl Program {
+    /// Return a textual representation of the tree without any location information. Two programs
+    /// have the same dump exactly if they only differ in formatting and comments.
+    /// ```
+    /// use cb_3::parser::C1Parser;
+    ///
+    /// let program = C1Parser::parse_ast("void main() { x = -(1 + 2) * 3; }").unwrap();
+    /// assert_eq!(program.dump(), "(void main\n  (= x (- (* (+ 1 2) 3))))\n");
+    /// ```
+    pub fn dump(&self) -> String {
+        let mut output = String::new();
+        for function in &self.functions {
+            write!(output, "({} {}", function.return_type, function.name.name).unwrap();
+            dump_statements(&mut output, &function.body, 1);
+            output.push_str(")\n");
+        }
+        output
+    }
+}
+
+fn dump_statements(output: &mut String, statements: &[Statement], depth: usize) {
+    for statement in statements {
+        write!(output, "\n{}", "  ".repeat(depth)).unwrap();
+        dump_statement(output, statement, depth);
+    }
+}
+
+fn dump_statement(output: &mut String, statement: &Statement, depth: usize) {
+    match &statement.kind {
+        StatementKind::Block(statements) => {
+            output.push_str("(block");
+            dump_statements(output, statements, depth + 1);
+            output.push(')');
+        }
+        StatementKind::If { condition, body } => {
+            write!(output, "(if {}\n{}", condition, "  ".repeat(depth + 1)).unwrap();
+            dump_statement(output, body, depth + 1);
+            output.push(')');
+        }
+        StatementKind::Return(None) => output.push_str("(return)"),
+        StatementKind::Return(Some(value)) => write!(output, "(return {})", value).unwrap(),
+        StatementKind::Printf(value) => write!(output, "(printf {})", value).unwrap(),
+        StatementKind::Assignment { target, value } => {
+            write!(output, "(= {} {})", target.name, value).unwrap()
+        }
+        StatementKind::Call(function) => write!(output, "(call {})", function.name).unwrap(),
+    }
+}
+
+/// Expressions are displayed as s-expressions, e.g. `(+ 1 (* x 2))`
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ExpressionKind::Assignment { target, value } => {
+                write!(f, "(= {} {})", target.name, value)
+            }
+            ExpressionKind::Binary {
+                operator,
+                left,
+                right,
+            } => write!(f, "({} {} {})", operator.symbol(), left, right),
+            ExpressionKind::Negation(operand) => write!(f, "(- {})", operand),
+            ExpressionKind::Literal(literal) => write!(f, "{}", literal.text),
+            ExpressionKind::Call(function) => write!(f, "(call {})", function.name),
+            ExpressionKind::Variable(variable) => write!(f, "{}", variable.name),
+        }
+    }
+}
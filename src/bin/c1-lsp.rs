@@ -0,0 +1,15 @@
+use cb_3::lsp::Server;
+use std::io;
+use std::process::ExitCode;
+
+/// Language server for C(-1) editors. Talks JSON-RPC over stdin and stdout.
+fn main() -> ExitCode {
+    let stdin = io::stdin();
+    match Server::new().run(stdin.lock(), io::stdout().lock()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("c1-lsp: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,279 @@
+use cb_3::checker;
+use cb_3::diagnostic::{self, Diagnostic};
+use cb_3::dot;
+use cb_3::error_code::ErrorCode;
+use cb_3::formatter;
+use cb_3::generator::Generator;
+use cb_3::grammar::Grammar;
+use cb_3::grammar_analysis::GrammarAnalysis;
+use cb_3::highlight;
+use cb_3::parser::C1Parser;
+use cb_3::source_map::SourceMap;
+use std::env;
+use std::fs;
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage: c1 check [--format human|json|jsonl] <file>...
+       c1 dot [--ast] <file>
+       c1 explain [<code>]
+       c1 fmt [--check] <file>...
+       c1 generate [--typed] [--seed <n>] [--depth <n>]
+       c1 grammar [<file>]
+       c1 highlight [--format ansi|html] [--diagnostics] <file>
+       c1 trace [--format tree|jsonl] <file>";
+
+/// Output formats for diagnostics
+enum Format {
+    Human,
+    Json,
+    JsonLines,
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("check") => check(&args[1..]),
+        Some("dot") => graph(&args[1..]),
+        Some("explain") => explain(&args[1..]),
+        Some("fmt") => fmt(&args[1..]),
+        Some("generate") => generate(&args[1..]),
+        Some("grammar") => grammar(&args[1..]),
+        Some("highlight") => highlight(&args[1..]),
+        Some("trace") => trace(&args[1..]),
+        _ => Err(String::from(USAGE)),
+    };
+    match result {
+        Ok(code) => code,
+        Err(message) => {
+            eprintln!("{}", message);
+            ExitCode::from(2)
+        }
+    }
+}
+
+/// Parse all given files and report the diagnostics in the requested format
+fn check(args: &[String]) -> Result<ExitCode, String> {
+    let mut format = Format::Human;
+    let mut files = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => {
+                format = match args.next().map(String::as_str) {
+                    Some("human") => Format::Human,
+                    Some("json") => Format::Json,
+                    Some("jsonl") => Format::JsonLines,
+                    _ => return Err(String::from(USAGE)),
+                }
+            }
+            file => files.push(file),
+        }
+    }
+    if files.is_empty() {
+        return Err(String::from(USAGE));
+    }
+
+    let mut sources = SourceMap::new();
+    for file in files {
+        let text = fs::read_to_string(file).map_err(|error| format!("{}: {}", file, error))?;
+        sources.add(file, text);
+    }
+    let diagnostics: Vec<Diagnostic> = sources
+        .files()
+        .flat_map(|(file, _)| checker::diagnostics_in_file(&sources, file))
+        .collect();
+
+    match format {
+        Format::Human => diagnostics.iter().for_each(|d| eprintln!("{}", d)),
+        Format::Json => println!("{}", diagnostic::to_json(&diagnostics)),
+        Format::JsonLines => print!("{}", diagnostic::to_json_lines(&diagnostics)),
+    }
+    Ok(if diagnostics.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+/// Print the derivation tree of the file, or with `--ast` its abstract syntax tree, as a Graphviz
+/// graph
+fn graph(args: &[String]) -> Result<ExitCode, String> {
+    let (ast, file) = match args {
+        [file] => (false, file),
+        [flag, file] if flag == "--ast" => (true, file),
+        _ => return Err(String::from(USAGE)),
+    };
+    let text = fs::read_to_string(file).map_err(|error| format!("{}: {}", file, error))?;
+    let (result, events) = C1Parser::parse_with_events(&text);
+    match result {
+        Ok(program) if ast => print!("{}", dot::ast(&text, &program)),
+        Ok(_) => print!("{}", dot::derivation(&text, &events)),
+        Err(diagnostic) => {
+            eprintln!("{}", diagnostic.in_file(file.as_str()));
+            return Ok(ExitCode::FAILURE);
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Print the long explanation of an error code, or a list of all codes if none is given
+fn explain(args: &[String]) -> Result<ExitCode, String> {
+    match args {
+        [] => {
+            for error_code in ErrorCode::ALL {
+                println!("{}: {}", error_code, error_code.title());
+            }
+        }
+        [code] => {
+            let error_code: ErrorCode = code.parse()?;
+            println!("{}: {}\n", error_code, error_code.title());
+            print!("{}", error_code.explanation());
+        }
+        _ => return Err(String::from(USAGE)),
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Format the given files in place. With `--check` the files are left untouched, and the command
+/// fails if any of them is not formatted.
+fn fmt(args: &[String]) -> Result<ExitCode, String> {
+    let check = args.iter().any(|arg| arg == "--check");
+    let files: Vec<&String> = args.iter().filter(|arg| *arg != "--check").collect();
+    if files.is_empty() {
+        return Err(String::from(USAGE));
+    }
+
+    let mut success = true;
+    for file in files {
+        let text = fs::read_to_string(file).map_err(|error| format!("{}: {}", file, error))?;
+        match formatter::format(&text) {
+            Ok(formatted) if formatted == text => {}
+            Ok(_) if check => {
+                eprintln!("{}: not formatted", file);
+                success = false;
+            }
+            Ok(formatted) => {
+                fs::write(file, formatted).map_err(|error| format!("{}: {}", file, error))?
+            }
+            Err(diagnostic) => {
+                eprintln!("{}", diagnostic.in_file(file.as_str()));
+                success = false;
+            }
+        }
+    }
+    Ok(if success {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+/// Print a random program, derived from the grammar of C(-1) or, with `--typed`, one that passes
+/// the semantic checks and terminates
+fn generate(args: &[String]) -> Result<ExitCode, String> {
+    let mut typed = false;
+    let mut seed = 0;
+    let mut depth = 8;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let number = |value: Option<&String>| value.and_then(|value| value.parse().ok());
+        match arg.as_str() {
+            "--typed" => typed = true,
+            "--seed" => seed = number(args.next()).ok_or_else(|| String::from(USAGE))?,
+            "--depth" => depth = number(args.next()).ok_or_else(|| String::from(USAGE))? as usize,
+            _ => return Err(String::from(USAGE)),
+        }
+    }
+    let mut generator = Generator::new(seed, depth);
+    if typed {
+        print!("{}", generator.typed_program());
+    } else {
+        println!("{}", generator.derivation(&Grammar::c1().to_bnf()));
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Print the analysis of an EBNF grammar, by default the grammar of C(-1). The command fails if
+/// the grammar has conflicts that two tokens of lookahead cannot resolve, left recursion or
+/// unreachable nonterminals.
+fn grammar(args: &[String]) -> Result<ExitCode, String> {
+    let grammar = match args {
+        [] => Grammar::c1(),
+        [file] => {
+            let text = fs::read_to_string(file).map_err(|error| format!("{}: {}", file, error))?;
+            Grammar::parse(&text).map_err(|error| format!("{}:{}", file, error))?
+        }
+        _ => return Err(String::from(USAGE)),
+    };
+    let analysis = GrammarAnalysis::new(&grammar);
+    print!("{}", analysis);
+    Ok(if analysis.is_ll2() && analysis.unreachable.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+/// Print the file with syntax highlighting, optionally underlining its diagnostics
+fn highlight(args: &[String]) -> Result<ExitCode, String> {
+    let mut html = false;
+    let mut with_diagnostics = false;
+    let mut file = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => {
+                html = match args.next().map(String::as_str) {
+                    Some("ansi") => false,
+                    Some("html") => true,
+                    _ => return Err(String::from(USAGE)),
+                }
+            }
+            "--diagnostics" => with_diagnostics = true,
+            name if file.is_none() => file = Some(name),
+            _ => return Err(String::from(USAGE)),
+        }
+    }
+    let file = file.ok_or_else(|| String::from(USAGE))?;
+
+    let text = fs::read_to_string(file).map_err(|error| format!("{}: {}", file, error))?;
+    let diagnostics = if with_diagnostics {
+        checker::diagnostics(&text)
+    } else {
+        Vec::new()
+    };
+    if html {
+        println!("{}", highlight::html(&text, &diagnostics));
+    } else {
+        print!("{}", highlight::ansi(&text, &diagnostics));
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Print the trace of parsing the file as an indented tree or as JSON Lines
+fn trace(args: &[String]) -> Result<ExitCode, String> {
+    let (json, file) = match args {
+        [file] => (false, file),
+        [flag, format, file] if flag == "--format" => match format.as_str() {
+            "tree" => (false, file),
+            "jsonl" => (true, file),
+            _ => return Err(String::from(USAGE)),
+        },
+        _ => return Err(String::from(USAGE)),
+    };
+    let text = fs::read_to_string(file).map_err(|error| format!("{}: {}", file, error))?;
+    let (result, trace) = C1Parser::parse_traced(&text);
+    if json {
+        print!("{}", trace.to_json_lines());
+    } else {
+        print!("{}", trace);
+    }
+    Ok(match result {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(diagnostic) => {
+            eprintln!("{}", diagnostic.in_file(file.as_str()));
+            ExitCode::FAILURE
+        }
+    })
+}
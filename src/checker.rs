@@ -0,0 +1,615 @@
+use crate::ast::{
+    BinaryOperator, Expression, ExpressionKind, FunctionDefinition, Identifier, LiteralKind,
+    Program, Statement, StatementKind, Type,
+};
+use crate::diagnostic::{Diagnostic, Position};
+use crate::error_code::ErrorCode;
+use crate::parser::C1Parser;
+use crate::source_map::{FileId, SourceMap};
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// # Overview
+/// Semantic analysis of a parsed C(-1) program. The checker resolves every function and variable
+/// name to its definition and infers the type of every expression.
+///
+/// C(-1) has no declarations: functions are visible in the whole program, and a variable is
+/// defined by its first assignment in its function. Its type is the type of the first assigned
+/// value. Variables of different functions are independent of each other.
+///
+/// # Examples
+/// ```
+/// use cb_3::ast::Type;
+/// use cb_3::checker;
+/// use cb_3::parser::C1Parser;
+///
+/// let text = "int one() { x = 1; return x; }";
+/// let analysis = checker::check(text, &C1Parser::parse_ast(text).unwrap());
+/// assert!(analysis.diagnostics.is_empty());
+///
+/// let symbol = analysis.symbol_at(26).unwrap();
+/// assert_eq!(analysis.definition(symbol), 12..13);
+/// assert_eq!(analysis.type_at(26), Some(Type::Int));
+/// ```
+pub fn check(text: &str, program: &Program) -> Analysis {
+    let mut checker = Checker {
+        text,
+        analysis: Analysis::default(),
+        scope: HashMap::new(),
+        function: 0,
+    };
+    checker.program(program);
+    for diagnostic in &mut checker.analysis.diagnostics {
+        diagnostic.file_id = program.file;
+    }
+    checker.analysis
+}
+
+/// Parse and check the given text and return all problems that were found. Semantic checks are
+/// only performed if the text could be parsed, otherwise the syntax error and all lexical errors
+/// are reported.
+pub fn diagnostics(text: &str) -> Vec<Diagnostic> {
+    match C1Parser::parse_ast(text) {
+        Ok(program) => check(text, &program).diagnostics,
+        Err(_) => C1Parser::diagnostics(text),
+    }
+}
+
+/// Parse and check a file of a source map like [`diagnostics`]. The diagnostics refer to the
+/// file, so they are displayed with its name and can be located with [`SourceMap::span_of`].
+/// ```
+/// use cb_3::checker;
+/// use cb_3::source_map::SourceMap;
+///
+/// let mut sources = SourceMap::new();
+/// sources.add("lib.c-1", "int one() { return 1; }");
+/// let main = sources.add("main.c-1", "void main() { printf(two()); }");
+///
+/// let diagnostics = checker::diagnostics_in_file(&sources, main);
+/// assert_eq!(diagnostics[0].to_string(), "main.c-1:1:22: error[E0012]: Function 'two' is not defined");
+/// assert_eq!(sources.span_of(&diagnostics[0]), Some(45..48));
+/// ```
+pub fn diagnostics_in_file(sources: &SourceMap, file: FileId) -> Vec<Diagnostic> {
+    diagnostics(sources.file(file).text())
+        .into_iter()
+        .map(|diagnostic| diagnostic.in_source_file(sources, file))
+        .collect()
+}
+
+/// Everything that is known about a program after semantic analysis
+#[derive(Debug, Default, Clone)]
+pub struct Analysis {
+    pub functions: Vec<Function>,
+    pub variables: Vec<Variable>,
+    /// Every occurrence of a function or variable name, including its definition, in text order
+    pub references: Vec<Reference>,
+    /// The inferred type of every expression whose type is known
+    pub types: Vec<(Range<usize>, Type)>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Function {
+    pub name: String,
+    pub return_type: Type,
+    /// Byte range of the name in the function definition
+    pub span: Range<usize>,
+    /// Byte range of the whole function definition
+    pub definition: Range<usize>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Variable {
+    pub name: String,
+    /// Index of the function that contains the variable
+    pub function: usize,
+    /// The type of the first assigned value, if it could be inferred
+    pub ty: Option<Type>,
+    /// Byte range of the name in the first assignment
+    pub span: Range<usize>,
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+pub enum Symbol {
+    Function(usize),
+    Variable(usize),
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Reference {
+    pub span: Range<usize>,
+    pub symbol: Symbol,
+}
+
+impl Analysis {
+    /// Return the symbol whose name is at the given byte offset. An offset directly behind a name
+    /// also refers to it.
+    pub fn symbol_at(&self, offset: usize) -> Option<Symbol> {
+        self.references
+            .iter()
+            .find(|reference| reference.span.start <= offset && offset <= reference.span.end)
+            .map(|reference| reference.symbol)
+    }
+
+    pub fn name(&self, symbol: Symbol) -> &str {
+        match symbol {
+            Symbol::Function(index) => &self.functions[index].name,
+            Symbol::Variable(index) => &self.variables[index].name,
+        }
+    }
+
+    /// Return the byte range of the name at the definition of the symbol
+    pub fn definition(&self, symbol: Symbol) -> Range<usize> {
+        match symbol {
+            Symbol::Function(index) => self.functions[index].span.clone(),
+            Symbol::Variable(index) => self.variables[index].span.clone(),
+        }
+    }
+
+    /// Return the byte ranges of all occurrences of the symbol, including its definition
+    pub fn references(&self, symbol: Symbol) -> Vec<Range<usize>> {
+        self.references
+            .iter()
+            .filter(|reference| reference.symbol == symbol)
+            .map(|reference| reference.span.clone())
+            .collect()
+    }
+
+    /// Return a C-like signature of the symbol, e.g. `int blub()` or `float x`
+    pub fn signature(&self, symbol: Symbol) -> String {
+        match symbol {
+            Symbol::Function(index) => {
+                let function = &self.functions[index];
+                format!("{} {}()", function.return_type, function.name)
+            }
+            Symbol::Variable(index) => {
+                let variable = &self.variables[index];
+                match variable.ty {
+                    Some(ty) => format!("{} {}", ty, variable.name),
+                    None => format!("{{unknown}} {}", variable.name),
+                }
+            }
+        }
+    }
+
+    /// Return the type of the innermost expression that contains the given byte offset
+    pub fn type_at(&self, offset: usize) -> Option<Type> {
+        self.types
+            .iter()
+            .filter(|(span, _)| span.start <= offset && offset < span.end)
+            .min_by_key(|(span, _)| span.len())
+            .map(|(_, ty)| *ty)
+    }
+}
+
+struct Checker<'a> {
+    text: &'a str,
+    analysis: Analysis,
+    /// The variables of the function that is currently checked
+    scope: HashMap<String, usize>,
+    /// Index of the function that is currently checked
+    function: usize,
+}
+
+impl<'a> Checker<'a> {
+    fn program(&mut self, program: &Program) {
+        // Functions can be called before their definition, so all of them are collected first
+        let mut definitions = Vec::new();
+        for function in &program.functions {
+            let index = match self.find_function(&function.name.name) {
+                Some(first) => {
+                    self.error(
+                        ErrorCode::DuplicateFunction,
+                        format!(
+                            "Function '{}' is defined multiple times",
+                            function.name.name
+                        ),
+                        &function.name.span,
+                    );
+                    first
+                }
+                None => {
+                    self.analysis.functions.push(Function {
+                        name: function.name.name.clone(),
+                        return_type: function.return_type,
+                        span: function.name.span.clone(),
+                        definition: function.span.clone(),
+                    });
+                    self.analysis.functions.len() - 1
+                }
+            };
+            definitions.push(index);
+        }
+
+        for (function, index) in program.functions.iter().zip(definitions) {
+            self.function_definition(function, index);
+        }
+        self.analysis
+            .references
+            .sort_by_key(|reference| reference.span.start);
+    }
+
+    fn function_definition(&mut self, function: &FunctionDefinition, index: usize) {
+        self.scope.clear();
+        self.function = index;
+        self.reference(&function.name, Symbol::Function(index));
+        for statement in &function.body {
+            self.statement(statement, function.return_type);
+        }
+    }
+
+    fn statement(&mut self, statement: &Statement, return_type: Type) {
+        match &statement.kind {
+            StatementKind::Block(statements) => {
+                for statement in statements {
+                    self.statement(statement, return_type);
+                }
+            }
+            StatementKind::If { condition, body } => {
+                let ty = self.expression(condition);
+                self.expect_type(ty, Type::Boolean, condition);
+                self.statement(body, return_type);
+            }
+            StatementKind::Return(None) => {
+                if return_type != Type::Void {
+                    self.error(
+                        ErrorCode::MismatchedTypes,
+                        format!("Missing return value in function returning {}", return_type),
+                        &statement.span,
+                    );
+                }
+            }
+            StatementKind::Return(Some(value)) => {
+                let ty = self.expression(value);
+                if return_type == Type::Void {
+                    self.error(
+                        ErrorCode::MismatchedTypes,
+                        "Function returning void must not return a value",
+                        &value.span,
+                    );
+                } else {
+                    self.expect_type(ty, return_type, value);
+                }
+            }
+            StatementKind::Printf(value) => {
+                let ty = self.expression(value);
+                self.expect_value(ty, value);
+            }
+            StatementKind::Assignment { target, value } => {
+                let ty = self.expression(value);
+                self.assignment(target, ty, value);
+            }
+            StatementKind::Call(function) => {
+                self.call(function);
+            }
+        }
+    }
+
+    /// Infer the type of the expression. `None` is returned if the type cannot be determined
+    /// because of an error that has already been reported.
+    fn expression(&mut self, expression: &Expression) -> Option<Type> {
+        let ty = match &expression.kind {
+            ExpressionKind::Assignment { target, value } => {
+                let ty = self.expression(value);
+                self.assignment(target, ty, value)
+            }
+            ExpressionKind::Binary {
+                operator,
+                left,
+                right,
+            } => {
+                let left_type = self.expression(left);
+                let right_type = self.expression(right);
+                self.binary(*operator, (left, left_type), (right, right_type))
+            }
+            ExpressionKind::Negation(operand) => {
+                let ty = self.expression(operand);
+                self.expect_number(ty, BinaryOperator::Minus, operand)
+            }
+            ExpressionKind::Literal(literal) => Some(match literal.kind {
+                LiteralKind::Int => Type::Int,
+                LiteralKind::Float => Type::Float,
+                LiteralKind::Boolean => Type::Boolean,
+            }),
+            ExpressionKind::Call(function) => self.call(function),
+            ExpressionKind::Variable(variable) => match self.scope.get(&variable.name) {
+                Some(&index) => {
+                    self.reference(variable, Symbol::Variable(index));
+                    self.analysis.variables[index].ty
+                }
+                None => {
+                    self.error(
+                        ErrorCode::UndefinedVariable,
+                        format!("Variable '{}' is used before it is assigned", variable.name),
+                        &variable.span,
+                    );
+                    None
+                }
+            },
+        };
+        if let Some(ty) = ty {
+            self.analysis.types.push((expression.span.clone(), ty));
+        }
+        ty
+    }
+
+    fn binary(
+        &mut self,
+        operator: BinaryOperator,
+        (left, left_type): (&Expression, Option<Type>),
+        (right, right_type): (&Expression, Option<Type>),
+    ) -> Option<Type> {
+        match operator {
+            BinaryOperator::Plus
+            | BinaryOperator::Minus
+            | BinaryOperator::Times
+            | BinaryOperator::Divide => {
+                let left_type = self.expect_number(left_type, operator, left);
+                let right_type = self.expect_number(right_type, operator, right);
+                match (left_type?, right_type?) {
+                    (Type::Int, Type::Int) => Some(Type::Int),
+                    _ => Some(Type::Float),
+                }
+            }
+            BinaryOperator::And | BinaryOperator::Or => {
+                for (operand, ty) in [(left, left_type), (right, right_type)] {
+                    if ty.is_some_and(|ty| ty != Type::Boolean) {
+                        self.operator_error(operator, ty, operand);
+                    }
+                }
+                Some(Type::Boolean)
+            }
+            BinaryOperator::Equal | BinaryOperator::NotEqual => {
+                let left_type = self.expect_value(left_type, left);
+                let right_type = self.expect_value(right_type, right);
+                if let (Some(left_type), Some(right_type)) = (left_type, right_type) {
+                    if is_number(left_type) != is_number(right_type) {
+                        self.error(
+                            ErrorCode::MismatchedTypes,
+                            format!(
+                                "Cannot compare {} with {} using '{}'",
+                                left_type,
+                                right_type,
+                                operator.symbol()
+                            ),
+                            &(left.span.start..right.span.end),
+                        );
+                    }
+                }
+                Some(Type::Boolean)
+            }
+            BinaryOperator::Less
+            | BinaryOperator::LessEqual
+            | BinaryOperator::Greater
+            | BinaryOperator::GreaterEqual => {
+                self.expect_number(left_type, operator, left);
+                self.expect_number(right_type, operator, right);
+                Some(Type::Boolean)
+            }
+        }
+    }
+
+    /// Assign a value of the given type to a variable, defining the variable if necessary, and
+    /// return the type of the variable
+    fn assignment(
+        &mut self,
+        target: &Identifier,
+        ty: Option<Type>,
+        value: &Expression,
+    ) -> Option<Type> {
+        match self.scope.get(&target.name) {
+            Some(&index) => {
+                self.reference(target, Symbol::Variable(index));
+                let variable_type = self.analysis.variables[index].ty;
+                if let Some(variable_type) = variable_type {
+                    self.expect_type(ty, variable_type, value);
+                }
+                variable_type
+            }
+            None => {
+                let ty = self.expect_value(ty, value);
+                self.analysis.variables.push(Variable {
+                    name: target.name.clone(),
+                    function: self.function,
+                    ty,
+                    span: target.span.clone(),
+                });
+                let index = self.analysis.variables.len() - 1;
+                self.scope.insert(target.name.clone(), index);
+                self.reference(target, Symbol::Variable(index));
+                ty
+            }
+        }
+    }
+
+    fn call(&mut self, function: &Identifier) -> Option<Type> {
+        match self.find_function(&function.name) {
+            Some(index) => {
+                self.reference(function, Symbol::Function(index));
+                Some(self.analysis.functions[index].return_type)
+            }
+            None => {
+                self.error(
+                    ErrorCode::UndefinedFunction,
+                    format!("Function '{}' is not defined", function.name),
+                    &function.span,
+                );
+                None
+            }
+        }
+    }
+
+    /// Report an error if a value of type `found` cannot be used where `expected` is required. An
+    /// int is implicitly converted to a float.
+    fn expect_type(&mut self, found: Option<Type>, expected: Type, expression: &Expression) {
+        match found {
+            Some(found)
+                if found != expected && !(found == Type::Int && expected == Type::Float) =>
+            {
+                self.error(
+                    ErrorCode::MismatchedTypes,
+                    format!("Mismatched types: expected {}, found {}", expected, found),
+                    &expression.span,
+                );
+            }
+            _ => {}
+        }
+    }
+
+    /// Report an error if the expression has no value, i.e. it calls a void function
+    fn expect_value(&mut self, found: Option<Type>, expression: &Expression) -> Option<Type> {
+        if found == Some(Type::Void) {
+            self.error(
+                ErrorCode::MismatchedTypes,
+                "Expression of type void cannot be used as a value",
+                &expression.span,
+            );
+            return None;
+        }
+        found
+    }
+
+    /// Report an error if the operand of an arithmetic or ordering operator is not a number
+    fn expect_number(
+        &mut self,
+        found: Option<Type>,
+        operator: BinaryOperator,
+        operand: &Expression,
+    ) -> Option<Type> {
+        match found {
+            Some(ty) if !is_number(ty) => {
+                self.operator_error(operator, found, operand);
+                None
+            }
+            _ => found,
+        }
+    }
+
+    fn operator_error(
+        &mut self,
+        operator: BinaryOperator,
+        found: Option<Type>,
+        operand: &Expression,
+    ) {
+        if let Some(ty) = found {
+            self.error(
+                ErrorCode::MismatchedTypes,
+                format!(
+                    "Operator '{}' cannot be applied to {}",
+                    operator.symbol(),
+                    ty
+                ),
+                &operand.span,
+            );
+        }
+    }
+
+    fn find_function(&self, name: &str) -> Option<usize> {
+        self.analysis
+            .functions
+            .iter()
+            .position(|function| function.name == name)
+    }
+
+    fn reference(&mut self, identifier: &Identifier, symbol: Symbol) {
+        self.analysis.references.push(Reference {
+            span: identifier.span.clone(),
+            symbol,
+        });
+    }
+
+    fn error(&mut self, code: ErrorCode, message: impl Into<String>, span: &Range<usize>) {
+        let start = Position::at(self.text, span.start);
+        let end = Position::at(self.text, span.end);
+        self.analysis
+            .diagnostics
+            .push(Diagnostic::error(message, start, end).with_code(code));
+    }
+}
+
+fn is_number(ty: Type) -> bool {
+    matches!(ty, Type::Int | Type::Float)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ast::Type;
+    use crate::checker::{self, check, Symbol};
+    use crate::error_code::ErrorCode;
+    use crate::parser::C1Parser;
+
+    fn codes(text: &str) -> Vec<ErrorCode> {
+        checker::diagnostics(text)
+            .into_iter()
+            .filter_map(|diagnostic| diagnostic.code)
+            .collect()
+    }
+
+    #[test]
+    fn example_is_valid() {
+        let text = include_str!("../tests/data/beispiel.c-1");
+        let analysis = check(text, &C1Parser::parse_ast(text).unwrap());
+        assert!(
+            analysis.diagnostics.is_empty(),
+            "{:?}",
+            analysis.diagnostics
+        );
+        assert_eq!(analysis.functions.len(), 3);
+        // a and b exist in blah and in main
+        assert_eq!(analysis.variables.len(), 8);
+        let blub = analysis.references(Symbol::Function(0));
+        assert_eq!(blub.len(), 6);
+        assert_eq!(analysis.signature(Symbol::Function(1)), "float blah()");
+    }
+
+    #[test]
+    fn variables_are_resolved_per_function() {
+        let text = "int f() { x = 1; return x; } bool g() { x = true; return x; }";
+        let analysis = check(text, &C1Parser::parse_ast(text).unwrap());
+        assert_eq!(analysis.symbol_at(24), Some(Symbol::Variable(0)));
+        assert_eq!(analysis.symbol_at(57), Some(Symbol::Variable(1)));
+        assert_eq!(analysis.signature(Symbol::Variable(1)), "bool x");
+        assert_eq!(
+            analysis.references(Symbol::Variable(0)),
+            vec![10..11, 24..25]
+        );
+    }
+
+    #[test]
+    fn types_are_inferred() {
+        let text = "void f() { x = 1 + 2.5; y = (x < 3) && true; }";
+        let analysis = check(text, &C1Parser::parse_ast(text).unwrap());
+        assert!(analysis.diagnostics.is_empty());
+        assert_eq!(analysis.type_at(15), Some(Type::Int));
+        assert_eq!(analysis.type_at(17), Some(Type::Float));
+        assert_eq!(analysis.signature(Symbol::Variable(1)), "bool y");
+    }
+
+    #[test]
+    fn semantic_errors() {
+        assert_eq!(
+            codes("void f() { f(); g(); }"),
+            vec![ErrorCode::UndefinedFunction]
+        );
+        assert_eq!(
+            codes("void f() { x = x + 1; }"),
+            vec![ErrorCode::UndefinedVariable]
+        );
+        assert_eq!(
+            codes("void f() {} int f() {}"),
+            vec![ErrorCode::DuplicateFunction]
+        );
+        assert_eq!(
+            codes("int f() { return; }"),
+            vec![ErrorCode::MismatchedTypes]
+        );
+        assert_eq!(
+            codes("void f() { x = 1; x = true; }"),
+            vec![ErrorCode::MismatchedTypes]
+        );
+        assert_eq!(codes("void f() { x = 1.5; x = 1; }"), vec![]);
+        assert_eq!(codes("void f() { if (1) printf(f()); }").len(), 2);
+        assert_eq!(codes("void f() { printf(1 + true == 2); }").len(), 1);
+        assert_eq!(codes("void f() { printf(true == 2); }").len(), 1);
+    }
+}
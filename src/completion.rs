@@ -0,0 +1,259 @@
+use crate::checker::{self, Symbol};
+use crate::lexer::{C1Lexer, C1Token};
+use crate::parser::C1Parser;
+
+const TYPE_KEYWORDS: [&str; 4] = ["bool", "float", "int", "void"];
+const STATEMENT_KEYWORDS: [&str; 3] = ["if", "printf", "return"];
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+pub enum CompletionKind {
+    Function,
+    Variable,
+    Type,
+    Keyword,
+}
+
+/// A candidate for the identifier or keyword at the cursor
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Completion {
+    pub label: String,
+    pub kind: CompletionKind,
+}
+
+/// The syntactic position of the cursor
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+enum Context {
+    /// Where a function definition can start
+    TopLevel,
+    /// Where a statement can start
+    Statement,
+    /// Where an operand of an expression can start
+    Expression,
+    /// Where no identifier or keyword can be completed, e.g. after a constant
+    None,
+}
+
+/// # Overview
+/// Return the completion candidates for the given byte offset in the text. The identifier that
+/// ends at the cursor, if any, is used as prefix that all candidates must start with.
+///
+/// At the start of a top-level item, the type keywords are proposed. At the start of a statement,
+/// the statement keywords, the functions of the program and the variables of the current function
+/// are proposed. Inside of expressions, functions and variables are proposed. A variable is only
+/// proposed if it has been assigned before the cursor.
+///
+/// The text does not have to be a valid program: the program is parsed with error recovery, so
+/// the functions and variables of everything that can be parsed are known.
+///
+/// # Examples
+/// ```
+/// use cb_3::completion::complete;
+///
+/// let text = "int number() { return 42; }\nvoid main() { nice = 1; x = n";
+/// let labels: Vec<String> = complete(text, text.len()).into_iter().map(|c| c.label).collect();
+/// assert_eq!(labels, vec!["nice", "number"]);
+/// ```
+pub fn complete(text: &str, offset: usize) -> Vec<Completion> {
+    let start = text[..offset]
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| c.is_alphanumeric() || *c == '_')
+        .last()
+        .map_or(offset, |(index, _)| index);
+    let prefix = &text[start..offset];
+    if in_comment(text, start) {
+        return Vec::new();
+    }
+
+    let mut completions = Vec::new();
+    let context = context(&text[..start]);
+    match context {
+        Context::TopLevel => completions.extend(keywords(&TYPE_KEYWORDS, CompletionKind::Type)),
+        Context::Statement => {
+            completions.extend(keywords(&STATEMENT_KEYWORDS, CompletionKind::Keyword))
+        }
+        Context::Expression | Context::None => {}
+    }
+    if matches!(context, Context::Statement | Context::Expression) {
+        completions.extend(symbols(text, start));
+    }
+    completions.retain(|completion| completion.label.starts_with(prefix));
+    completions
+}
+
+fn keywords(keywords: &[&str], kind: CompletionKind) -> Vec<Completion> {
+    keywords
+        .iter()
+        .map(|keyword| Completion {
+            label: keyword.to_string(),
+            kind,
+        })
+        .collect()
+}
+
+/// Return the variables that have been assigned before the offset in the function that contains
+/// it, followed by all functions, each group sorted by name
+fn symbols(text: &str, offset: usize) -> Vec<Completion> {
+    let (program, _) = C1Parser::parse_recovering(text);
+    let analysis = checker::check(text, &program);
+    // The function that contains the offset is the last one that starts before it, because the
+    // closing brace may still be missing
+    let current = program
+        .functions
+        .iter()
+        .rposition(|function| function.span.start < offset)
+        .and_then(|index| analysis.symbol_at(program.functions[index].name.span.start));
+
+    let mut variables: Vec<Completion> = analysis
+        .variables
+        .iter()
+        .filter(|variable| {
+            Some(Symbol::Function(variable.function)) == current && variable.span.end <= offset
+        })
+        .map(|variable| Completion {
+            label: variable.name.clone(),
+            kind: CompletionKind::Variable,
+        })
+        .collect();
+    variables.sort_by(|a, b| a.label.cmp(&b.label));
+    let mut functions: Vec<Completion> = analysis
+        .functions
+        .iter()
+        .map(|function| Completion {
+            label: function.name.clone(),
+            kind: CompletionKind::Function,
+        })
+        .collect();
+    functions.sort_by(|a, b| a.label.cmp(&b.label));
+    variables.extend(functions);
+    variables
+}
+
+/// Determine the context from the tokens in front of the cursor
+fn context(before: &str) -> Context {
+    let mut lexer = C1Lexer::new(before);
+    let mut depth = 0;
+    // For every open parenthesis, whether it encloses the condition of an if statement
+    let mut parentheses = Vec::new();
+    let mut previous = None;
+    let mut closes_condition = false;
+    while let Some(token) = lexer.current_token() {
+        closes_condition = false;
+        match token {
+            C1Token::LeftBrace => depth += 1,
+            C1Token::RightBrace => depth -= 1,
+            C1Token::LeftParenthesis => parentheses.push(previous == Some(C1Token::KwIf)),
+            C1Token::RightParenthesis => closes_condition = parentheses.pop().unwrap_or(false),
+            _ => {}
+        }
+        previous = Some(token);
+        lexer.eat();
+    }
+
+    if depth <= 0 {
+        return match previous {
+            None | Some(C1Token::RightBrace) => Context::TopLevel,
+            _ => Context::None,
+        };
+    }
+    match previous {
+        Some(C1Token::LeftBrace | C1Token::RightBrace | C1Token::Semicolon) => Context::Statement,
+        Some(C1Token::RightParenthesis) if closes_condition => Context::Statement,
+        Some(
+            C1Token::KwReturn
+            | C1Token::LeftParenthesis
+            | C1Token::Assign
+            | C1Token::Equal
+            | C1Token::NotEqual
+            | C1Token::Less
+            | C1Token::Greater
+            | C1Token::LessEqual
+            | C1Token::GreaterEqual
+            | C1Token::Plus
+            | C1Token::Minus
+            | C1Token::Asterisk
+            | C1Token::Slash
+            | C1Token::And
+            | C1Token::Or,
+        ) => Context::Expression,
+        _ => Context::None,
+    }
+}
+
+/// Check whether the offset lies inside of a comment. The end of a line comment still belongs to
+/// the comment.
+fn in_comment(text: &str, offset: usize) -> bool {
+    let mut lexer = C1Lexer::with_trivia(text);
+    while let (Some(token), Some(span)) = (lexer.current_token(), lexer.current_span()) {
+        if span.start >= offset {
+            return false;
+        }
+        let inside = match token {
+            C1Token::CComment => offset < span.end,
+            C1Token::CPPComment => offset < span.end || !text[span.clone()].ends_with('\n'),
+            _ => false,
+        };
+        if inside {
+            return true;
+        }
+        lexer.eat();
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::completion::{complete, CompletionKind};
+
+    /// Complete at the position of `|` and return the labels
+    fn labels(text: &str) -> Vec<String> {
+        let offset = text.find('|').unwrap();
+        let text = text.replace('|', "");
+        complete(&text, offset)
+            .into_iter()
+            .map(|completion| completion.label)
+            .collect()
+    }
+
+    #[test]
+    fn type_keywords_at_top_level() {
+        assert_eq!(labels("|"), vec!["bool", "float", "int", "void"]);
+        assert_eq!(labels("void f() {}\nf|"), vec!["float"]);
+        assert!(labels("int |").is_empty());
+    }
+
+    #[test]
+    fn statement_starts() {
+        let text = "int one() { return 1; }\nvoid main() {\n  a = 1;\n  |\n  b = 2;\n}";
+        assert_eq!(
+            labels(text),
+            vec!["if", "printf", "return", "a", "main", "one"]
+        );
+        let text = "void main() { a = 1; if (a < 2) r| }";
+        assert_eq!(labels(text), vec!["return"]);
+    }
+
+    #[test]
+    fn expressions_in_incomplete_input() {
+        let text = "int blub() { return 1; }\nvoid main() {\n  blah = 2;\n  x = (bl|";
+        assert_eq!(labels(text), vec!["blah", "blub"]);
+        let text = "void f() { x = 1; }\nvoid g() { y = 1; printf(|); }";
+        assert_eq!(labels(text), vec!["y", "f", "g"]);
+        assert!(labels("void f() { x = 1| }").is_empty());
+        assert!(labels("void f() { x = 1; // |\n}").is_empty());
+    }
+
+    #[test]
+    fn multi_byte_prefixes() {
+        assert!(labels("void f() { printf(éx|").is_empty());
+        assert!(labels("void f() { blah = 1; x = blä|").is_empty());
+        assert_eq!(labels("void f() { _a = 1; x = ä + _|"), vec!["_a"]);
+    }
+
+    #[test]
+    fn kinds() {
+        let completions = complete("void f() { x = 1; x = ", 22);
+        assert_eq!(completions[0].kind, CompletionKind::Variable);
+        assert_eq!(completions[1].kind, CompletionKind::Function);
+    }
+}
@@ -0,0 +1,480 @@
+use crate::diagnostic::Diagnostic;
+use crate::lexer::{C1Lexer, C1Token};
+use crate::parser::{C1Parser, Event, Rule};
+use std::fmt::Write;
+use std::ops::Range;
+use std::rc::Rc;
+
+/// # Overview
+/// Lossless concrete syntax tree. In contrast to the abstract syntax tree, the concrete syntax tree
+/// contains one node per applied grammar rule and every token of the text, including whitespace,
+/// linebreaks and comments. The text of the tree is always identical to the parsed text, even if it
+/// contains syntax errors.
+///
+/// The tree is split into two layers: the green tree is immutable, knows only the lengths of its
+/// elements and can be shared between trees, the red tree ([`SyntaxNode`], [`SyntaxToken`]) is built
+/// on demand on top of it and adds absolute offsets and parent pointers.
+///
+/// # Examples
+/// ```
+/// use cb_3::cst::{self, NodeKind};
+/// use cb_3::parser::Rule;
+///
+/// let text = "void main() {\n  // say hi\n  printf(1);\n}\n";
+/// let parse = cst::parse(text);
+/// assert!(parse.errors.is_empty());
+/// assert_eq!(parse.root.text(), text);
+///
+/// let function = &parse.root.child_nodes()[0];
+/// assert_eq!(function.kind(), NodeKind::Rule(Rule::FunctionDefinition));
+/// assert_eq!(function.text_range(), 0..40);
+/// ```
+pub fn parse(text: &str) -> Parse {
+    let (result, events) = C1Parser::parse_with_events(text);
+    let mut builder = TreeBuilder {
+        lexer: C1Lexer::with_trivia(text),
+        stack: Vec::new(),
+    };
+    for event in events {
+        match event {
+            Event::Enter(rule) => {
+                // Trivia in front of a node belongs to its parent
+                if !builder.stack.is_empty() {
+                    builder.trivia();
+                }
+                builder.stack.push((NodeKind::Rule(rule), Vec::new()));
+            }
+            Event::Token { span, .. } => {
+                builder.trivia();
+                debug_assert_eq!(builder.lexer.current_span(), Some(span));
+                builder.token();
+            }
+            // The root node is closed after all remaining tokens have been added
+            Event::Exit(Rule::Program) => {}
+            Event::Exit(_) => builder.finish_node(),
+        }
+    }
+    if builder.stack.is_empty() {
+        builder
+            .stack
+            .push((NodeKind::Rule(Rule::Program), Vec::new()));
+    }
+
+    // After a syntax error, everything that has not been parsed is collected in an error node
+    builder.trivia();
+    if builder.lexer.current_token().is_some() {
+        builder.stack.push((NodeKind::Error, Vec::new()));
+        while builder.lexer.current_token().is_some() {
+            builder.token();
+        }
+    }
+    while builder.stack.len() > 1 {
+        builder.finish_node();
+    }
+    let (kind, children) = builder.stack.pop().unwrap();
+    Parse {
+        root: SyntaxNode::new_root(Rc::new(GreenNode::new(kind, children))),
+        errors: result
+            .err()
+            .into_iter()
+            .map(|diagnostic| *diagnostic)
+            .collect(),
+    }
+}
+
+/// The result of parsing a text into a concrete syntax tree
+pub struct Parse {
+    pub root: SyntaxNode,
+    pub errors: Vec<Diagnostic>,
+}
+
+/// Builds the green tree from the parser events and the tokens of a lexer that keeps trivia
+struct TreeBuilder<'a> {
+    lexer: C1Lexer<'a>,
+    stack: Vec<(NodeKind, Vec<GreenElement>)>,
+}
+
+impl<'a> TreeBuilder<'a> {
+    /// Add the current token to the innermost open node
+    fn token(&mut self) {
+        let token = GreenToken {
+            kind: self.lexer.current_token().unwrap(),
+            text: self.lexer.current_text().unwrap().to_owned(),
+        };
+        self.stack
+            .last_mut()
+            .unwrap()
+            .1
+            .push(GreenElement::Token(Rc::new(token)));
+        self.lexer.eat();
+    }
+
+    /// Add all trivia tokens up to the next significant token to the innermost open node
+    fn trivia(&mut self) {
+        while self
+            .lexer
+            .current_token()
+            .is_some_and(|token| token.is_trivia())
+        {
+            self.token();
+        }
+    }
+
+    /// Close the innermost open node and add it to its parent
+    fn finish_node(&mut self) {
+        let (kind, children) = self.stack.pop().unwrap();
+        let node = GreenElement::Node(Rc::new(GreenNode::new(kind, children)));
+        self.stack.last_mut().unwrap().1.push(node);
+    }
+}
+
+/// The kind of an inner node of the concrete syntax tree
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+pub enum NodeKind {
+    /// A successfully or partially applied grammar rule
+    Rule(Rule),
+    /// Tokens that could not be parsed because of a syntax error
+    Error,
+}
+
+impl NodeKind {
+    /// Return the name of the node kind, i.e. the name of the rule or "error"
+    pub fn name(&self) -> &'static str {
+        match self {
+            NodeKind::Rule(rule) => rule.name(),
+            NodeKind::Error => "error",
+        }
+    }
+}
+
+/// Immutable inner node that only knows the length of its text
+#[derive(Debug, PartialEq, Eq)]
+pub struct GreenNode {
+    kind: NodeKind,
+    text_len: usize,
+    children: Vec<GreenElement>,
+}
+
+impl GreenNode {
+    pub fn new(kind: NodeKind, children: Vec<GreenElement>) -> GreenNode {
+        let text_len = children.iter().map(GreenElement::text_len).sum();
+        GreenNode {
+            kind,
+            text_len,
+            children,
+        }
+    }
+
+    pub fn kind(&self) -> NodeKind {
+        self.kind
+    }
+
+    pub fn text_len(&self) -> usize {
+        self.text_len
+    }
+
+    pub fn children(&self) -> &[GreenElement] {
+        &self.children
+    }
+}
+
+/// Immutable token that only knows its text
+#[derive(Debug, PartialEq, Eq)]
+pub struct GreenToken {
+    kind: C1Token,
+    text: String,
+}
+
+impl GreenToken {
+    pub fn new(kind: C1Token, text: String) -> GreenToken {
+        GreenToken { kind, text }
+    }
+
+    pub fn kind(&self) -> C1Token {
+        self.kind
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum GreenElement {
+    Node(Rc<GreenNode>),
+    Token(Rc<GreenToken>),
+}
+
+impl GreenElement {
+    pub fn text_len(&self) -> usize {
+        match self {
+            GreenElement::Node(node) => node.text_len,
+            GreenElement::Token(token) => token.text.len(),
+        }
+    }
+}
+
+/// Inner node of the concrete syntax tree with its absolute position in the text
+#[derive(Debug, Clone)]
+pub struct SyntaxNode(Rc<NodeData>);
+
+#[derive(Debug)]
+struct NodeData {
+    green: Rc<GreenNode>,
+    offset: usize,
+    parent: Option<SyntaxNode>,
+}
+
+/// Token of the concrete syntax tree with its absolute position in the text
+#[derive(Debug, Clone)]
+pub struct SyntaxToken {
+    green: Rc<GreenToken>,
+    offset: usize,
+    parent: SyntaxNode,
+}
+
+#[derive(Debug, Clone)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+impl SyntaxNode {
+    /// Create the red root node for a green tree
+    pub fn new_root(green: Rc<GreenNode>) -> SyntaxNode {
+        SyntaxNode(Rc::new(NodeData {
+            green,
+            offset: 0,
+            parent: None,
+        }))
+    }
+
+    pub fn kind(&self) -> NodeKind {
+        self.0.green.kind
+    }
+
+    pub fn green(&self) -> &Rc<GreenNode> {
+        &self.0.green
+    }
+
+    pub fn parent(&self) -> Option<SyntaxNode> {
+        self.0.parent.clone()
+    }
+
+    /// Return the byte range of the node in the text
+    pub fn text_range(&self) -> Range<usize> {
+        self.0.offset..self.0.offset + self.0.green.text_len
+    }
+
+    /// Return the text of the node, including all trivia
+    pub fn text(&self) -> String {
+        self.tokens().iter().map(SyntaxToken::text).collect()
+    }
+
+    /// Return the direct children of the node
+    pub fn children(&self) -> Vec<SyntaxElement> {
+        let mut offset = self.0.offset;
+        self.0
+            .green
+            .children
+            .iter()
+            .map(|child| {
+                let element = match child {
+                    GreenElement::Node(green) => {
+                        SyntaxElement::Node(SyntaxNode(Rc::new(NodeData {
+                            green: green.clone(),
+                            offset,
+                            parent: Some(self.clone()),
+                        })))
+                    }
+                    GreenElement::Token(green) => SyntaxElement::Token(SyntaxToken {
+                        green: green.clone(),
+                        offset,
+                        parent: self.clone(),
+                    }),
+                };
+                offset += child.text_len();
+                element
+            })
+            .collect()
+    }
+
+    /// Return the direct children of the node that are nodes themselves
+    pub fn child_nodes(&self) -> Vec<SyntaxNode> {
+        self.children()
+            .into_iter()
+            .filter_map(|child| match child {
+                SyntaxElement::Node(node) => Some(node),
+                SyntaxElement::Token(_) => None,
+            })
+            .collect()
+    }
+
+    /// Return all tokens in the subtree of the node in text order
+    pub fn tokens(&self) -> Vec<SyntaxToken> {
+        let mut tokens = Vec::new();
+        for child in self.children() {
+            match child {
+                SyntaxElement::Node(node) => tokens.extend(node.tokens()),
+                SyntaxElement::Token(token) => tokens.push(token),
+            }
+        }
+        tokens
+    }
+
+    /// Return the token that contains the given byte offset
+    pub fn token_at_offset(&self, offset: usize) -> Option<SyntaxToken> {
+        for child in self.children() {
+            match child {
+                SyntaxElement::Node(node) if node.text_range().contains(&offset) => {
+                    return node.token_at_offset(offset)
+                }
+                SyntaxElement::Token(token) if token.text_range().contains(&offset) => {
+                    return Some(token)
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// Return an indented representation of the tree with one node or token per line
+    pub fn debug_tree(&self) -> String {
+        let mut output = String::new();
+        self.write_tree(&mut output, 0);
+        output
+    }
+
+    fn write_tree(&self, output: &mut String, depth: usize) {
+        let range = self.text_range();
+        writeln!(
+            output,
+            "{}{}@{}..{}",
+            "  ".repeat(depth),
+            self.kind().name(),
+            range.start,
+            range.end
+        )
+        .unwrap();
+        for child in self.children() {
+            match child {
+                SyntaxElement::Node(node) => node.write_tree(output, depth + 1),
+                SyntaxElement::Token(token) => {
+                    let range = token.text_range();
+                    writeln!(
+                        output,
+                        "{}{:?}@{}..{} {:?}",
+                        "  ".repeat(depth + 1),
+                        token.kind(),
+                        range.start,
+                        range.end,
+                        token.text()
+                    )
+                    .unwrap();
+                }
+            }
+        }
+    }
+}
+
+impl SyntaxToken {
+    pub fn kind(&self) -> C1Token {
+        self.green.kind
+    }
+
+    pub fn text(&self) -> &str {
+        &self.green.text
+    }
+
+    /// Return the byte range of the token in the text
+    pub fn text_range(&self) -> Range<usize> {
+        self.offset..self.offset + self.green.text.len()
+    }
+
+    /// Return the innermost node that contains the token
+    pub fn parent(&self) -> SyntaxNode {
+        self.parent.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cst::{parse, NodeKind};
+    use crate::lexer::C1Token;
+    use crate::parser::Rule;
+
+    #[test]
+    fn example_is_lossless() {
+        let text = include_str!("../tests/data/beispiel.c-1");
+        let parse = parse(text);
+        assert!(parse.errors.is_empty());
+        assert_eq!(parse.root.text(), text);
+        assert_eq!(parse.root.text_range(), 0..text.len());
+        assert_eq!(parse.root.child_nodes().len(), 3);
+    }
+
+    #[test]
+    fn tree_structure() {
+        let parse = parse("int f() { x = 1; } // end");
+        assert_eq!(
+            parse.root.debug_tree(),
+            r#"program@0..25
+  function_definition@0..18
+    type@0..3
+      KwInt@0..3 "int"
+    Whitespace@3..4 " "
+    Identifier@4..5 "f"
+    LeftParenthesis@5..6 "("
+    RightParenthesis@6..7 ")"
+    Whitespace@7..8 " "
+    LeftBrace@8..9 "{"
+    Whitespace@9..10 " "
+    statement_list@10..16
+      block@10..16
+        statement@10..16
+          stat_assignment@10..15
+            Identifier@10..11 "x"
+            Whitespace@11..12 " "
+            Assign@12..13 "="
+            Whitespace@13..14 " "
+            assignment@14..15
+              expr@14..15
+                simp_expr@14..15
+                  term@14..15
+                    factor@14..15
+                      ConstInt@14..15 "1"
+          Semicolon@15..16 ";"
+    Whitespace@16..17 " "
+    RightBrace@17..18 "}"
+  Whitespace@18..19 " "
+  CPPComment@19..25 "// end"
+"#
+        );
+    }
+
+    #[test]
+    fn syntax_errors_are_lossless() {
+        let text = "void f() {\n  x = ;\n  /* rest */ y = 2;\n}\n\nint g() {}";
+        let parse = parse(text);
+        assert_eq!(parse.errors.len(), 1);
+        assert_eq!(parse.root.text(), text);
+        let error = parse
+            .root
+            .tokens()
+            .into_iter()
+            .find(|token| token.text() == "y")
+            .unwrap()
+            .parent();
+        assert_eq!(error.kind(), NodeKind::Error);
+    }
+
+    #[test]
+    fn tokens_are_found_by_offset() {
+        let parse = parse("void main() { printf(42); }");
+        let token = parse.root.token_at_offset(22).unwrap();
+        assert_eq!(token.kind(), C1Token::ConstInt);
+        assert_eq!(token.text_range(), 21..23);
+        assert_eq!(token.parent().kind(), NodeKind::Rule(Rule::Factor));
+        assert!(parse.root.token_at_offset(100).is_none());
+    }
+}
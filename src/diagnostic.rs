@@ -0,0 +1,276 @@
+use crate::error_code::ErrorCode;
+use crate::source_map::{FileId, SourceMap};
+use serde::Serialize;
+use std::fmt;
+
+/// How severe a reported problem is
+#[derive(Serialize, Debug, PartialEq, Eq, Copy, Clone)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Note => write!(f, "note"),
+        }
+    }
+}
+
+/// A location in a source text. Lines and columns start at 1, columns are counted in characters.
+#[derive(Serialize, Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Position {
+    /// Compute the position of the given byte offset in the text. An offset inside of a character
+    /// refers to the character, an offset behind the end of the text to its end.
+    /// ```
+    /// use cb_3::diagnostic::Position;
+    ///
+    /// assert_eq!(Position::at("int\n  x", 6), Position { line: 2, column: 3 });
+    /// ```
+    pub fn at(text: &str, offset: usize) -> Position {
+        let before = &text[..char_boundary(text, offset)];
+        let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+        Position {
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        }
+    }
+
+    /// Compute the byte offset of the position in the text. Columns behind the end of their line
+    /// are clamped to the end of the line, lines behind the end of the text to the end of the
+    /// text. Line and column 0 are treated like 1.
+    /// ```
+    /// use cb_3::diagnostic::Position;
+    ///
+    /// assert_eq!(Position { line: 2, column: 3 }.offset("int\n  x"), 6);
+    /// assert_eq!(Position { line: 1, column: 9 }.offset("int\n  x"), 3);
+    /// ```
+    pub fn offset(&self, text: &str) -> usize {
+        let line_start = if self.line <= 1 {
+            0
+        } else {
+            text.match_indices('\n')
+                .nth(self.line - 2)
+                .map_or(text.len(), |(index, _)| index + 1)
+        };
+        let line_end = text[line_start..]
+            .find('\n')
+            .map_or(text.len(), |index| line_start + index);
+        text[line_start..line_end]
+            .char_indices()
+            .nth(self.column.saturating_sub(1))
+            .map_or(line_end, |(index, _)| line_start + index)
+    }
+}
+
+/// Return the start of the character that contains the byte offset, or the end of the text if
+/// the offset lies behind it
+pub(crate) fn char_boundary(text: &str, offset: usize) -> usize {
+    let mut offset = offset.min(text.len());
+    while !text.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    offset
+}
+
+/// A proposed edit that resolves a diagnostic: the text between `start` and `end` is replaced by
+/// `replacement`.
+#[derive(Serialize, Debug, PartialEq, Eq, Clone)]
+pub struct Fix {
+    pub message: String,
+    pub start: Position,
+    pub end: Position,
+    pub replacement: String,
+}
+
+/// A problem found in a C(-1) source text, e.g. a syntax error reported by the parser
+#[derive(Serialize, Debug, PartialEq, Eq, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: Option<ErrorCode>,
+    pub message: String,
+    pub file: Option<String>,
+    /// The file of a [`SourceMap`] that the positions refer to, see [`SourceMap::span_of`]
+    #[serde(skip)]
+    pub file_id: Option<FileId>,
+    pub start: Position,
+    pub end: Position,
+    pub expected: Vec<String>,
+    pub fixes: Vec<Fix>,
+}
+
+impl Diagnostic {
+    /// Create an error diagnostic covering the given range
+    pub fn error(message: impl Into<String>, start: Position, end: Position) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Error,
+            code: None,
+            message: message.into(),
+            file: None,
+            file_id: None,
+            start,
+            end,
+            expected: Vec::new(),
+            fixes: Vec::new(),
+        }
+    }
+
+    /// Attach the stable error code identifying the kind of problem
+    pub fn with_code(mut self, code: ErrorCode) -> Diagnostic {
+        self.code = Some(code);
+        self
+    }
+
+    /// Attach the name of the file the diagnostic belongs to
+    pub fn in_file(mut self, file: impl Into<String>) -> Diagnostic {
+        self.file = Some(file.into());
+        self
+    }
+
+    /// Attach a file of a source map, whose text the positions refer to, together with its name
+    pub fn in_source_file(mut self, sources: &SourceMap, file: FileId) -> Diagnostic {
+        self.file_id = Some(file);
+        self.in_file(sources.file(file).name())
+    }
+
+    /// Record what would have been accepted at the location of the diagnostic
+    pub fn expecting(mut self, expected: impl Into<String>) -> Diagnostic {
+        self.expected.push(expected.into());
+        self
+    }
+
+    /// Attach a suggested fix
+    pub fn with_fix(mut self, fix: Fix) -> Diagnostic {
+        self.fixes.push(fix);
+        self
+    }
+
+    /// Serialize the diagnostic as a single-line JSON object
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("diagnostics are always serializable")
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}:", file)?;
+        }
+        write!(
+            f,
+            "{}:{}: {}",
+            self.start.line, self.start.column, self.severity
+        )?;
+        if let Some(code) = &self.code {
+            write!(f, "[{}]", code)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+/// Serialize a list of diagnostics as a JSON array
+/// ```
+/// use cb_3::diagnostic::{to_json, Diagnostic, Position};
+///
+/// let position = Position { line: 1, column: 5 };
+/// let json = to_json(&[Diagnostic::error("Invalid factor", position, position)]);
+/// assert!(json.starts_with(r#"[{"severity":"error","code":null,"message":"Invalid factor""#));
+/// ```
+pub fn to_json(diagnostics: &[Diagnostic]) -> String {
+    serde_json::to_string(diagnostics).expect("diagnostics are always serializable")
+}
+
+/// Serialize a list of diagnostics as JSON Lines, i.e. one JSON object per line
+pub fn to_json_lines(diagnostics: &[Diagnostic]) -> String {
+    diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.to_json() + "\n")
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diagnostic::{to_json_lines, Diagnostic, Fix, Position};
+    use crate::error_code::ErrorCode;
+
+    #[test]
+    fn position_of_offset() {
+        assert_eq!(Position::at("", 0), Position { line: 1, column: 1 });
+        assert_eq!(Position::at("ab\ncd", 2), Position { line: 1, column: 3 });
+        assert_eq!(Position::at("ab\ncd", 3), Position { line: 2, column: 1 });
+        assert_eq!(Position::at("äb\nc", 2), Position { line: 1, column: 2 });
+        assert_eq!(Position::at("äb\nc", 1), Position { line: 1, column: 1 });
+        assert_eq!(Position::at("äb\nc", 9), Position { line: 2, column: 2 });
+    }
+
+    #[test]
+    fn offset_of_position() {
+        let offset = |line, column| Position { line, column }.offset("äb\ncd\n");
+        assert_eq!(offset(1, 2), 2);
+        assert_eq!(offset(2, 2), 5);
+        assert_eq!(offset(1, 0), 0);
+        assert_eq!(offset(0, 0), 0);
+        // Columns behind the end of a line stay on the line
+        assert_eq!(offset(1, 4), 3);
+        assert_eq!(offset(1, 99), 3);
+        assert_eq!(offset(2, 3), 6);
+        assert_eq!(offset(3, 5), 7);
+        assert_eq!(offset(9, 1), 7);
+    }
+
+    #[test]
+    fn json_schema_is_stable() {
+        let start = Position { line: 2, column: 7 };
+        let end = Position { line: 2, column: 8 };
+        let diagnostic = Diagnostic::error("Expected ';' after statement", start, end)
+            .with_code(ErrorCode::MissingSemicolon)
+            .in_file("main.c-1")
+            .expecting("\";\"")
+            .with_fix(Fix {
+                message: String::from("insert ';'"),
+                start,
+                end: start,
+                replacement: String::from(";"),
+            });
+        assert_eq!(
+            diagnostic.to_json(),
+            r#"{"severity":"error","code":"E0001","message":"Expected ';' after statement","file":"main.c-1","#.to_owned()
+                + r#""start":{"line":2,"column":7},"end":{"line":2,"column":8},"expected":["\";\""],"#
+                + r#""fixes":[{"message":"insert ';'","start":{"line":2,"column":7},"end":{"line":2,"column":7},"replacement":";"}]}"#
+        );
+    }
+
+    #[test]
+    fn json_lines_has_one_object_per_line() {
+        let position = Position { line: 1, column: 1 };
+        let diagnostics = vec![
+            Diagnostic::error("first", position, position),
+            Diagnostic::error("second", position, position),
+        ];
+        let lines = to_json_lines(&diagnostics);
+        assert_eq!(lines.lines().count(), 2);
+        assert!(lines.ends_with('\n'));
+    }
+
+    #[test]
+    fn display_contains_location() {
+        let start = Position { line: 3, column: 4 };
+        let diagnostic = Diagnostic::error("Invalid factor", start, start).in_file("a.c-1");
+        assert_eq!(diagnostic.to_string(), "a.c-1:3:4: error: Invalid factor");
+        let diagnostic = diagnostic.with_code(ErrorCode::InvalidFactor);
+        assert_eq!(
+            diagnostic.to_string(),
+            "a.c-1:3:4: error[E0002]: Invalid factor"
+        );
+    }
+}
@@ -0,0 +1,177 @@
+use crate::checker;
+use crate::minimizer;
+use std::fmt;
+
+/// What a run of a program can be observed to do
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Execution {
+    pub stdout: String,
+    pub exit: i32,
+}
+
+/// An engine that executes C(-1) programs, e.g. an interpreter or a compiler together with the
+/// execution of the compiled program
+pub trait Backend {
+    fn name(&self) -> &str;
+
+    /// Run the program, or return why the backend failed to run it
+    fn run(&self, text: &str) -> Result<Execution, String>;
+}
+
+/// Return every backend that is available in this build. No execution backend has been
+/// implemented yet, so the list is empty, and [`compare`] accepts every program.
+pub fn backends() -> Vec<Box<dyn Backend>> {
+    Vec::new()
+}
+
+/// A program for which the backends disagree, together with the result of every backend
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Discrepancy {
+    pub program: String,
+    pub results: Vec<(String, Result<Execution, String>)>,
+}
+
+impl fmt::Display for Discrepancy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "The backends disagree on this program:")?;
+        writeln!(f, "{}", self.program.trim_end())?;
+        for (name, result) in &self.results {
+            match result {
+                Ok(execution) => {
+                    writeln!(f, "{} exited with {}, output:", name, execution.exit)?;
+                    write!(f, "{}", execution.stdout)?;
+                    if !execution.stdout.is_empty() && !execution.stdout.ends_with('\n') {
+                        writeln!(f)?;
+                    }
+                }
+                Err(error) => writeln!(f, "{} failed: {}", name, error)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// # Overview
+/// Run the program with every backend and check that they all produce the same output and exit
+/// value. If they disagree, the discrepancy is reported for the smallest valid program found by
+/// [`minimizer::reduce`] on which they still disagree.
+///
+/// # Examples
+/// ```
+/// use cb_3::differential::{self, Backend, Execution};
+///
+/// struct Silent;
+///
+/// impl Backend for Silent {
+///     fn name(&self) -> &str {
+///         "silent"
+///     }
+///
+///     fn run(&self, _: &str) -> Result<Execution, String> {
+///         Ok(Execution { stdout: String::new(), exit: 0 })
+///     }
+/// }
+///
+/// let backends: Vec<Box<dyn Backend>> = vec![Box::new(Silent), Box::new(Silent)];
+/// let execution = differential::compare("void main() { printf(1); }", &backends).unwrap();
+/// assert_eq!(execution, Some(Execution { stdout: String::new(), exit: 0 }));
+/// ```
+pub fn compare(
+    text: &str,
+    backends: &[Box<dyn Backend>],
+) -> Result<Option<Execution>, Box<Discrepancy>> {
+    let results = run_all(text, backends);
+    if agree(&results) {
+        return Ok(results
+            .into_iter()
+            .next()
+            .and_then(|(_, result)| result.ok()));
+    }
+    let program = minimizer::reduce(text, |candidate| {
+        checker::diagnostics(candidate).is_empty() && !agree(&run_all(candidate, backends))
+    });
+    Err(Box::new(Discrepancy {
+        results: run_all(&program, backends),
+        program,
+    }))
+}
+
+fn run_all(text: &str, backends: &[Box<dyn Backend>]) -> Vec<(String, Result<Execution, String>)> {
+    backends
+        .iter()
+        .map(|backend| (backend.name().to_owned(), backend.run(text)))
+        .collect()
+}
+
+fn agree(results: &[(String, Result<Execution, String>)]) -> bool {
+    results.windows(2).all(|pair| pair[0].1 == pair[1].1)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::differential::{compare, Backend, Execution};
+
+    /// Print one line for every printf, like a backend would for constant arguments
+    struct Printer {
+        /// Skip the printf statements that contain this text
+        skip: Option<&'static str>,
+    }
+
+    impl Backend for Printer {
+        fn name(&self) -> &str {
+            match self.skip {
+                Some(_) => "broken",
+                None => "printer",
+            }
+        }
+
+        fn run(&self, text: &str) -> Result<Execution, String> {
+            let stdout = text
+                .split("printf(")
+                .skip(1)
+                .filter_map(|rest| rest.split_once(')'))
+                .map(|(argument, _)| argument)
+                .filter(|argument| self.skip.is_none_or(|skip| !argument.contains(skip)))
+                .map(|argument| format!("{}\n", argument))
+                .collect();
+            Ok(Execution { stdout, exit: 0 })
+        }
+    }
+
+    #[test]
+    fn minimizes_discrepancies() {
+        let backends: Vec<Box<dyn Backend>> = vec![
+            Box::new(Printer { skip: None }),
+            Box::new(Printer { skip: Some("true") }),
+        ];
+        let text = "\
+int f() {
+    printf(1);
+    return 2;
+}
+
+void main() {
+    x = 1;
+    printf(x);
+    if (x < 2) {
+        printf(true && false);
+        printf(f());
+    }
+}
+";
+        assert_eq!(
+            compare("void main() { printf(1); }", &backends),
+            Ok(Some(Execution {
+                stdout: String::from("1\n"),
+                exit: 0
+            }))
+        );
+        let discrepancy = compare(text, &backends).unwrap_err();
+        let program: Vec<&str> = discrepancy.program.split_whitespace().collect();
+        assert_eq!(program.join(" "), "void main() { { printf(true); } }");
+        assert_eq!(discrepancy.results[1].1.as_ref().unwrap().stdout, "");
+        assert!(discrepancy
+            .to_string()
+            .contains("printer exited with 0, output:\ntrue\n"));
+    }
+}
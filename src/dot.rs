@@ -0,0 +1,354 @@
+use crate::ast::{
+    Expression, ExpressionKind, FunctionDefinition, Identifier, Program, Statement, StatementKind,
+};
+use crate::diagnostic::Position;
+use crate::parser::Event;
+use std::fmt::Write;
+use std::ops::Range;
+
+/// Source text in node labels is shortened to this number of characters
+const MAX_SNIPPET_LENGTH: usize = 32;
+
+/// # Overview
+/// Render the derivation tree recorded by [`C1Parser::parse_with_events`] in the DOT language of
+/// Graphviz. Every invocation of a grammar rule becomes a node labelled with the name of the rule,
+/// the source text it derived and its lines. The tokens are the leaves of the tree.
+///
+/// After a syntax error the events are incomplete, and the tree shows the derivation up to the
+/// error.
+///
+/// [`C1Parser::parse_with_events`]: crate::parser::C1Parser::parse_with_events
+///
+/// # Examples
+/// ```
+/// use cb_3::dot;
+/// use cb_3::parser::C1Parser;
+///
+/// let text = "void main() { x = 1; }";
+/// let (_, events) = C1Parser::parse_with_events(text);
+/// let graph = dot::derivation(text, &events);
+/// assert!(graph.starts_with("digraph derivation {\n"));
+/// assert!(graph.contains("label=\"stat_assignment\\n\\\"x = 1\\\"\\nline 1\"];"));
+/// ```
+pub fn derivation(text: &str, events: &[Event]) -> String {
+    // Rule invocations with their span, and tokens, in the order they appear in the events
+    let mut nodes: Vec<(String, Option<Range<usize>>, bool)> = Vec::new();
+    let mut edges = Vec::new();
+    let mut stack: Vec<usize> = Vec::new();
+    for event in events {
+        match event {
+            Event::Enter(rule) => {
+                if let Some(&parent) = stack.last() {
+                    edges.push((parent, nodes.len()));
+                }
+                stack.push(nodes.len());
+                nodes.push((rule.name().to_owned(), None, false));
+            }
+            Event::Token { span, .. } => {
+                for &index in &stack {
+                    let node_span = nodes[index].1.get_or_insert(span.clone());
+                    node_span.end = span.end;
+                }
+                if let Some(&parent) = stack.last() {
+                    edges.push((parent, nodes.len()));
+                }
+                nodes.push((text[span.clone()].to_owned(), Some(span.clone()), true));
+            }
+            Event::Exit(_) => {
+                stack.pop();
+            }
+        }
+    }
+
+    let mut graph = Graph::new("derivation");
+    for (title, span, is_token) in nodes {
+        if is_token {
+            graph.node(&escape(&title), "box");
+        } else {
+            graph.node(&label(text, &title, span), "ellipse");
+        }
+    }
+    for (from, to) in edges {
+        graph.edge(from, to);
+    }
+    graph.finish()
+}
+
+/// Render the abstract syntax tree in the DOT language of Graphviz. Every function, statement,
+/// expression and identifier becomes a node labelled with its kind or operator, its source text
+/// and its lines.
+/// ```
+/// use cb_3::dot;
+/// use cb_3::parser::C1Parser;
+///
+/// let text = "int f() {\n  return 1 + x;\n}";
+/// let program = C1Parser::parse_ast(text).unwrap();
+/// let graph = dot::ast(text, &program);
+/// assert!(graph.contains("label=\"int f()\\n\\\"int f() { return 1 + x; }\\\"\\nlines 1-3\"];"));
+/// assert!(graph.contains("label=\"+\\n\\\"1 + x\\\"\\nline 2\"];"));
+/// assert!(graph.contains("label=\"x\\nline 2\"];"));
+/// ```
+pub fn ast(text: &str, program: &Program) -> String {
+    let mut writer = AstWriter {
+        text,
+        graph: Graph::new("ast"),
+    };
+    let root = writer.graph.node("program", "ellipse");
+    for function in &program.functions {
+        let child = writer.function(function);
+        writer.graph.edge(root, child);
+    }
+    writer.graph.finish()
+}
+
+struct AstWriter<'a> {
+    text: &'a str,
+    graph: Graph,
+}
+
+impl AstWriter<'_> {
+    /// Add a node with the label for the given title and span
+    fn node(&mut self, title: &str, span: &Range<usize>) -> usize {
+        let label = label(self.text, title, Some(span.clone()));
+        self.graph.node(&label, "ellipse")
+    }
+
+    fn function(&mut self, function: &FunctionDefinition) -> usize {
+        let title = format!("{} {}()", function.return_type, function.name.name);
+        let node = self.node(&title, &function.span);
+        for statement in &function.body {
+            let child = self.statement(statement);
+            self.graph.edge(node, child);
+        }
+        node
+    }
+
+    fn statement(&mut self, statement: &Statement) -> usize {
+        let span = &statement.span;
+        match &statement.kind {
+            StatementKind::Block(statements) => {
+                let node = self.node("block", span);
+                for statement in statements {
+                    let child = self.statement(statement);
+                    self.graph.edge(node, child);
+                }
+                node
+            }
+            StatementKind::If { condition, body } => {
+                let node = self.node("if", span);
+                let condition = self.expression(condition);
+                self.graph.edge(node, condition);
+                let body = self.statement(body);
+                self.graph.edge(node, body);
+                node
+            }
+            StatementKind::Return(value) => {
+                let node = self.node("return", span);
+                if let Some(value) = value {
+                    let child = self.expression(value);
+                    self.graph.edge(node, child);
+                }
+                node
+            }
+            StatementKind::Printf(value) => {
+                let node = self.node("printf", span);
+                let child = self.expression(value);
+                self.graph.edge(node, child);
+                node
+            }
+            StatementKind::Assignment { target, value } => self.assignment(span, target, value),
+            StatementKind::Call(function) => self.node(&format!("call {}", function.name), span),
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression) -> usize {
+        let span = &expression.span;
+        match &expression.kind {
+            ExpressionKind::Assignment { target, value } => self.assignment(span, target, value),
+            ExpressionKind::Binary {
+                operator,
+                left,
+                right,
+            } => {
+                let node = self.node(operator.symbol(), span);
+                let left = self.expression(left);
+                self.graph.edge(node, left);
+                let right = self.expression(right);
+                self.graph.edge(node, right);
+                node
+            }
+            ExpressionKind::Negation(operand) => {
+                let node = self.node("-", span);
+                let operand = self.expression(operand);
+                self.graph.edge(node, operand);
+                node
+            }
+            ExpressionKind::Literal(literal) => self.node(&literal.text, span),
+            ExpressionKind::Call(function) => self.node(&format!("call {}", function.name), span),
+            ExpressionKind::Variable(variable) => self.identifier(variable),
+        }
+    }
+
+    fn assignment(
+        &mut self,
+        span: &Range<usize>,
+        target: &Identifier,
+        value: &Expression,
+    ) -> usize {
+        let node = self.node("=", span);
+        let target = self.identifier(target);
+        self.graph.edge(node, target);
+        let value = self.expression(value);
+        self.graph.edge(node, value);
+        node
+    }
+
+    fn identifier(&mut self, identifier: &Identifier) -> usize {
+        self.node(&identifier.name, &identifier.span)
+    }
+}
+
+/// A directed graph in the DOT language whose nodes are numbered in the order they are added
+struct Graph {
+    output: String,
+    nodes: usize,
+}
+
+impl Graph {
+    fn new(name: &str) -> Graph {
+        Graph {
+            output: format!("digraph {} {{\n  node [fontname=\"monospace\"];\n", name),
+            nodes: 0,
+        }
+    }
+
+    /// Add a node with the given, already escaped, label and return its number
+    fn node(&mut self, label: &str, shape: &str) -> usize {
+        writeln!(
+            self.output,
+            "  n{} [shape={}, label=\"{}\"];",
+            self.nodes, shape, label
+        )
+        .unwrap();
+        self.nodes += 1;
+        self.nodes - 1
+    }
+
+    fn edge(&mut self, from: usize, to: usize) {
+        writeln!(self.output, "  n{} -> n{};", from, to).unwrap();
+    }
+
+    fn finish(mut self) -> String {
+        self.output.push_str("}\n");
+        self.output
+    }
+}
+
+/// Return the escaped label of a node: the title, the source text of the span unless it equals
+/// the title, and the lines of the span
+fn label(text: &str, title: &str, span: Option<Range<usize>>) -> String {
+    let mut label = escape(title);
+    let Some(span) = span else {
+        return label;
+    };
+    let snippet = snippet(&text[span.clone()]);
+    if snippet != title {
+        write!(label, "\\n\\\"{}\\\"", escape(&snippet)).unwrap();
+    }
+    let first = Position::at(text, span.start).line;
+    let last = Position::at(text, span.end).line;
+    if first == last {
+        write!(label, "\\nline {}", first).unwrap();
+    } else {
+        write!(label, "\\nlines {}-{}", first, last).unwrap();
+    }
+    label
+}
+
+/// Collapse all whitespace of the source text and shorten it to [`MAX_SNIPPET_LENGTH`] characters
+fn snippet(source: &str) -> String {
+    let collapsed = source.split_whitespace().collect::<Vec<_>>().join(" ");
+    if collapsed.chars().count() <= MAX_SNIPPET_LENGTH {
+        return collapsed;
+    }
+    let mut shortened: String = collapsed.chars().take(MAX_SNIPPET_LENGTH - 3).collect();
+    shortened.push_str("...");
+    shortened
+}
+
+/// Escape the text for a quoted DOT string
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dot::{ast, derivation};
+    use crate::parser::C1Parser;
+
+    const EXAMPLE: &str = include_str!("../tests/data/beispiel.c-1");
+
+    /// Return the labels of all nodes
+    fn labels(graph: &str) -> Vec<&str> {
+        graph
+            .lines()
+            .filter_map(|line| line.split_once("label=\""))
+            .map(|(_, label)| label.strip_suffix("\"];").unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn derivation_of_nested_expression() {
+        let text = "void f() {\n  x = a * (b + c);\n}";
+        let (_, events) = C1Parser::parse_with_events(text);
+        let graph = derivation(text, &events);
+        let labels = labels(&graph);
+        assert_eq!(
+            labels[0],
+            "program\\n\\\"void f() { x = a * (b + c); }\\\"\\nlines 1-3"
+        );
+        assert!(labels.contains(&"term\\n\\\"a * (b + c)\\\"\\nline 2"));
+        assert!(labels.contains(&"factor\\n\\\"(b + c)\\\"\\nline 2"));
+        assert!(labels.contains(&"simp_expr\\n\\\"b + c\\\"\\nline 2"));
+        // The parenthesized expression derives from the factor
+        assert_eq!(
+            labels
+                .iter()
+                .filter(|label| label.starts_with("factor"))
+                .count(),
+            4
+        );
+        // One edge for every node except the root
+        let edges = graph.lines().filter(|line| line.contains("->")).count();
+        assert_eq!(edges, labels.len() - 1);
+        assert!(graph.contains("[shape=box, label=\"(\"];"));
+    }
+
+    #[test]
+    fn derivation_up_to_syntax_error() {
+        let text = "void f() { x = ; }";
+        let (result, events) = C1Parser::parse_with_events(text);
+        assert!(result.is_err());
+        let graph = derivation(text, &events);
+        assert!(labels(&graph).contains(&"stat_assignment\\n\\\"x =\\\"\\nline 1"));
+        assert!(graph.ends_with("}\n"));
+    }
+
+    #[test]
+    fn ast_of_example() {
+        let program = C1Parser::parse_ast(EXAMPLE).unwrap();
+        let graph = ast(EXAMPLE, &program);
+        let labels = labels(&graph);
+        assert_eq!(labels[0], "program");
+        assert_eq!(
+            labels[1],
+            "int blub()\\n\\\"int blub() { blub1 = 23; blub...\\\"\\nlines 1-8"
+        );
+        assert!(labels.contains(&"*\\n\\\"blub1 * (blub2 + blub3)\\\"\\nline 5"));
+        assert!(labels.contains(&"+\\n\\\"blub2 + blub3\\\"\\nline 5"));
+        assert!(labels.contains(&"3.14159\\nline 18"));
+        assert!(labels.contains(&"call blub\\n\\\"blub()\\\"\\nline 28"));
+    }
+}
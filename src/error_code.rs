@@ -0,0 +1,665 @@
+use crate::lexer::C1Token;
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// Stable identifiers for every error that can be reported for a C(-1) program. The codes never
+/// change their meaning once assigned, new errors get new codes.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+pub enum ErrorCode {
+    /// E0001: a statement is not terminated by ';'
+    MissingSemicolon,
+    /// E0002: a token that cannot start a factor was found inside of an expression
+    InvalidFactor,
+    /// E0003: '(' is missing
+    MissingLeftParenthesis,
+    /// E0004: ')' is missing
+    MissingRightParenthesis,
+    /// E0005: '{' is missing
+    MissingLeftBrace,
+    /// E0006: '}' is missing
+    MissingRightBrace,
+    /// E0007: a token that cannot start a statement was found in a statement list
+    InvalidStatement,
+    /// E0008: a statement starts with an identifier that is neither assigned nor called
+    InvalidIdentifierStatement,
+    /// E0009: a function definition does not start with a return type
+    InvalidReturnType,
+    /// E0010: an identifier is missing
+    ExpectedIdentifier,
+    /// E0011: '=' is missing in an assignment
+    ExpectedAssign,
+    /// E0012: a function is called that is not defined anywhere in the program
+    UndefinedFunction,
+    /// E0013: a variable is used before a value has been assigned to it
+    UndefinedVariable,
+    /// E0014: two functions have the same name
+    DuplicateFunction,
+    /// E0015: a value of the wrong type is used
+    MismatchedTypes,
+    /// E0016: a character that cannot start any token was found
+    UnexpectedCharacter,
+    /// E0017: a block comment is not closed until the end of the text
+    UnterminatedComment,
+    /// E0018: a string constant is not closed until the end of the line
+    UnterminatedString,
+    /// E0019: the exponent of a float constant has no digits, a digit separator is misplaced or the
+    /// constant is not in decimal notation
+    MalformedFloat,
+    /// E0020: an integer constant does not fit into 64 bits
+    IntegerOverflow,
+    /// E0021: an integer constant has no digits, invalid digits or a misplaced digit separator
+    MalformedInt,
+    /// E0022: an identifier contains non-ASCII characters, but Unicode identifiers are not enabled
+    UnicodeIdentifier,
+}
+
+impl ErrorCode {
+    /// All error codes in ascending order
+    pub const ALL: [ErrorCode; 22] = [
+        ErrorCode::MissingSemicolon,
+        ErrorCode::InvalidFactor,
+        ErrorCode::MissingLeftParenthesis,
+        ErrorCode::MissingRightParenthesis,
+        ErrorCode::MissingLeftBrace,
+        ErrorCode::MissingRightBrace,
+        ErrorCode::InvalidStatement,
+        ErrorCode::InvalidIdentifierStatement,
+        ErrorCode::InvalidReturnType,
+        ErrorCode::ExpectedIdentifier,
+        ErrorCode::ExpectedAssign,
+        ErrorCode::UndefinedFunction,
+        ErrorCode::UndefinedVariable,
+        ErrorCode::DuplicateFunction,
+        ErrorCode::MismatchedTypes,
+        ErrorCode::UnexpectedCharacter,
+        ErrorCode::UnterminatedComment,
+        ErrorCode::UnterminatedString,
+        ErrorCode::MalformedFloat,
+        ErrorCode::IntegerOverflow,
+        ErrorCode::MalformedInt,
+        ErrorCode::UnicodeIdentifier,
+    ];
+
+    /// Return the code that is reported when the given token was expected but not found
+    /// ```
+    /// use cb_3::error_code::ErrorCode;
+    /// use cb_3::C1Token;
+    ///
+    /// assert_eq!(ErrorCode::expecting(&C1Token::Semicolon).code(), "E0001");
+    /// ```
+    pub fn expecting(token: &C1Token) -> ErrorCode {
+        match token {
+            C1Token::Semicolon => ErrorCode::MissingSemicolon,
+            C1Token::LeftParenthesis => ErrorCode::MissingLeftParenthesis,
+            C1Token::RightParenthesis => ErrorCode::MissingRightParenthesis,
+            C1Token::LeftBrace => ErrorCode::MissingLeftBrace,
+            C1Token::RightBrace => ErrorCode::MissingRightBrace,
+            C1Token::Identifier => ErrorCode::ExpectedIdentifier,
+            C1Token::Assign => ErrorCode::ExpectedAssign,
+            C1Token::ConstInt | C1Token::ConstFloat | C1Token::ConstBoolean => {
+                ErrorCode::InvalidFactor
+            }
+            C1Token::KwBoolean | C1Token::KwFloat | C1Token::KwInt | C1Token::KwVoid => {
+                ErrorCode::InvalidReturnType
+            }
+            _ => ErrorCode::InvalidStatement,
+        }
+    }
+
+    /// Return the stable code, e.g. "E0001"
+    pub fn code(&self) -> &'static str {
+        match self {
+            ErrorCode::MissingSemicolon => "E0001",
+            ErrorCode::InvalidFactor => "E0002",
+            ErrorCode::MissingLeftParenthesis => "E0003",
+            ErrorCode::MissingRightParenthesis => "E0004",
+            ErrorCode::MissingLeftBrace => "E0005",
+            ErrorCode::MissingRightBrace => "E0006",
+            ErrorCode::InvalidStatement => "E0007",
+            ErrorCode::InvalidIdentifierStatement => "E0008",
+            ErrorCode::InvalidReturnType => "E0009",
+            ErrorCode::ExpectedIdentifier => "E0010",
+            ErrorCode::ExpectedAssign => "E0011",
+            ErrorCode::UndefinedFunction => "E0012",
+            ErrorCode::UndefinedVariable => "E0013",
+            ErrorCode::DuplicateFunction => "E0014",
+            ErrorCode::MismatchedTypes => "E0015",
+            ErrorCode::UnexpectedCharacter => "E0016",
+            ErrorCode::UnterminatedComment => "E0017",
+            ErrorCode::UnterminatedString => "E0018",
+            ErrorCode::MalformedFloat => "E0019",
+            ErrorCode::IntegerOverflow => "E0020",
+            ErrorCode::MalformedInt => "E0021",
+            ErrorCode::UnicodeIdentifier => "E0022",
+        }
+    }
+
+    /// Return a one line summary of the error
+    pub fn title(&self) -> &'static str {
+        match self {
+            ErrorCode::MissingSemicolon => "missing semicolon",
+            ErrorCode::InvalidFactor => "unexpected token in factor",
+            ErrorCode::MissingLeftParenthesis => "missing opening parenthesis",
+            ErrorCode::MissingRightParenthesis => "missing closing parenthesis",
+            ErrorCode::MissingLeftBrace => "missing opening brace",
+            ErrorCode::MissingRightBrace => "missing closing brace",
+            ErrorCode::InvalidStatement => "invalid statement",
+            ErrorCode::InvalidIdentifierStatement => "identifier is neither assigned nor called",
+            ErrorCode::InvalidReturnType => "invalid return type",
+            ErrorCode::ExpectedIdentifier => "expected identifier",
+            ErrorCode::ExpectedAssign => "expected '='",
+            ErrorCode::UndefinedFunction => "undefined function",
+            ErrorCode::UndefinedVariable => "variable used before assignment",
+            ErrorCode::DuplicateFunction => "function defined multiple times",
+            ErrorCode::MismatchedTypes => "mismatched types",
+            ErrorCode::UnexpectedCharacter => "unexpected character",
+            ErrorCode::UnterminatedComment => "unterminated block comment",
+            ErrorCode::UnterminatedString => "unterminated string literal",
+            ErrorCode::MalformedFloat => "malformed float constant",
+            ErrorCode::IntegerOverflow => "integer constant out of range",
+            ErrorCode::MalformedInt => "malformed integer constant",
+            ErrorCode::UnicodeIdentifier => "non-ASCII identifier",
+        }
+    }
+
+    /// Return the long explanation of the error including examples
+    pub fn explanation(&self) -> &'static str {
+        match self {
+            ErrorCode::MissingSemicolon => {
+                "\
+Every statement except for `if` statements and blocks has to be terminated by a semicolon.
+This applies to `return`, `printf`, assignments and function calls.
+
+Erroneous example:
+
+    void main() {
+        x = 1
+        printf(x);
+    }
+
+Corrected example:
+
+    void main() {
+        x = 1;
+        printf(x);
+    }
+"
+            }
+            ErrorCode::InvalidFactor => {
+                "\
+An expression was expected, but the token found cannot start one. The operands of an expression
+(factors) are integer, float and boolean constants, variables, function calls and assignments in
+parentheses. Keywords, string constants and operators without a left operand are not allowed.
+
+Erroneous example:
+
+    void main() {
+        x = * 2;
+        printf(printf);
+    }
+
+Corrected example:
+
+    void main() {
+        x = 3 * 2;
+        printf(x);
+    }
+"
+            }
+            ErrorCode::MissingLeftParenthesis => {
+                "\
+An opening parenthesis is required after the name of a function in its definition or in a call,
+after `if` and after `printf`.
+
+Erroneous example:
+
+    void main {
+        x = 0;
+        if x < 1 printf(x);
+    }
+
+Corrected example:
+
+    void main() {
+        x = 0;
+        if (x < 1) printf(x);
+    }
+"
+            }
+            ErrorCode::MissingRightParenthesis => {
+                "\
+An opening parenthesis has not been closed. C(-1) functions have no parameters, so the parentheses
+of a function definition or function call must be empty.
+
+Erroneous example:
+
+    float calc(int x) {
+        printf((1 + 2);
+    }
+
+Corrected example:
+
+    float calc() {
+        printf((1 + 2));
+    }
+"
+            }
+            ErrorCode::MissingLeftBrace => {
+                "\
+The body of a function definition has to be enclosed in braces.
+
+Erroneous example:
+
+    void main()
+        printf(1);
+
+Corrected example:
+
+    void main() {
+        printf(1);
+    }
+"
+            }
+            ErrorCode::MissingRightBrace => {
+                "\
+A function body or block has not been closed before the end of the file.
+
+Erroneous example:
+
+    void main() {
+        x = 0;
+        if (x < 1) {
+            printf(x);
+    }
+
+Corrected example:
+
+    void main() {
+        x = 0;
+        if (x < 1) {
+            printf(x);
+        }
+    }
+"
+            }
+            ErrorCode::InvalidStatement => {
+                "\
+A statement was expected, but the token found cannot start one. Statements are `if` statements,
+`return` statements, `printf` calls, assignments, function calls and blocks in braces. Variables
+are not declared in C(-1), so a statement must not start with a type.
+
+Erroneous example:
+
+    void main() {
+        int x = 0;
+    }
+
+Corrected example:
+
+    void main() {
+        x = 0;
+    }
+"
+            }
+            ErrorCode::InvalidIdentifierStatement => {
+                "\
+A statement that starts with an identifier must either assign a value to a variable or call a
+function. A variable on its own is not a statement.
+
+Erroneous example:
+
+    void foo() {
+    }
+
+    void main() {
+        foo;
+    }
+
+Corrected example:
+
+    void foo() {
+    }
+
+    void main() {
+        foo();
+    }
+"
+            }
+            ErrorCode::InvalidReturnType => {
+                "\
+A C(-1) program consists of function definitions only, and every function definition starts with
+its return type: `bool`, `int`, `float` or `void`.
+
+Erroneous example:
+
+    const bar() {
+        return 0;
+    }
+
+Corrected example:
+
+    int bar() {
+        return 0;
+    }
+"
+            }
+            ErrorCode::ExpectedIdentifier => {
+                "\
+A name was expected, e.g. the name of a function after its return type.
+
+Erroneous example:
+
+    int () {
+        return 0;
+    }
+
+Corrected example:
+
+    int zero() {
+        return 0;
+    }
+"
+            }
+            ErrorCode::ExpectedAssign => {
+                "\
+An assignment has to contain '=' between the variable and the assigned value. Note that `==`
+compares two values and cannot be used for assignments.
+
+The parser only reads an identifier as the target of an assignment if it is followed by '=', so a
+statement with a missing '=' like `x 1;` is reported as E0008 instead.
+"
+            }
+            ErrorCode::UndefinedFunction => {
+                "\
+A function is called, but no function with this name is defined in the program. Functions may be
+called before their definition, but they have to be defined somewhere.
+
+Erroneous example:
+
+    void main() {
+        greet();
+    }
+
+Corrected example:
+
+    void main() {
+        greet();
+    }
+
+    void greet() {
+        printf(42);
+    }
+"
+            }
+            ErrorCode::UndefinedVariable => {
+                "\
+Variables are not declared in C(-1). A variable comes into existence with the first assignment to
+it in its function, and it cannot be used before that assignment. Every function has its own
+variables, so a variable of another function is not visible either.
+
+Erroneous example:
+
+    void main() {
+        printf(x);
+        x = 1;
+    }
+
+Corrected example:
+
+    void main() {
+        x = 1;
+        printf(x);
+    }
+"
+            }
+            ErrorCode::DuplicateFunction => {
+                "\
+Every function in a program needs a unique name, even if the functions differ in their return
+types.
+
+Erroneous example:
+
+    int one() {
+        return 1;
+    }
+
+    float one() {
+        return 1.0;
+    }
+
+Corrected example:
+
+    int one() {
+        return 1;
+    }
+
+    float unit() {
+        return 1.0;
+    }
+"
+            }
+            ErrorCode::MismatchedTypes => {
+                "\
+A value is used where a value of a different type is required. The type of a variable is the type
+of the first value assigned to it, and it cannot change later. Arithmetic operators and orderings
+require numbers, `&&` and `||` require booleans, conditions of `if` statements have to be booleans
+and returned values have to match the return type of the function. An `int` is converted to a
+`float` automatically, no other conversions exist. Calls of `void` functions have no value.
+
+Erroneous example:
+
+    int answer() {
+        return true;
+    }
+
+Corrected example:
+
+    int answer() {
+        return 42;
+    }
+"
+            }
+            ErrorCode::UnexpectedCharacter => {
+                "\
+A character was found that does not belong to any token of C(-1). Besides letters, digits,
+whitespace and the characters of operators and punctuation, characters only appear in comments and
+string constants.
+
+Erroneous example:
+
+    void main() {
+        x = 1;
+        printf($x);
+    }
+
+Corrected example:
+
+    void main() {
+        x = 1;
+        printf(x);
+    }
+"
+            }
+            ErrorCode::UnterminatedComment => {
+                "\
+A block comment that starts with `/*` has to be closed by `*/`. Otherwise the rest of the program
+is part of the comment.
+
+Erroneous example:
+
+    void main() {
+        printf(1);
+    }
+    /* end of main
+
+Corrected example:
+
+    void main() {
+        printf(1);
+    }
+    /* end of main */
+"
+            }
+            ErrorCode::UnterminatedString => {
+                "\
+A string constant that starts with `\"` has to be closed by another `\"` on the same line. C(-1)
+has no use for string constants in expressions, so a lone `\"` usually is a typo.
+
+Erroneous example:
+
+    void main() {
+        printf(1); \"
+    }
+
+Corrected example:
+
+    void main() {
+        printf(1);
+    }
+"
+            }
+            ErrorCode::MalformedFloat => {
+                "\
+The exponent of a float constant consists of `e` or `E`, an optional sign and at least one digit.
+Float constants are always written in decimal notation, the hexadecimal and binary notation of
+integers like `0x1.8` cannot be used for them. Like in integers, digits may be separated by `_`,
+but every separator has to stand between two digits.
+
+Erroneous example:
+
+    void main() {
+        x = 1.5e;
+        printf(x);
+    }
+
+Corrected example:
+
+    void main() {
+        x = 1.5e0;
+        printf(x);
+    }
+"
+            }
+            ErrorCode::IntegerOverflow => {
+                "\
+Integers are signed 64 bit numbers, so an integer constant cannot be larger than
+9223372036854775807. Larger numbers can be written as float constants.
+
+Erroneous example:
+
+    void main() {
+        printf(9223372036854775808);
+    }
+
+Corrected example:
+
+    void main() {
+        printf(9223372036854775808.0);
+    }
+"
+            }
+            ErrorCode::MalformedInt => {
+                "\
+Integer constants are written in decimal notation, in octal notation with a leading `0`, in
+hexadecimal notation with the prefix `0x` or in binary notation with the prefix `0b`. They may only
+contain the digits of their base, and at least one digit has to follow the prefix. A single `_`
+can separate two digits.
+
+Erroneous example:
+
+    void main() {
+        x = 1_000;
+        printf(x + 019);
+    }
+
+Corrected example:
+
+    void main() {
+        x = 1_000;
+        printf(x + 017);
+    }
+"
+            }
+            ErrorCode::UnicodeIdentifier => {
+                "\
+Identifiers consist of ASCII letters, digits and `_`, and do not start with a digit. Identifiers
+with other letters, e.g. `größe`, are only accepted if Unicode identifiers are enabled in the
+options of the lexer.
+
+Erroneous example:
+
+    int größe() {
+        return 3;
+    }
+
+Corrected example:
+
+    int groesse() {
+        return 3;
+    }
+"
+            }
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+impl FromStr for ErrorCode {
+    type Err = String;
+
+    /// Look up an error code by its textual representation, e.g. "E0001"
+    fn from_str(code: &str) -> Result<Self, Self::Err> {
+        ErrorCode::ALL
+            .iter()
+            .find(|error_code| error_code.code().eq_ignore_ascii_case(code))
+            .copied()
+            .ok_or_else(|| format!("unknown error code '{}'", code))
+    }
+}
+
+impl Serialize for ErrorCode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.code())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error_code::ErrorCode;
+    use std::collections::HashSet;
+
+    #[test]
+    fn codes_are_unique_and_ordered() {
+        let codes: Vec<&str> = ErrorCode::ALL.iter().map(ErrorCode::code).collect();
+        let unique: HashSet<&&str> = codes.iter().collect();
+        assert_eq!(unique.len(), codes.len());
+        assert!(codes.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn codes_can_be_looked_up() {
+        for error_code in ErrorCode::ALL {
+            assert_eq!(error_code.code().parse(), Ok(error_code));
+        }
+        assert_eq!("e0002".parse(), Ok(ErrorCode::InvalidFactor));
+        assert!("E9999".parse::<ErrorCode>().is_err());
+    }
+
+    #[test]
+    fn every_code_is_explained() {
+        for error_code in ErrorCode::ALL {
+            // The parser cannot report ExpectedAssign, so there is no example for it
+            if error_code == ErrorCode::ExpectedAssign {
+                continue;
+            }
+            assert!(error_code.explanation().contains("Erroneous example"));
+            assert!(error_code.explanation().contains("Corrected example"));
+        }
+    }
+}
@@ -0,0 +1,392 @@
+use crate::ast::{
+    Expression, ExpressionKind, FunctionDefinition, Precedence, Program, Statement, StatementKind,
+};
+use crate::diagnostic::Diagnostic;
+use crate::lexer::C1Token;
+use crate::parser::C1Parser;
+use logos::Logos;
+use std::ops::Range;
+
+/// The string used for one level of indentation
+const INDENTATION: &str = "    ";
+
+/// Format a C(-1) program in the canonical style: one statement per line, four spaces of indentation
+/// per nested block, opening braces on the line of the function or if statement, and single spaces
+/// around binary operators. Formatting a formatted program does not change it anymore.
+///
+/// Comments are preserved. A comment at the end of a line stays there, and a comment on its own
+/// line keeps its own line. Statements are printed from the syntax tree, so a comment inside of a
+/// statement or an if condition, e.g. `x = /* one */ 1;`, is moved to its own line in front of it.
+/// ```
+/// use cb_3::formatter::format;
+///
+/// let formatted = format("int  main(){x=1+2*y; // answer\n if(x<3)return x;}").unwrap();
+/// assert_eq!(formatted, "int main() {\n    x = 1 + 2 * y; // answer\n    if (x < 3) return x;\n}\n");
+/// ```
+pub fn format(text: &str) -> Result<String, Box<Diagnostic>> {
+    let program = C1Parser::parse_ast(text)?;
+    let mut formatter = Formatter {
+        source: text,
+        comments: comments(text),
+        next_comment: 0,
+        lines: Vec::new(),
+        indentation: 0,
+        last_end: 0,
+    };
+    formatter.program(&program);
+    Ok(formatter
+        .lines
+        .iter()
+        .map(|line| format!("{}\n", line))
+        .collect())
+}
+
+/// A comment in the source text, without the linebreak that terminates '//' comments
+struct Comment<'a> {
+    text: &'a str,
+    span: Range<usize>,
+}
+
+/// Collect all comments of the text in the order of their appearance
+fn comments(text: &str) -> Vec<Comment<'_>> {
+    C1Token::lexer(text)
+        .spanned()
+        .filter(|(token, _)| matches!(token, C1Token::CComment | C1Token::CPPComment))
+        .map(|(_, span)| {
+            let comment = text[span.clone()].trim_end_matches('\n');
+            Comment {
+                text: comment,
+                span: span.start..span.start + comment.len(),
+            }
+        })
+        .collect()
+}
+
+/// Pretty printer state. Comments are emitted in source order: every comment that starts in front of
+/// the next printed element is printed on its own line before it, and a comment that follows the last
+/// printed element on the same source line is appended to its line.
+struct Formatter<'a> {
+    source: &'a str,
+    comments: Vec<Comment<'a>>,
+    next_comment: usize,
+    lines: Vec<String>,
+    indentation: usize,
+    /// Source offset up to which everything has been printed
+    last_end: usize,
+}
+
+impl<'a> Formatter<'a> {
+    fn program(&mut self, program: &Program) {
+        for function in &program.functions {
+            self.function_definition(function);
+        }
+        self.leading_comments(self.source.len());
+    }
+
+    fn function_definition(&mut self, function: &FunctionDefinition) {
+        // Functions are always separated by exactly one blank line
+        if self.lines.last().is_some_and(|line| !line.is_empty()) {
+            self.lines.push(String::new());
+        }
+        let open_brace = self.find_token(function.name.span.end, C1Token::LeftBrace);
+        self.leading_comments(open_brace);
+        self.begin_item(function.span.start);
+        self.line(format!(
+            "{} {}() {{",
+            function.return_type, function.name.name
+        ));
+        self.finish_item(open_brace + 1);
+        self.block_body(&function.body, function.span.end - 1);
+        self.line(String::from("}"));
+        self.finish_item(function.span.end);
+    }
+
+    /// Print the statements of a block and the comments in front of its closing brace
+    fn block_body(&mut self, statements: &[Statement], close_brace: usize) {
+        self.indentation += 1;
+        for statement in statements {
+            self.statement(statement);
+        }
+        self.leading_comments(close_brace);
+        self.indentation -= 1;
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match &statement.kind {
+            StatementKind::Block(statements) => {
+                self.leading_comments(statement.span.start);
+                self.begin_item(statement.span.start);
+                self.line(String::from("{"));
+                self.finish_item(statement.span.start + 1);
+                self.block_body(statements, statement.span.end - 1);
+                self.line(String::from("}"));
+                self.finish_item(statement.span.end);
+            }
+            StatementKind::If { condition, body } => {
+                // Comments inside of the condition are moved in front of the if statement
+                self.leading_comments(condition.span.end);
+                let header = format!("if ({})", expression(condition));
+                match (&body.kind, simple_statement(body)) {
+                    (StatementKind::Block(statements), _) => {
+                        self.leading_comments(body.span.start);
+                        self.begin_item(statement.span.start);
+                        self.line(format!("{} {{", header));
+                        self.finish_item(body.span.start + 1);
+                        self.block_body(statements, body.span.end - 1);
+                        self.line(String::from("}"));
+                        self.finish_item(body.span.end);
+                    }
+                    (_, Some(simple)) if !self.comment_before(body.span.end) => {
+                        self.begin_item(statement.span.start);
+                        self.line(format!("{} {}", header, simple));
+                        self.finish_item(body.span.end);
+                    }
+                    _ => self.nested_body(statement, header, body),
+                }
+            }
+            _ => {
+                if let Some(simple) = simple_statement(statement) {
+                    self.leading_comments(statement.span.end);
+                    self.begin_item(statement.span.start);
+                    self.line(simple);
+                    self.finish_item(statement.span.end);
+                }
+            }
+        }
+    }
+
+    /// Print the body of an if statement without braces on its own, indented line
+    fn nested_body(&mut self, statement: &Statement, header: String, body: &Statement) {
+        self.begin_item(statement.span.start);
+        self.line(header);
+        let close_parenthesis = self.find_last_token(
+            statement.span.start..body.span.start,
+            C1Token::RightParenthesis,
+        );
+        self.finish_item(close_parenthesis + 1);
+        self.indentation += 1;
+        self.statement(body);
+        self.indentation -= 1;
+    }
+
+    /// Print all comments that start before the given offset on their own lines
+    fn leading_comments(&mut self, limit: usize) {
+        while self.comment_before(limit) {
+            let comment = &self.comments[self.next_comment];
+            let (text, span) = (comment.text, comment.span.clone());
+            self.next_comment += 1;
+            self.begin_item(span.start);
+            self.line(text.to_owned());
+            self.finish_item(span.end);
+        }
+    }
+
+    /// Check whether the next unprinted comment starts before the given offset
+    fn comment_before(&self, limit: usize) -> bool {
+        self.comments
+            .get(self.next_comment)
+            .is_some_and(|comment| comment.span.start < limit)
+    }
+
+    /// Insert a blank line if the element starting at the given offset was separated by one from the
+    /// previously printed element. Blank lines directly after an opening brace are dropped.
+    fn begin_item(&mut self, start: usize) {
+        let after_opening = self
+            .lines
+            .last()
+            .is_none_or(|line| line.is_empty() || line.ends_with('{'));
+        if !after_opening && self.last_end < start {
+            let gap = &self.source[self.last_end..start];
+            if gap.matches('\n').count() > 1 {
+                self.lines.push(String::new());
+            }
+        }
+    }
+
+    /// Record that the source text up to the given offset has been printed and append comments that
+    /// directly follow on the same source line to the last printed line
+    fn finish_item(&mut self, end: usize) {
+        self.last_end = self.last_end.max(end);
+        while let Some(comment) = self.comments.get(self.next_comment) {
+            let on_same_line = comment.span.start >= self.last_end
+                && self.source[self.last_end..comment.span.start]
+                    .chars()
+                    .all(|c| c == ' ' || c == '\t');
+            if !on_same_line {
+                break;
+            }
+            let line = self
+                .lines
+                .last_mut()
+                .expect("a line has been printed before");
+            line.push(' ');
+            line.push_str(comment.text);
+            self.last_end = comment.span.end;
+            self.next_comment += 1;
+        }
+    }
+
+    fn line(&mut self, text: String) {
+        self.lines
+            .push(INDENTATION.repeat(self.indentation) + &text);
+    }
+
+    /// Return the offset of the first occurrence of the token after the given offset
+    fn find_token(&self, from: usize, token: C1Token) -> usize {
+        C1Token::lexer(&self.source[from..])
+            .spanned()
+            .find(|(candidate, _)| *candidate == token)
+            .map_or(self.source.len(), |(_, span)| from + span.start)
+    }
+
+    /// Return the offset of the last occurrence of the token in the given range
+    fn find_last_token(&self, range: Range<usize>, token: C1Token) -> usize {
+        C1Token::lexer(&self.source[range.clone()])
+            .spanned()
+            .filter(|(candidate, _)| *candidate == token)
+            .last()
+            .map_or(range.start, |(_, span)| range.start + span.start)
+    }
+}
+
+/// Format a statement that fits on a single line, including its semicolon, or return `None` for
+/// blocks and if statements, which span multiple lines
+fn simple_statement(statement: &Statement) -> Option<String> {
+    let line = match &statement.kind {
+        StatementKind::Return(None) => String::from("return;"),
+        StatementKind::Return(Some(value)) => format!("return {};", expression(value)),
+        StatementKind::Printf(value) => format!("printf({});", expression(value)),
+        StatementKind::Assignment { target, value } => {
+            format!("{} = {};", target.name, expression(value))
+        }
+        StatementKind::Call(function) => format!("{}();", function.name),
+        StatementKind::Block(_) | StatementKind::If { .. } => return None,
+    };
+    Some(line)
+}
+
+/// Format an expression with the minimal amount of parentheses
+fn expression(expression: &Expression) -> String {
+    match &expression.kind {
+        ExpressionKind::Assignment { target, value } => {
+            format!("{} = {}", target.name, self::expression(value))
+        }
+        ExpressionKind::Binary {
+            operator,
+            left,
+            right,
+        } => {
+            let precedence = operator.precedence();
+            // Comparisons are not associative, both sides are simp_exprs
+            let (left_precedence, right_precedence) = match precedence {
+                Precedence::Comparison => (Precedence::Sum, Precedence::Sum),
+                Precedence::Sum => (Precedence::Sum, Precedence::Product),
+                _ => (Precedence::Product, Precedence::Factor),
+            };
+            format!(
+                "{} {} {}",
+                operand(left, left_precedence),
+                operator.symbol(),
+                operand(right, right_precedence)
+            )
+        }
+        ExpressionKind::Negation(operand) => {
+            format!("-{}", self::operand(operand, Precedence::Product))
+        }
+        ExpressionKind::Literal(literal) => literal.text.clone(),
+        ExpressionKind::Call(function) => format!("{}()", function.name),
+        ExpressionKind::Variable(variable) => variable.name.clone(),
+    }
+}
+
+/// Format an expression that is used in a position requiring at least the given precedence
+fn operand(operand: &Expression, precedence: Precedence) -> String {
+    if operand.precedence() < precedence {
+        format!("({})", expression(operand))
+    } else {
+        expression(operand)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formatter::format;
+    use crate::parser::C1Parser;
+
+    /// Check that formatting is idempotent and does not change the syntax tree
+    fn assert_round_trip(text: &str) -> String {
+        let formatted = format(text).unwrap();
+        assert_eq!(format(&formatted).unwrap(), formatted);
+        assert_eq!(
+            C1Parser::parse_ast(&formatted).unwrap().dump(),
+            C1Parser::parse_ast(text).unwrap().dump()
+        );
+        formatted
+    }
+
+    #[test]
+    fn example_round_trip() {
+        let formatted = assert_round_trip(include_str!("../tests/data/beispiel.c-1"));
+        assert!(formatted.starts_with("int blub() {\n    blub1 = 23;\n"));
+        assert!(formatted.contains("\n    if (a <= b) printf(a + b);\n"));
+        assert!(formatted.contains("\n    return 3.14159;\n}\n\nvoid main() {\n"));
+    }
+
+    #[test]
+    fn nested_blocks_are_indented() {
+        let formatted = assert_round_trip("void f(){if(a){{b();}}g();}");
+        assert_eq!(
+            formatted,
+            "void f() {\n    if (a) {\n        {\n            b();\n        }\n    }\n    g();\n}\n"
+        );
+    }
+
+    #[test]
+    fn parentheses_are_minimal() {
+        let formatted = assert_round_trip(
+            "void f(){x=((a*b))+c; y=a*(b+c); z=(a-b)-(c-d); w=-(a+b); v=(-a)*b; u=(a<b)==(c=d); t=-a-b;}",
+        );
+        assert!(formatted.contains("x = a * b + c;"));
+        assert!(formatted.contains("y = a * (b + c);"));
+        assert!(formatted.contains("z = a - b - (c - d);"));
+        assert!(formatted.contains("w = -(a + b);"));
+        assert!(formatted.contains("v = (-a) * b;"));
+        assert!(formatted.contains("u = (a < b) == (c = d);"));
+        assert!(formatted.contains("t = -a - b;"));
+    }
+
+    #[test]
+    fn comments_are_preserved() {
+        let text = "// header\n\nint f() { // entry\n  x = /* inline */ 1;\n\n\n  /* before\n     return */\n  return x; // done\n  // last\n}\n// end";
+        let formatted = assert_round_trip(text);
+        assert_eq!(
+            formatted,
+            "// header\n\nint f() { // entry\n    /* inline */\n    x = 1;\n\n    /* before\n     return */\n    return x; // done\n    // last\n}\n// end\n"
+        );
+    }
+
+    #[test]
+    fn inline_comments_are_moved_in_front_of_their_statement() {
+        let text = "void f() {\n  x = /* one */ 1; // trailing\n  if (x /* two */ < 2) return;\n}";
+        let formatted = assert_round_trip(text);
+        assert_eq!(
+            formatted,
+            "void f() {\n    /* one */\n    x = 1; // trailing\n    /* two */\n    if (x < 2) return;\n}\n"
+        );
+    }
+
+    #[test]
+    fn if_body_with_comment_is_moved_to_next_line() {
+        let formatted =
+            assert_round_trip("void f() {\n if ((x)) // why\n y = 1;\n if (x) /* a */ y = 2; }");
+        assert_eq!(
+            formatted,
+            "void f() {\n    if (x) // why\n        y = 1;\n    if (x) /* a */\n        y = 2;\n}\n"
+        );
+    }
+
+    #[test]
+    fn syntax_errors_are_reported() {
+        assert!(format("void f() { x = ; }").is_err());
+    }
+}
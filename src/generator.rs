@@ -0,0 +1,474 @@
+use crate::ast::Type;
+use crate::grammar::{Bnf, Symbol, Terminal};
+use crate::lexer::C1Token;
+
+/// Names for identifiers in derivations. None of them is a keyword, and `main` is the only one
+/// that is usually defined as a function.
+const NAMES: [&str; 6] = ["a", "b", "x", "y", "f", "main"];
+
+/// A deterministic source of random numbers, a linear congruential generator
+pub(crate) struct Random(u64);
+
+impl Random {
+    pub(crate) fn new(seed: u64) -> Random {
+        Random(seed)
+    }
+
+    /// Return a random number below the bound
+    pub(crate) fn below(&mut self, bound: usize) -> usize {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        ((self.0 >> 33) as usize) % bound
+    }
+}
+
+/// # Overview
+/// A generator of random C(-1) programs for fuzzing, in two modes:
+///
+/// - [`Generator::derivation`] walks a grammar and derives random token sequences. They are
+///   syntactically valid, but usually reference undefined functions and mix up types.
+/// - [`Generator::typed_program`] builds programs that the [checker](crate::checker) accepts and
+///   whose execution terminates, so that they can be run by any backend and the outputs compared.
+///
+/// The generator is deterministic: the same seed and depth always produce the same programs. The
+/// depth bounds the nesting of rules, statements and expressions.
+///
+/// # Examples
+/// ```
+/// use cb_3::generator::Generator;
+/// use cb_3::grammar::Grammar;
+/// use cb_3::parser::C1Parser;
+///
+/// let bnf = Grammar::c1().to_bnf();
+/// let program = Generator::new(7, 12).derivation(&bnf);
+/// assert!(C1Parser::parse(&program).is_ok());
+/// assert_eq!(program, Generator::new(7, 12).derivation(&bnf));
+/// ```
+pub struct Generator {
+    random: Random,
+    max_depth: usize,
+}
+
+impl Generator {
+    pub fn new(seed: u64, max_depth: usize) -> Generator {
+        Generator {
+            random: Random::new(seed),
+            max_depth,
+        }
+    }
+
+    fn below(&mut self, bound: usize) -> usize {
+        self.random.below(bound)
+    }
+
+    fn chance(&mut self, percent: usize) -> bool {
+        self.below(100) < percent
+    }
+
+    fn choose<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len())]
+    }
+
+    /// Derive a random word from the start symbol of the grammar, with the tokens separated by
+    /// spaces. Below the maximum depth every production is chosen with the same probability,
+    /// beyond it the productions that lead to terminals in the fewest steps are chosen.
+    pub fn derivation(&mut self, bnf: &Bnf) -> String {
+        let heights = heights(bnf);
+        let mut tokens = Vec::new();
+        if !bnf.nonterminals.is_empty() {
+            self.derive(bnf, &heights, 0, 0, &mut tokens);
+        }
+        tokens.join(" ")
+    }
+
+    fn derive(
+        &mut self,
+        bnf: &Bnf,
+        heights: &[Option<usize>],
+        nonterminal: usize,
+        depth: usize,
+        tokens: &mut Vec<String>,
+    ) {
+        let productions: Vec<&[Symbol]> = bnf
+            .productions
+            .iter()
+            .filter(|production| production.nonterminal == nonterminal)
+            .map(|production| production.symbols.as_slice())
+            .filter(|symbols| {
+                let height = height(symbols, heights);
+                height.is_some() && (depth < self.max_depth || height <= heights[nonterminal])
+            })
+            .collect();
+        if productions.is_empty() {
+            return;
+        }
+        let symbols = *self.choose(&productions);
+        for symbol in symbols {
+            match symbol {
+                Symbol::Terminal(Terminal::Token(token)) => {
+                    let text = self.token_text(*token);
+                    tokens.push(text);
+                }
+                Symbol::Terminal(Terminal::Eof) => {}
+                Symbol::Nonterminal(index) => self.derive(bnf, heights, *index, depth + 1, tokens),
+            }
+        }
+    }
+
+    /// Return a random text the token is lexed from
+    fn token_text(&mut self, token: C1Token) -> String {
+        match token {
+            C1Token::Identifier => self.choose(&NAMES).to_string(),
+            C1Token::ConstInt => self.below(100).to_string(),
+            C1Token::ConstFloat => format!("{}.{}", self.below(10), self.below(100)),
+            C1Token::ConstBoolean => self.choose(&["true", "false"]).to_string(),
+            C1Token::ConstString => String::from("\"text\""),
+            token => token.description().trim_matches('\'').to_owned(),
+        }
+    }
+
+    /// Generate a program that passes the semantic checks and terminates. It consists of a few
+    /// functions and a `void main()` that prints their results. Functions only call functions
+    /// defined before them, and C(-1) has no loops, so every execution terminates.
+    ///
+    /// Divisions and multiplications only have nonzero literals below 10 as right operand, so
+    /// that there is no division by zero and values grow slowly. Variables that are first
+    /// assigned inside the body of an if statement are not used after it, so that no variable is
+    /// read before it has a value.
+    /// ```
+    /// use cb_3::checker;
+    /// use cb_3::generator::Generator;
+    ///
+    /// let program = Generator::new(1, 4).typed_program();
+    /// assert!(program.contains("void main() {\n"));
+    /// assert_eq!(checker::diagnostics(&program), vec![]);
+    /// ```
+    pub fn typed_program(&mut self) -> String {
+        let mut program = TypedProgram {
+            text: String::new(),
+            functions: Vec::new(),
+            variables: 0,
+        };
+        for index in 0..1 + self.below(4) {
+            let return_type = *self.choose(&[Type::Boolean, Type::Float, Type::Int, Type::Void]);
+            let name = format!("f{}", index);
+            program.function(self, &name, return_type);
+            program.functions.push((name, return_type));
+        }
+        program.function(self, "main", Type::Void);
+        program.text
+    }
+}
+
+/// The number of derivation steps each nonterminal needs at least to derive a word of terminals,
+/// or `None` if it cannot derive one
+fn heights(bnf: &Bnf) -> Vec<Option<usize>> {
+    let mut heights = vec![None; bnf.nonterminals.len()];
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for production in &bnf.productions {
+            let height = height(&production.symbols, &heights);
+            if height.is_some()
+                && (heights[production.nonterminal].is_none()
+                    || height < heights[production.nonterminal])
+            {
+                heights[production.nonterminal] = height;
+                changed = true;
+            }
+        }
+    }
+    heights
+}
+
+/// The number of derivation steps a production needs at least to derive a word of terminals
+fn height(symbols: &[Symbol], heights: &[Option<usize>]) -> Option<usize> {
+    let mut height = 1;
+    for symbol in symbols {
+        if let Symbol::Nonterminal(index) = symbol {
+            height = height.max(heights[*index]? + 1);
+        }
+    }
+    Some(height)
+}
+
+/// The state of [`Generator::typed_program`]
+struct TypedProgram {
+    text: String,
+    /// The functions that have been defined so far, they can be called by later functions
+    functions: Vec<(String, Type)>,
+    /// The number of variables of the current function, including those that are out of scope
+    variables: usize,
+}
+
+/// The variables that have a value at some point of a function
+type Scope = Vec<(String, Type)>;
+
+impl TypedProgram {
+    fn function(&mut self, generator: &mut Generator, name: &str, return_type: Type) {
+        self.text
+            .push_str(&format!("{} {}() {{\n", return_type, name));
+        let mut scope = Scope::new();
+        self.variables = 0;
+        for _ in 0..1 + generator.below(4) {
+            self.statement(generator, &mut scope, return_type, 1);
+        }
+        if name == "main" {
+            for (function, ty) in self.functions.clone() {
+                let call = match ty {
+                    Type::Void => format!("    {}();\n", function),
+                    _ => format!("    printf({}());\n", function),
+                };
+                self.text.push_str(&call);
+            }
+        } else if return_type != Type::Void {
+            self.return_statement(generator, &scope, return_type, "    ");
+        }
+        self.text.push_str("}\n\n");
+    }
+
+    fn statement(
+        &mut self,
+        generator: &mut Generator,
+        scope: &mut Scope,
+        return_type: Type,
+        depth: usize,
+    ) {
+        let indentation = "    ".repeat(depth);
+        match generator.below(5) {
+            0 if depth < generator.max_depth => {
+                let condition = self.expression(generator, scope, Type::Boolean, 0);
+                self.text
+                    .push_str(&format!("{}if ({}) {{\n", indentation, condition.0));
+                // Variables defined in the body may have no value after it
+                let mut body = scope.clone();
+                for _ in 0..1 + generator.below(3) {
+                    self.statement(generator, &mut body, return_type, depth + 1);
+                }
+                if generator.chance(20) {
+                    let indentation = format!("{}    ", indentation);
+                    self.return_statement(generator, &body, return_type, &indentation);
+                }
+                self.text.push_str(&format!("{}}}\n", indentation));
+            }
+            1 => {
+                let ty = *generator.choose(&[Type::Boolean, Type::Float, Type::Int]);
+                let value = self.expression(generator, scope, ty, 0);
+                self.text
+                    .push_str(&format!("{}printf({});\n", indentation, value.0));
+            }
+            2 if !self.functions.is_empty() => {
+                let (function, _) = generator.choose(&self.functions);
+                self.text
+                    .push_str(&format!("{}{}();\n", indentation, function));
+            }
+            _ => {
+                let (target, ty) = match scope.len() {
+                    0 => self.new_variable(generator),
+                    length if generator.chance(50) => scope[generator.below(length)].clone(),
+                    _ => self.new_variable(generator),
+                };
+                let value = self.expression(generator, scope, ty, 0);
+                self.text
+                    .push_str(&format!("{}{} = {};\n", indentation, target, value.0));
+                if !scope.iter().any(|(name, _)| *name == target) {
+                    scope.push((target, ty));
+                }
+            }
+        }
+    }
+
+    /// Choose the name and type of a new variable, it is added to the scope by the caller once it
+    /// has a value
+    fn new_variable(&mut self, generator: &mut Generator) -> (String, Type) {
+        let ty = *generator.choose(&[Type::Boolean, Type::Float, Type::Int]);
+        self.variables += 1;
+        (format!("v{}", self.variables - 1), ty)
+    }
+
+    fn return_statement(
+        &mut self,
+        generator: &mut Generator,
+        scope: &Scope,
+        return_type: Type,
+        indentation: &str,
+    ) {
+        let statement = match return_type {
+            Type::Void => format!("{}return;\n", indentation),
+            _ => {
+                let (value, _) = self.expression(generator, scope, return_type, 0);
+                format!("{}return {};\n", indentation, value)
+            }
+        };
+        self.text.push_str(&statement);
+    }
+
+    /// Generate an expression of the given type, together with whether it is a single factor
+    /// that needs no parentheses as an operand
+    fn expression(
+        &self,
+        generator: &mut Generator,
+        scope: &Scope,
+        ty: Type,
+        depth: usize,
+    ) -> (String, bool) {
+        let variables: Vec<&String> = scope
+            .iter()
+            .filter(|(_, other)| *other == ty)
+            .map(|(name, _)| name)
+            .collect();
+        let functions: Vec<&String> = self
+            .functions
+            .iter()
+            .filter(|(_, other)| *other == ty)
+            .map(|(name, _)| name)
+            .collect();
+        let leaf = depth >= generator.max_depth || generator.chance(30);
+        match generator.below(4) {
+            0 if !variables.is_empty() => {
+                return (generator.choose(&variables).to_string(), true);
+            }
+            1 if !functions.is_empty() => {
+                return (format!("{}()", generator.choose(&functions)), true);
+            }
+            _ if leaf => return (literal(generator, ty), true),
+            _ => {}
+        }
+
+        let operand = |generator: &mut Generator, ty: Type| {
+            let (text, factor) = self.expression(generator, scope, ty, depth + 1);
+            if factor {
+                text
+            } else {
+                format!("({})", text)
+            }
+        };
+        let text = match ty {
+            Type::Boolean => match generator.below(3) {
+                0 => {
+                    let operator = generator.choose(&["&&", "||"]);
+                    let left = operand(generator, Type::Boolean);
+                    format!(
+                        "{} {} {}",
+                        left,
+                        operator,
+                        operand(generator, Type::Boolean)
+                    )
+                }
+                1 => {
+                    let operator = generator.choose(&["==", "!=", "<", ">", "<=", ">="]);
+                    let left = operand(generator, Type::Int);
+                    format!("{} {} {}", left, operator, operand(generator, Type::Float))
+                }
+                _ => {
+                    let operator = generator.choose(&["==", "!="]);
+                    let left = operand(generator, Type::Boolean);
+                    format!(
+                        "{} {} {}",
+                        left,
+                        operator,
+                        operand(generator, Type::Boolean)
+                    )
+                }
+            },
+            _ => {
+                // A float operation needs one float operand, the other one may be an int
+                let other = match ty {
+                    Type::Float => *generator.choose(&[Type::Float, Type::Int]),
+                    _ => ty,
+                };
+                match generator.below(4) {
+                    0 => format!("-{}", operand(generator, ty)),
+                    1 => {
+                        let operator = generator.choose(&["*", "/"]);
+                        let left = operand(generator, ty);
+                        format!("{} {} {}", left, operator, 1 + generator.below(9))
+                    }
+                    _ => {
+                        let operator = generator.choose(&["+", "-"]);
+                        let left = operand(generator, ty);
+                        format!("{} {} {}", left, operator, operand(generator, other))
+                    }
+                }
+            }
+        };
+        (text, false)
+    }
+}
+
+fn literal(generator: &mut Generator, ty: Type) -> String {
+    match ty {
+        Type::Boolean => generator.choose(&["true", "false"]).to_string(),
+        Type::Float => format!("{}.{}", generator.below(10), generator.below(100)),
+        _ => generator.below(100).to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::checker::{self, Symbol};
+    use crate::generator::Generator;
+    use crate::grammar::{Grammar, C1_GRAMMAR};
+    use crate::parser::C1Parser;
+
+    #[test]
+    fn derivations_are_accepted_by_the_parser() {
+        let bnf = Grammar::c1().to_bnf();
+        let mut lengths = Vec::new();
+        for seed in 0..200 {
+            let program = Generator::new(seed, 14).derivation(&bnf);
+            assert!(C1Parser::parse(&program).is_ok(), "{}", program);
+            lengths.push(program.len());
+        }
+        assert!(lengths.contains(&0));
+        assert!(lengths.iter().any(|&length| length > 100));
+
+        // The depth bounds the length of the derivations
+        let shallow: usize = (0..200)
+            .map(|seed| Generator::new(seed, 4).derivation(&bnf).len())
+            .sum();
+        assert!(shallow < lengths.iter().sum());
+    }
+
+    #[test]
+    fn derivations_from_another_grammar() {
+        let grammar = C1_GRAMMAR.replace(
+            "statement           ::= ifstatement",
+            "statement           ::= <KW_WHILE> \"(\" assignment \")\" block | ifstatement",
+        );
+        let bnf = Grammar::parse(&grammar).unwrap().to_bnf();
+        let programs: Vec<String> = (0..200)
+            .map(|seed| Generator::new(seed, 14).derivation(&bnf))
+            .collect();
+        assert!(programs.iter().any(|program| program.contains("while (")));
+    }
+
+    #[test]
+    fn typed_programs_are_valid_and_terminate() {
+        for seed in 0..200 {
+            let program = Generator::new(seed, 4).typed_program();
+            assert_eq!(program, Generator::new(seed, 4).typed_program());
+            let ast = C1Parser::parse_ast(&program).unwrap();
+            let analysis = checker::check(&program, &ast);
+            assert_eq!(analysis.diagnostics, vec![], "{}", program);
+            // Functions only call the functions defined before them
+            for reference in &analysis.references {
+                let Symbol::Function(callee) = reference.symbol else {
+                    continue;
+                };
+                let caller = analysis
+                    .functions
+                    .iter()
+                    .position(|function| function.definition.contains(&reference.span.start))
+                    .unwrap();
+                assert!(
+                    callee < caller || reference.span == analysis.functions[callee].span,
+                    "{}",
+                    program
+                );
+            }
+        }
+    }
+}
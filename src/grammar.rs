@@ -0,0 +1,830 @@
+use crate::lexer::{C1Lexer, C1Token};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Write};
+
+/// The grammar of C(-1) as shipped with the repository
+pub const C1_GRAMMAR: &str = include_str!("../c-1-syntax.ebnf");
+
+/// A terminal symbol: a token of the lexer or the end of the input
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone)]
+pub enum Terminal {
+    Token(C1Token),
+    Eof,
+}
+
+impl fmt::Display for Terminal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Terminal::Token(token) => write!(f, "{}", token.description()),
+            Terminal::Eof => write!(f, "end of file"),
+        }
+    }
+}
+
+/// The right-hand side of a rule in the EBNF
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Expression {
+    Terminal(Terminal),
+    Nonterminal(String),
+    Sequence(Vec<Expression>),
+    Alternatives(Vec<Expression>),
+    /// `( ... )*`
+    Repetition(Box<Expression>),
+    /// `( ... )?`
+    Optional(Box<Expression>),
+}
+
+/// A rule of the EBNF, e.g. `term ::= factor ( ( "*" | "/" | "&&" ) factor )*`
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Definition {
+    pub name: String,
+    pub expression: Expression,
+    /// The line of the rule in the grammar file, starting at 1
+    pub line: usize,
+}
+
+/// Problems in a grammar file
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum GrammarError {
+    /// The text does not follow the EBNF dialect
+    Syntax { line: usize, message: String },
+    /// A `<TOKEN>` or quoted terminal that is not a token of the lexer
+    UnknownTerminal { line: usize, terminal: String },
+    /// A nonterminal that is used but never defined
+    UndefinedNonterminal { line: usize, name: String },
+    /// A nonterminal that is defined twice
+    DuplicateDefinition { line: usize, name: String },
+}
+
+impl fmt::Display for GrammarError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GrammarError::Syntax { line, message } => write!(f, "{}: {}", line, message),
+            GrammarError::UnknownTerminal { line, terminal } => {
+                write!(f, "{}: '{}' is not a token of C(-1)", line, terminal)
+            }
+            GrammarError::UndefinedNonterminal { line, name } => {
+                write!(f, "{}: '{}' is used but not defined", line, name)
+            }
+            GrammarError::DuplicateDefinition { line, name } => {
+                write!(f, "{}: '{}' is defined more than once", line, name)
+            }
+        }
+    }
+}
+
+/// # Overview
+/// A grammar in the EBNF dialect of `c-1-syntax.ebnf`: rules of the form `name ::= expression`,
+/// where expressions consist of nonterminal names, `<TOKEN>` names and quoted terminals, and are
+/// combined with `|`, parentheses, `( )*` and `( )?`. The first rule defines the start symbol.
+///
+/// Terminals are mapped to the tokens of the lexer: `<ID>` is an identifier, `<EOF>` the end of
+/// the input, `<KW_IF>` or `<CONST_INT>` are keywords and constants, and quoted terminals are
+/// lexed like C(-1) source text.
+///
+/// # Examples
+/// ```
+/// use cb_3::grammar::{Expression, Grammar, Terminal};
+/// use cb_3::C1Token;
+///
+/// let grammar = Grammar::parse("list ::= ( <ID> \",\" )* <EOF>").unwrap();
+/// let Expression::Sequence(items) = &grammar.definitions[0].expression else { panic!() };
+/// assert_eq!(items[1], Expression::Terminal(Terminal::Eof));
+/// assert_eq!(
+///     items[0],
+///     Expression::Repetition(Box::new(Expression::Sequence(vec![
+///         Expression::Terminal(Terminal::Token(C1Token::Identifier)),
+///         Expression::Terminal(Terminal::Token(C1Token::Comma)),
+///     ])))
+/// );
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Grammar {
+    pub definitions: Vec<Definition>,
+}
+
+impl Grammar {
+    /// Parse a grammar and check that all terminals are tokens and all nonterminals are defined
+    pub fn parse(text: &str) -> Result<Grammar, GrammarError> {
+        let mut parser = EbnfParser {
+            tokens: ebnf_tokens(text)?,
+            position: 0,
+        };
+        let mut definitions: Vec<Definition> = Vec::new();
+        while parser.current().is_some() {
+            let definition = parser.definition()?;
+            if definitions
+                .iter()
+                .any(|other| other.name == definition.name)
+            {
+                return Err(GrammarError::DuplicateDefinition {
+                    line: definition.line,
+                    name: definition.name,
+                });
+            }
+            definitions.push(definition);
+        }
+        for definition in &definitions {
+            let mut names = Vec::new();
+            nonterminals(&definition.expression, &mut names);
+            if let Some(name) = names
+                .into_iter()
+                .find(|name| definitions.iter().all(|other| &other.name != name))
+            {
+                return Err(GrammarError::UndefinedNonterminal {
+                    line: definition.line,
+                    name: name.to_owned(),
+                });
+            }
+        }
+        Ok(Grammar { definitions })
+    }
+
+    /// Return the grammar of `c-1-syntax.ebnf`
+    pub fn c1() -> Grammar {
+        Grammar::parse(C1_GRAMMAR).expect("the shipped grammar is valid")
+    }
+
+    /// Convert the grammar into plain BNF. Every rule becomes a nonterminal with one production
+    /// per alternative. Nested groups become helper nonterminals named after their rule, e.g.
+    /// `term.1`: a repetition `( X )*` becomes `N ::= X N | ε`, an option `( X )?` becomes
+    /// `N ::= X | ε`, and a group of alternatives gets one production per alternative.
+    pub fn to_bnf(&self) -> Bnf {
+        let mut bnf = Bnf {
+            nonterminals: self
+                .definitions
+                .iter()
+                .map(|definition| definition.name.clone())
+                .collect(),
+            productions: Vec::new(),
+        };
+        let indices: BTreeMap<&str, usize> = self
+            .definitions
+            .iter()
+            .enumerate()
+            .map(|(index, definition)| (definition.name.as_str(), index))
+            .collect();
+        for (index, definition) in self.definitions.iter().enumerate() {
+            let mut converter = BnfConverter {
+                bnf: &mut bnf,
+                indices: &indices,
+                rule: &definition.name,
+                helpers: 0,
+            };
+            converter.alternatives(index, &definition.expression);
+        }
+        bnf
+    }
+}
+
+/// Collect the names of all nonterminals used in the expression
+fn nonterminals<'a>(expression: &'a Expression, names: &mut Vec<&'a str>) {
+    match expression {
+        Expression::Terminal(_) => {}
+        Expression::Nonterminal(name) => names.push(name),
+        Expression::Sequence(items) | Expression::Alternatives(items) => {
+            for item in items {
+                nonterminals(item, names);
+            }
+        }
+        Expression::Repetition(inner) | Expression::Optional(inner) => nonterminals(inner, names),
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+enum EbnfTokenKind {
+    Name(String),
+    Define,
+    Bar,
+    Open,
+    Close,
+    Star,
+    Question,
+    /// `<NAME>`
+    Token(String),
+    /// `"text"`
+    Literal(String),
+}
+
+struct EbnfToken {
+    kind: EbnfTokenKind,
+    line: usize,
+}
+
+fn ebnf_tokens(text: &str) -> Result<Vec<EbnfToken>, GrammarError> {
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut chars = text.chars().peekable();
+    while let Some(char) = chars.next() {
+        let kind = match char {
+            '\n' => {
+                line += 1;
+                continue;
+            }
+            char if char.is_whitespace() => continue,
+            '|' => EbnfTokenKind::Bar,
+            '(' => EbnfTokenKind::Open,
+            ')' => EbnfTokenKind::Close,
+            '*' => EbnfTokenKind::Star,
+            '?' => EbnfTokenKind::Question,
+            ':' if chars.next_if_eq(&':').is_some() && chars.next_if_eq(&'=').is_some() => {
+                EbnfTokenKind::Define
+            }
+            '<' | '"' => {
+                let end = if char == '<' { '>' } else { '"' };
+                let mut content = String::new();
+                loop {
+                    match chars.next() {
+                        Some(next) if next == end => break,
+                        Some('\n') | None => {
+                            return Err(GrammarError::Syntax {
+                                line,
+                                message: format!("Missing '{}'", end),
+                            })
+                        }
+                        Some(next) => content.push(next),
+                    }
+                }
+                if char == '<' {
+                    EbnfTokenKind::Token(content)
+                } else {
+                    EbnfTokenKind::Literal(content)
+                }
+            }
+            char if char.is_ascii_alphabetic() || char == '_' => {
+                let mut name = String::from(char);
+                while let Some(next) =
+                    chars.next_if(|next| next.is_ascii_alphanumeric() || *next == '_')
+                {
+                    name.push(next);
+                }
+                EbnfTokenKind::Name(name)
+            }
+            char => {
+                return Err(GrammarError::Syntax {
+                    line,
+                    message: format!("Unexpected character '{}'", char),
+                })
+            }
+        };
+        tokens.push(EbnfToken { kind, line });
+    }
+    Ok(tokens)
+}
+
+struct EbnfParser {
+    tokens: Vec<EbnfToken>,
+    position: usize,
+}
+
+impl EbnfParser {
+    fn current(&self) -> Option<&EbnfTokenKind> {
+        self.tokens.get(self.position).map(|token| &token.kind)
+    }
+
+    /// Return the line of the current token, or of the last token at the end of the text
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.position)
+            .or(self.tokens.last())
+            .map_or(1, |token| token.line)
+    }
+
+    fn error(&self, message: &str) -> GrammarError {
+        GrammarError::Syntax {
+            line: self.line(),
+            message: message.to_owned(),
+        }
+    }
+
+    /// Check whether a new definition starts at the current token
+    fn at_definition(&self) -> bool {
+        matches!(self.current(), Some(EbnfTokenKind::Name(_)))
+            && matches!(
+                self.tokens.get(self.position + 1).map(|token| &token.kind),
+                Some(EbnfTokenKind::Define)
+            )
+    }
+
+    /// definition ::= name "::=" alternatives
+    fn definition(&mut self) -> Result<Definition, GrammarError> {
+        let line = self.line();
+        let Some(EbnfTokenKind::Name(name)) = self.current().cloned() else {
+            return Err(self.error("Expected the name of a rule"));
+        };
+        self.position += 1;
+        if self.current() != Some(&EbnfTokenKind::Define) {
+            return Err(self.error("Expected '::=' after the name of the rule"));
+        }
+        self.position += 1;
+        let expression = self.alternatives()?;
+        Ok(Definition {
+            name,
+            expression,
+            line,
+        })
+    }
+
+    /// alternatives ::= sequence ( "|" sequence )*
+    fn alternatives(&mut self) -> Result<Expression, GrammarError> {
+        let mut alternatives = vec![self.sequence()?];
+        while self.current() == Some(&EbnfTokenKind::Bar) {
+            self.position += 1;
+            alternatives.push(self.sequence()?);
+        }
+        Ok(if alternatives.len() == 1 {
+            alternatives.pop().unwrap()
+        } else {
+            Expression::Alternatives(alternatives)
+        })
+    }
+
+    /// sequence ::= ( item )+
+    fn sequence(&mut self) -> Result<Expression, GrammarError> {
+        let mut items = Vec::new();
+        while !self.at_definition() {
+            match self.current() {
+                Some(
+                    EbnfTokenKind::Name(_)
+                    | EbnfTokenKind::Token(_)
+                    | EbnfTokenKind::Literal(_)
+                    | EbnfTokenKind::Open,
+                ) => items.push(self.item()?),
+                _ => break,
+            }
+        }
+        match items.len() {
+            0 => Err(self.error("Expected a symbol or a group")),
+            1 => Ok(items.pop().unwrap()),
+            _ => Ok(Expression::Sequence(items)),
+        }
+    }
+
+    /// item ::= name | <TOKEN> | "literal" | "(" alternatives ")" ( "*" | "?" )?
+    fn item(&mut self) -> Result<Expression, GrammarError> {
+        let line = self.line();
+        let kind = self.current().cloned();
+        self.position += 1;
+        match kind {
+            Some(EbnfTokenKind::Name(name)) => Ok(Expression::Nonterminal(name)),
+            Some(EbnfTokenKind::Token(name)) => named_terminal(&name)
+                .map(Expression::Terminal)
+                .ok_or(GrammarError::UnknownTerminal {
+                    line,
+                    terminal: format!("<{}>", name),
+                }),
+            Some(EbnfTokenKind::Literal(text)) => literal_terminal(&text)
+                .map(Expression::Terminal)
+                .ok_or(GrammarError::UnknownTerminal {
+                    line,
+                    terminal: format!("\"{}\"", text),
+                }),
+            _ => {
+                let inner = self.alternatives()?;
+                if self.current() != Some(&EbnfTokenKind::Close) {
+                    return Err(self.error("Expected ')' to close the group"));
+                }
+                self.position += 1;
+                match self.current() {
+                    Some(EbnfTokenKind::Star) => {
+                        self.position += 1;
+                        Ok(Expression::Repetition(Box::new(inner)))
+                    }
+                    Some(EbnfTokenKind::Question) => {
+                        self.position += 1;
+                        Ok(Expression::Optional(Box::new(inner)))
+                    }
+                    _ => Ok(inner),
+                }
+            }
+        }
+    }
+}
+
+/// The tokens that are written as `<NAME>` in the grammar
+const NAMED_TOKENS: [(&str, C1Token); 16] = [
+    ("ID", C1Token::Identifier),
+    ("KW_BOOLEAN", C1Token::KwBoolean),
+    ("KW_DO", C1Token::KwDo),
+    ("KW_ELSE", C1Token::KwElse),
+    ("KW_FLOAT", C1Token::KwFloat),
+    ("KW_FOR", C1Token::KwFor),
+    ("KW_IF", C1Token::KwIf),
+    ("KW_INT", C1Token::KwInt),
+    ("KW_PRINTF", C1Token::KwPrintf),
+    ("KW_RETURN", C1Token::KwReturn),
+    ("KW_VOID", C1Token::KwVoid),
+    ("KW_WHILE", C1Token::KwWhile),
+    ("CONST_INT", C1Token::ConstInt),
+    ("CONST_FLOAT", C1Token::ConstFloat),
+    ("CONST_BOOLEAN", C1Token::ConstBoolean),
+    ("CONST_STRING", C1Token::ConstString),
+];
+
+/// Map a `<TOKEN>` name to a terminal
+fn named_terminal(name: &str) -> Option<Terminal> {
+    if name == "EOF" {
+        return Some(Terminal::Eof);
+    }
+    NAMED_TOKENS
+        .iter()
+        .find(|(other, _)| *other == name)
+        .map(|(_, token)| Terminal::Token(*token))
+}
+
+/// Map a quoted terminal to the single token it is lexed as
+fn literal_terminal(text: &str) -> Option<Terminal> {
+    let mut lexer = C1Lexer::new(text);
+    let token = lexer.current_token()?;
+    let complete = lexer.current_text() == Some(text);
+    lexer.eat();
+    (complete && lexer.current_token().is_none() && token != C1Token::Error)
+        .then_some(Terminal::Token(token))
+}
+
+/// A symbol on the right-hand side of a BNF production
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone)]
+pub enum Symbol {
+    Terminal(Terminal),
+    /// The index of the nonterminal in [`Bnf::nonterminals`]
+    Nonterminal(usize),
+}
+
+/// A production `nonterminal ::= symbols`. An empty list of symbols derives the empty word.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Production {
+    pub nonterminal: usize,
+    pub symbols: Vec<Symbol>,
+}
+
+/// A sequence of at most k terminals that can start a derivation
+pub type Lookahead = Vec<Terminal>;
+
+/// A grammar in plain BNF, as produced by [`Grammar::to_bnf`]. The first nonterminal is the start
+/// symbol.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Bnf {
+    pub nonterminals: Vec<String>,
+    pub productions: Vec<Production>,
+}
+
+impl Bnf {
+    /// Return the production as text, e.g. `term.1 ::= term.2 factor term.1`
+    pub fn production_to_string(&self, production: usize) -> String {
+        let production = &self.productions[production];
+        let mut text = format!("{} ::=", self.nonterminals[production.nonterminal]);
+        if production.symbols.is_empty() {
+            text.push_str(" ε");
+        }
+        for symbol in &production.symbols {
+            match symbol {
+                Symbol::Terminal(terminal) => write_terminal(&mut text, terminal),
+                Symbol::Nonterminal(index) => {
+                    text.push(' ');
+                    text.push_str(&self.nonterminals[*index]);
+                }
+            }
+        }
+        text
+    }
+
+    /// Compute the FIRST_k set of every nonterminal: the sequences of up to k terminals that the
+    /// words derived from the nonterminal can start with. A sequence shorter than k is a complete
+    /// word, so the empty sequence stands for the empty word.
+    pub fn first_sets(&self, k: usize) -> Vec<BTreeSet<Lookahead>> {
+        let mut first = vec![BTreeSet::new(); self.nonterminals.len()];
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for production in &self.productions {
+                for lookahead in self.first(&production.symbols, k, &first) {
+                    changed |= first[production.nonterminal].insert(lookahead);
+                }
+            }
+        }
+        first
+    }
+
+    /// Compute the FOLLOW_k set of every nonterminal: the sequences of up to k terminals that can
+    /// follow it in a derivation from the start symbol. A sequence shorter than k reaches the end
+    /// of the derivation.
+    pub fn follow_sets(&self, k: usize, first: &[BTreeSet<Lookahead>]) -> Vec<BTreeSet<Lookahead>> {
+        let mut follow = vec![BTreeSet::new(); self.nonterminals.len()];
+        if !follow.is_empty() {
+            follow[0].insert(Vec::new());
+        }
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for production in &self.productions {
+                for (position, symbol) in production.symbols.iter().enumerate() {
+                    let Symbol::Nonterminal(nonterminal) = symbol else {
+                        continue;
+                    };
+                    let rest = self.first(&production.symbols[position + 1..], k, first);
+                    let following = concatenate(&rest, &follow[production.nonterminal], k);
+                    for lookahead in following {
+                        changed |= follow[*nonterminal].insert(lookahead);
+                    }
+                }
+            }
+        }
+        follow
+    }
+
+    /// Compute the FIRST_k set of a sequence of symbols from the FIRST_k sets of the nonterminals
+    pub fn first(
+        &self,
+        symbols: &[Symbol],
+        k: usize,
+        first: &[BTreeSet<Lookahead>],
+    ) -> BTreeSet<Lookahead> {
+        let mut result = BTreeSet::from([Vec::new()]);
+        for symbol in symbols {
+            if result.iter().all(|lookahead| lookahead.len() >= k) {
+                break;
+            }
+            result = match symbol {
+                Symbol::Terminal(terminal) => {
+                    concatenate(&result, &BTreeSet::from([vec![*terminal]]), k)
+                }
+                Symbol::Nonterminal(index) => concatenate(&result, &first[*index], k),
+            };
+        }
+        result
+    }
+
+    /// Compute the lookahead set of every production: FIRST_k of its symbols followed by
+    /// FOLLOW_k of its nonterminal
+    pub fn lookahead_sets(&self, k: usize) -> Vec<BTreeSet<Lookahead>> {
+        let first = self.first_sets(k);
+        let follow = self.follow_sets(k, &first);
+        self.productions
+            .iter()
+            .map(|production| {
+                concatenate(
+                    &self.first(&production.symbols, k, &first),
+                    &follow[production.nonterminal],
+                    k,
+                )
+            })
+            .collect()
+    }
+}
+
+/// Append the terminal as it is written in the grammar, keywords are written as quoted terminals
+fn write_terminal(text: &mut String, terminal: &Terminal) {
+    let Terminal::Token(token) = terminal else {
+        text.push_str(" <EOF>");
+        return;
+    };
+    let description = token.description();
+    if let Some(quoted) = description.strip_prefix('\'') {
+        write!(text, " \"{}\"", quoted.trim_end_matches('\'')).unwrap();
+    } else if let Some((name, _)) = NAMED_TOKENS.iter().find(|(_, other)| other == token) {
+        write!(text, " <{}>", name).unwrap();
+    } else {
+        write!(text, " <{:?}>", token).unwrap();
+    }
+}
+
+/// Concatenate every sequence of the left set with every sequence of the right set, truncated to
+/// k terminals. Sequences that are complete already are not extended.
+fn concatenate(
+    left: &BTreeSet<Lookahead>,
+    right: &BTreeSet<Lookahead>,
+    k: usize,
+) -> BTreeSet<Lookahead> {
+    let mut result = BTreeSet::new();
+    for prefix in left {
+        if prefix.len() >= k {
+            result.insert(prefix.clone());
+            continue;
+        }
+        for suffix in right {
+            let mut lookahead = prefix.clone();
+            lookahead.extend(suffix.iter().take(k - prefix.len()));
+            result.insert(lookahead);
+        }
+    }
+    result
+}
+
+struct BnfConverter<'a> {
+    bnf: &'a mut Bnf,
+    indices: &'a BTreeMap<&'a str, usize>,
+    rule: &'a str,
+    helpers: usize,
+}
+
+impl BnfConverter<'_> {
+    /// Add one production of the nonterminal per alternative of the expression
+    fn alternatives(&mut self, nonterminal: usize, expression: &Expression) {
+        let alternatives = match expression {
+            Expression::Alternatives(alternatives) => alternatives.as_slice(),
+            expression => std::slice::from_ref(expression),
+        };
+        for alternative in alternatives {
+            let symbols = self.sequence(alternative);
+            self.bnf.productions.push(Production {
+                nonterminal,
+                symbols,
+            });
+        }
+    }
+
+    fn sequence(&mut self, expression: &Expression) -> Vec<Symbol> {
+        match expression {
+            Expression::Sequence(items) => items.iter().map(|item| self.symbol(item)).collect(),
+            expression => vec![self.symbol(expression)],
+        }
+    }
+
+    fn symbol(&mut self, expression: &Expression) -> Symbol {
+        match expression {
+            Expression::Terminal(terminal) => Symbol::Terminal(*terminal),
+            Expression::Nonterminal(name) => Symbol::Nonterminal(self.indices[name.as_str()]),
+            Expression::Sequence(_) | Expression::Alternatives(_) => {
+                let helper = self.helper();
+                self.alternatives(helper, expression);
+                Symbol::Nonterminal(helper)
+            }
+            Expression::Repetition(inner) => {
+                let helper = self.helper();
+                let mut symbols = self.sequence_or_group(inner);
+                symbols.push(Symbol::Nonterminal(helper));
+                self.push(helper, symbols);
+                self.push(helper, Vec::new());
+                Symbol::Nonterminal(helper)
+            }
+            Expression::Optional(inner) => {
+                let helper = self.helper();
+                let symbols = self.sequence_or_group(inner);
+                self.push(helper, symbols);
+                self.push(helper, Vec::new());
+                Symbol::Nonterminal(helper)
+            }
+        }
+    }
+
+    /// Return the symbols of a sequence, or a helper nonterminal for a group of alternatives
+    fn sequence_or_group(&mut self, expression: &Expression) -> Vec<Symbol> {
+        match expression {
+            Expression::Alternatives(_) => vec![self.symbol(expression)],
+            expression => self.sequence(expression),
+        }
+    }
+
+    fn push(&mut self, nonterminal: usize, symbols: Vec<Symbol>) {
+        self.bnf.productions.push(Production {
+            nonterminal,
+            symbols,
+        });
+    }
+
+    /// Add a new helper nonterminal for the current rule
+    fn helper(&mut self) -> usize {
+        self.helpers += 1;
+        self.bnf
+            .nonterminals
+            .push(format!("{}.{}", self.rule, self.helpers));
+        self.bnf.nonterminals.len() - 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::grammar::{Grammar, GrammarError, Symbol, Terminal};
+    use crate::lexer::C1Token;
+    use std::collections::BTreeSet;
+
+    fn token(token: C1Token) -> Terminal {
+        Terminal::Token(token)
+    }
+
+    #[test]
+    fn shipped_grammar() {
+        let grammar = Grammar::c1();
+        let names: Vec<&str> = grammar
+            .definitions
+            .iter()
+            .map(|definition| definition.name.as_str())
+            .collect();
+        assert_eq!(names[0], "program");
+        assert_eq!(names.len(), 16);
+        assert_eq!(grammar.definitions[4].line, 7);
+
+        let bnf = grammar.to_bnf();
+        let productions: Vec<String> = (0..bnf.productions.len())
+            .filter(|&index| {
+                bnf.nonterminals[bnf.productions[index].nonterminal].starts_with("term")
+            })
+            .map(|index| bnf.production_to_string(index))
+            .collect();
+        assert_eq!(
+            productions,
+            vec![
+                "term.2 ::= \"*\"",
+                "term.2 ::= \"/\"",
+                "term.2 ::= \"&&\"",
+                "term.1 ::= term.2 factor term.1",
+                "term.1 ::= ε",
+                "term ::= factor term.1",
+            ]
+        );
+        let program = bnf
+            .productions
+            .iter()
+            .find(|production| production.nonterminal == 0)
+            .unwrap();
+        assert_eq!(
+            program.symbols.last(),
+            Some(&Symbol::Terminal(Terminal::Eof))
+        );
+    }
+
+    #[test]
+    fn first_and_follow_sets() {
+        let bnf = Grammar::c1().to_bnf();
+        let index = |name: &str| {
+            bnf.nonterminals
+                .iter()
+                .position(|other| other == name)
+                .unwrap()
+        };
+        let first = bnf.first_sets(1);
+        let follow = bnf.follow_sets(1, &first);
+        let set = |tokens: &[C1Token]| -> BTreeSet<Vec<Terminal>> {
+            tokens.iter().map(|t| vec![token(*t)]).collect()
+        };
+
+        assert_eq!(
+            first[index("factor")],
+            set(&[
+                C1Token::ConstInt,
+                C1Token::ConstFloat,
+                C1Token::ConstBoolean,
+                C1Token::Identifier,
+                C1Token::LeftParenthesis
+            ])
+        );
+        // The optional value of a return statement makes it end after the keyword
+        assert_eq!(follow[index("returnstatement")], set(&[C1Token::Semicolon]));
+        assert_eq!(follow[index("statementlist")], set(&[C1Token::RightBrace]));
+        // statementlist derives the empty word
+        assert!(first[index("statementlist")].contains(&Vec::new()));
+
+        let first = bnf.first_sets(2);
+        assert!(
+            first[index("statassignment")]
+                .iter()
+                .all(|lookahead| lookahead[..]
+                    == [token(C1Token::Identifier), token(C1Token::Assign)])
+        );
+        assert!(first[index("factor")].contains(&vec![token(C1Token::Identifier)]));
+        assert!(first[index("factor")].contains(&vec![
+            token(C1Token::Identifier),
+            token(C1Token::LeftParenthesis)
+        ]));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            Grammar::parse("a ::= b"),
+            Err(GrammarError::UndefinedNonterminal {
+                line: 1,
+                name: "b".into()
+            })
+        );
+        assert_eq!(
+            Grammar::parse("a ::= \"x\"\na ::= \"y\""),
+            Err(GrammarError::DuplicateDefinition {
+                line: 2,
+                name: "a".into()
+            })
+        );
+        assert_eq!(
+            Grammar::parse("a ::= <NUMBER>"),
+            Err(GrammarError::UnknownTerminal {
+                line: 1,
+                terminal: "<NUMBER>".into()
+            })
+        );
+        assert_eq!(
+            Grammar::parse("a ::= \"x y\""),
+            Err(GrammarError::UnknownTerminal {
+                line: 1,
+                terminal: "\"x y\"".into()
+            })
+        );
+        assert_eq!(
+            Grammar::parse("a ::= ( \"x\"\n"),
+            Err(GrammarError::Syntax {
+                line: 1,
+                message: "Expected ')' to close the group".into()
+            })
+        );
+        assert!(Grammar::parse("a ::= | \"x\"").is_err());
+    }
+}
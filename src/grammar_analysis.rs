@@ -0,0 +1,311 @@
+use crate::grammar::{Bnf, Grammar, Lookahead, Symbol, Terminal};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt;
+
+/// Productions of one nonterminal that cannot be told apart by the lookahead
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Conflict {
+    pub nonterminal: usize,
+    /// The tokens of lookahead the productions share
+    pub lookahead: Lookahead,
+    /// The indices of the productions in [`Bnf::productions`]
+    pub productions: Vec<usize>,
+}
+
+/// # Overview
+/// The properties of a grammar that matter for a predictive parser, computed on its BNF form
+/// (see [`Grammar::to_bnf`]):
+///
+/// - which nonterminals derive the empty word (nullable),
+/// - the FIRST and FOLLOW sets for one token of lookahead,
+/// - the LL(1) conflicts, i.e. productions of the same nonterminal that can start with the same
+///   token, and the LL(2) conflicts among them that two tokens of lookahead cannot resolve either,
+/// - left recursion, which makes a predictive parser loop forever,
+/// - nonterminals that cannot be reached from the start symbol.
+///
+/// The analysis is displayed as a report.
+///
+/// # Examples
+/// ```
+/// use cb_3::grammar::Grammar;
+/// use cb_3::grammar_analysis::GrammarAnalysis;
+///
+/// let analysis = GrammarAnalysis::new(&Grammar::c1());
+/// let nonterminals: Vec<&str> = analysis
+///     .ll1_conflicts
+///     .iter()
+///     .map(|conflict| analysis.bnf.nonterminals[conflict.nonterminal].as_str())
+///     .collect();
+/// assert_eq!(nonterminals, vec!["statement", "assignment", "factor"]);
+/// assert!(analysis.ll2_conflicts.is_empty());
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct GrammarAnalysis {
+    pub bnf: Bnf,
+    pub nullable: Vec<bool>,
+    /// The tokens each nonterminal can start with
+    pub first: Vec<BTreeSet<Terminal>>,
+    /// The tokens that can follow each nonterminal
+    pub follow: Vec<BTreeSet<Terminal>>,
+    pub ll1_conflicts: Vec<Conflict>,
+    pub ll2_conflicts: Vec<Conflict>,
+    /// Cycles of nonterminals that derive each other at their start, e.g. `[a, b]` for
+    /// `a ::= b "x"` and `b ::= a "y"`
+    pub left_recursion: Vec<Vec<usize>>,
+    pub unreachable: Vec<usize>,
+}
+
+impl GrammarAnalysis {
+    pub fn new(grammar: &Grammar) -> GrammarAnalysis {
+        let bnf = grammar.to_bnf();
+        let first = bnf.first_sets(1);
+        let follow = bnf.follow_sets(1, &first);
+        let nullable: Vec<bool> = first.iter().map(|set| set.contains(&Vec::new())).collect();
+        GrammarAnalysis {
+            ll1_conflicts: conflicts(&bnf, 1),
+            ll2_conflicts: conflicts(&bnf, 2),
+            left_recursion: left_recursion(&bnf, &nullable),
+            unreachable: unreachable(&bnf),
+            first: first.iter().map(terminals).collect(),
+            follow: follow.iter().map(terminals).collect(),
+            nullable,
+            bnf,
+        }
+    }
+
+    /// Check whether a predictive parser with two tokens of lookahead can parse the grammar
+    pub fn is_ll2(&self) -> bool {
+        self.ll2_conflicts.is_empty() && self.left_recursion.is_empty()
+    }
+}
+
+/// Return the first token of every sequence, the empty sequence is left out
+fn terminals(lookaheads: &BTreeSet<Lookahead>) -> BTreeSet<Terminal> {
+    lookaheads
+        .iter()
+        .filter_map(|lookahead| lookahead.first().copied())
+        .collect()
+}
+
+/// Find the lookahead sequences of k tokens that belong to more than one production of the
+/// same nonterminal
+fn conflicts(bnf: &Bnf, k: usize) -> Vec<Conflict> {
+    let mut productions: BTreeMap<(usize, &Lookahead), Vec<usize>> = BTreeMap::new();
+    let lookahead_sets = bnf.lookahead_sets(k);
+    for (index, lookaheads) in lookahead_sets.iter().enumerate() {
+        for lookahead in lookaheads {
+            productions
+                .entry((bnf.productions[index].nonterminal, lookahead))
+                .or_default()
+                .push(index);
+        }
+    }
+    productions
+        .into_iter()
+        .filter(|(_, productions)| productions.len() > 1)
+        .map(|((nonterminal, lookahead), productions)| Conflict {
+            nonterminal,
+            lookahead: lookahead.clone(),
+            productions,
+        })
+        .collect()
+}
+
+/// Find the cycles of nonterminals that can derive a word starting with themselves. Every cycle
+/// is reported once, starting at its nonterminal with the lowest index.
+fn left_recursion(bnf: &Bnf, nullable: &[bool]) -> Vec<Vec<usize>> {
+    // The nonterminals that can appear at the start of a production of each nonterminal
+    let mut starts = vec![BTreeSet::new(); bnf.nonterminals.len()];
+    for production in &bnf.productions {
+        for symbol in &production.symbols {
+            match symbol {
+                Symbol::Nonterminal(index) => {
+                    starts[production.nonterminal].insert(*index);
+                    if !nullable[*index] {
+                        break;
+                    }
+                }
+                Symbol::Terminal(_) => break,
+            }
+        }
+    }
+
+    let mut cycles = Vec::new();
+    for start in 0..bnf.nonterminals.len() {
+        // Breadth-first search for the shortest path back to the start, only through
+        // nonterminals with a higher index
+        let mut parents: BTreeMap<usize, usize> = BTreeMap::new();
+        let mut queue = VecDeque::from([start]);
+        while let Some(nonterminal) = queue.pop_front() {
+            if starts[nonterminal].contains(&start) {
+                let mut cycle = vec![nonterminal];
+                while let Some(&parent) = parents.get(cycle.last().unwrap()) {
+                    cycle.push(parent);
+                }
+                cycle.reverse();
+                cycles.push(cycle);
+                break;
+            }
+            for &next in &starts[nonterminal] {
+                if next > start && !parents.contains_key(&next) {
+                    parents.insert(next, nonterminal);
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+    cycles
+}
+
+/// Find the nonterminals that do not appear in any derivation from the start symbol
+fn unreachable(bnf: &Bnf) -> Vec<usize> {
+    let mut reached = vec![false; bnf.nonterminals.len()];
+    let mut queue = VecDeque::new();
+    if !reached.is_empty() {
+        reached[0] = true;
+        queue.push_back(0);
+    }
+    while let Some(nonterminal) = queue.pop_front() {
+        for production in bnf
+            .productions
+            .iter()
+            .filter(|production| production.nonterminal == nonterminal)
+        {
+            for symbol in &production.symbols {
+                if let Symbol::Nonterminal(index) = symbol {
+                    if !reached[*index] {
+                        reached[*index] = true;
+                        queue.push_back(*index);
+                    }
+                }
+            }
+        }
+    }
+    (0..reached.len())
+        .filter(|&index| !reached[index])
+        .collect()
+}
+
+fn write_terminals(
+    f: &mut fmt::Formatter<'_>,
+    terminals: impl IntoIterator<Item = Terminal>,
+) -> fmt::Result {
+    let terminals: Vec<String> = terminals.into_iter().map(|t| t.to_string()).collect();
+    write!(f, "{}", terminals.join(", "))
+}
+
+/// The report lists the sets of every nonterminal, followed by all problems
+impl fmt::Display for GrammarAnalysis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names = &self.bnf.nonterminals;
+        for (index, name) in names.iter().enumerate() {
+            writeln!(f, "{}", name)?;
+            let nullable = if self.nullable[index] { "yes" } else { "no" };
+            writeln!(f, "  nullable: {}", nullable)?;
+            write!(f, "  FIRST:    ")?;
+            write_terminals(f, self.first[index].iter().copied())?;
+            write!(f, "\n  FOLLOW:   ")?;
+            write_terminals(f, self.follow[index].iter().copied())?;
+            writeln!(f)?;
+        }
+
+        for conflict in &self.ll1_conflicts {
+            write!(f, "\nLL(1) conflict in {} on ", names[conflict.nonterminal])?;
+            write_terminals(f, conflict.lookahead.iter().copied())?;
+            writeln!(f, ":")?;
+            for &production in &conflict.productions {
+                writeln!(f, "  {}", self.bnf.production_to_string(production))?;
+            }
+            let resolved = !self.ll2_conflicts.iter().any(|other| {
+                other.nonterminal == conflict.nonterminal
+                    && other.lookahead.first() == conflict.lookahead.first()
+            });
+            if resolved {
+                writeln!(f, "  resolved by the second token of lookahead")?;
+            }
+        }
+        for conflict in &self.ll2_conflicts {
+            write!(f, "\nLL(2) conflict in {} on ", names[conflict.nonterminal])?;
+            write_terminals(f, conflict.lookahead.iter().copied())?;
+            writeln!(f, ":")?;
+            for &production in &conflict.productions {
+                writeln!(f, "  {}", self.bnf.production_to_string(production))?;
+            }
+        }
+        for cycle in &self.left_recursion {
+            let cycle: Vec<&str> = cycle
+                .iter()
+                .chain(cycle.first())
+                .map(|&index| names[index].as_str())
+                .collect();
+            writeln!(f, "\nLeft recursion: {}", cycle.join(" -> "))?;
+        }
+        for &index in &self.unreachable {
+            writeln!(f, "\nUnreachable: {}", names[index])?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::grammar::{Grammar, Terminal};
+    use crate::grammar_analysis::GrammarAnalysis;
+    use crate::lexer::C1Token;
+
+    #[test]
+    fn shipped_grammar() {
+        let analysis = GrammarAnalysis::new(&Grammar::c1());
+        assert!(analysis.is_ll2());
+        assert!(analysis.unreachable.is_empty());
+        for conflict in &analysis.ll1_conflicts {
+            assert_eq!(
+                conflict.lookahead,
+                vec![Terminal::Token(C1Token::Identifier)]
+            );
+        }
+        let report = analysis.to_string();
+        assert!(report.contains(
+            "\nLL(1) conflict in statement on identifier:\n  \
+             statement ::= statassignment \";\"\n  \
+             statement ::= functioncall \";\"\n  \
+             resolved by the second token of lookahead\n"
+        ));
+        assert!(report
+            .contains("returnstatement\n  nullable: no\n  FIRST:    'return'\n  FOLLOW:   ';'\n"));
+        assert!(report.contains("statementlist\n  nullable: yes\n"));
+    }
+
+    #[test]
+    fn problems() {
+        let grammar = "\
+start ::= list <EOF>
+list ::= item | list \",\" item
+item ::= <ID> | \"(\" list \")\"
+lonely ::= other
+other ::= lonely \"x\"
+";
+        let analysis = GrammarAnalysis::new(&Grammar::parse(grammar).unwrap());
+        assert!(!analysis.is_ll2());
+        let name = |index: usize| analysis.bnf.nonterminals[index].as_str();
+        let cycles: Vec<Vec<&str>> = analysis
+            .left_recursion
+            .iter()
+            .map(|cycle| cycle.iter().map(|&index| name(index)).collect())
+            .collect();
+        assert_eq!(cycles, vec![vec!["list"], vec!["lonely", "other"]]);
+        let unreachable: Vec<&str> = analysis
+            .unreachable
+            .iter()
+            .map(|&index| name(index))
+            .collect();
+        assert_eq!(unreachable, vec!["lonely", "other"]);
+
+        let report = analysis.to_string();
+        assert!(report.contains(
+            "\nLL(2) conflict in list on identifier, ',':\n  list ::= item\n  list ::= list \",\" item\n"
+        ));
+        assert!(report.contains("\nLeft recursion: lonely -> other -> lonely\n"));
+        assert!(report.contains("\nUnreachable: other\n"));
+    }
+}
@@ -0,0 +1,367 @@
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::lexer::{C1Lexer, C1Token};
+use std::ops::Range;
+
+/// A style sheet for the output of [`html`]
+pub const STYLESHEET: &str = "\
+pre.c1 .keyword { color: #a626a4; font-weight: bold; }
+pre.c1 .type { color: #0184bc; }
+pre.c1 .literal { color: #986801; }
+pre.c1 .operator { color: #383a42; font-weight: bold; }
+pre.c1 .comment { color: #a0a1a7; font-style: italic; }
+pre.c1 .invalid { color: #e45649; }
+pre.c1 .underline-error { text-decoration: underline wavy #e45649; }
+pre.c1 .underline-warning { text-decoration: underline wavy #c18401; }
+pre.c1 .underline-note { text-decoration: underline dotted #4078f2; }
+";
+
+/// The highlighting category of a token
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+pub enum Category {
+    Keyword,
+    Type,
+    Literal,
+    Identifier,
+    Operator,
+    Punctuation,
+    Comment,
+    /// Text that is not a valid token
+    Invalid,
+    /// Whitespace and linebreaks, which are not highlighted
+    Whitespace,
+}
+
+impl Category {
+    /// Return the category of the token
+    /// ```
+    /// use cb_3::highlight::Category;
+    /// use cb_3::C1Token;
+    ///
+    /// assert_eq!(Category::of(C1Token::KwInt), Category::Type);
+    /// assert_eq!(Category::of(C1Token::ConstBoolean), Category::Literal);
+    /// ```
+    pub fn of(token: C1Token) -> Category {
+        match token {
+            C1Token::KwDo
+            | C1Token::KwElse
+            | C1Token::KwFor
+            | C1Token::KwIf
+            | C1Token::KwPrintf
+            | C1Token::KwReturn
+            | C1Token::KwWhile => Category::Keyword,
+            C1Token::KwBoolean | C1Token::KwFloat | C1Token::KwInt | C1Token::KwVoid => {
+                Category::Type
+            }
+            C1Token::ConstInt
+            | C1Token::ConstFloat
+            | C1Token::ConstBoolean
+            | C1Token::ConstString
+            | C1Token::UnterminatedString
+            | C1Token::MalformedFloat
+            | C1Token::OverflowingInt
+            | C1Token::MalformedInt => Category::Literal,
+            C1Token::Identifier | C1Token::UnicodeIdentifier => Category::Identifier,
+            C1Token::Plus
+            | C1Token::Minus
+            | C1Token::Asterisk
+            | C1Token::Slash
+            | C1Token::Assign
+            | C1Token::Equal
+            | C1Token::NotEqual
+            | C1Token::Less
+            | C1Token::Greater
+            | C1Token::LessEqual
+            | C1Token::GreaterEqual
+            | C1Token::And
+            | C1Token::Or => Category::Operator,
+            C1Token::Comma
+            | C1Token::Semicolon
+            | C1Token::LeftParenthesis
+            | C1Token::RightParenthesis
+            | C1Token::LeftBrace
+            | C1Token::RightBrace => Category::Punctuation,
+            C1Token::CComment | C1Token::CPPComment | C1Token::UnterminatedComment => {
+                Category::Comment
+            }
+            C1Token::Whitespace | C1Token::Linebreak => Category::Whitespace,
+            C1Token::Error => Category::Invalid,
+        }
+    }
+
+    /// Return the CSS class used by [`html`], or `None` for whitespace
+    pub fn class(&self) -> Option<&'static str> {
+        match self {
+            Category::Keyword => Some("keyword"),
+            Category::Type => Some("type"),
+            Category::Literal => Some("literal"),
+            Category::Identifier => Some("identifier"),
+            Category::Operator => Some("operator"),
+            Category::Punctuation => Some("punctuation"),
+            Category::Comment => Some("comment"),
+            Category::Invalid => Some("invalid"),
+            Category::Whitespace => None,
+        }
+    }
+
+    /// Return the parameters of the ANSI escape sequence used by [`ansi`], or `None` if the
+    /// category is printed in the default style
+    fn ansi(&self) -> Option<&'static str> {
+        match self {
+            Category::Keyword => Some("1;35"),
+            Category::Type => Some("36"),
+            Category::Literal => Some("33"),
+            Category::Operator => Some("1"),
+            Category::Comment => Some("90"),
+            Category::Invalid => Some("1;31"),
+            Category::Identifier | Category::Punctuation | Category::Whitespace => None,
+        }
+    }
+}
+
+/// A piece of the text that belongs to a single token and is covered by the same diagnostics
+struct Segment<'a> {
+    text: &'a str,
+    category: Category,
+    diagnostics: Vec<&'a Diagnostic>,
+}
+
+impl Segment<'_> {
+    /// Return the most severe of the covering diagnostics
+    fn severity(&self) -> Option<Severity> {
+        self.diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.severity)
+            .min_by_key(|severity| match severity {
+                Severity::Error => 0,
+                Severity::Warning => 1,
+                Severity::Note => 2,
+            })
+    }
+}
+
+/// # Overview
+/// Highlight the text as HTML. The result is a `pre` element of class `c1`, in which every token
+/// except whitespace is wrapped in a `span` whose class is the [`Category::class`] of the token.
+/// [`STYLESHEET`] contains matching styles.
+///
+/// Text covered by one of the diagnostics additionally gets the class `underline-error`,
+/// `underline-warning` or `underline-note` and the diagnostic messages as title. Diagnostics
+/// without extent underline the character they point to.
+///
+/// # Examples
+/// ```
+/// use cb_3::highlight::html;
+///
+/// assert_eq!(
+///     html("x = 1;", &[]),
+///     "<pre class=\"c1\"><span class=\"identifier\">x</span> <span class=\"operator\">=</span> \
+///      <span class=\"literal\">1</span><span class=\"punctuation\">;</span></pre>"
+/// );
+/// ```
+pub fn html(text: &str, diagnostics: &[Diagnostic]) -> String {
+    let mut result = String::from("<pre class=\"c1\">");
+    for segment in segments(text, diagnostics) {
+        let mut classes: Vec<&str> = segment.category.class().into_iter().collect();
+        classes.extend(segment.severity().map(|severity| match severity {
+            Severity::Error => "underline-error",
+            Severity::Warning => "underline-warning",
+            Severity::Note => "underline-note",
+        }));
+        if classes.is_empty() {
+            result.push_str(&escape(segment.text));
+            continue;
+        }
+        result.push_str(&format!("<span class=\"{}\"", classes.join(" ")));
+        if !segment.diagnostics.is_empty() {
+            let messages: Vec<&str> = segment
+                .diagnostics
+                .iter()
+                .map(|diagnostic| diagnostic.message.as_str())
+                .collect();
+            result.push_str(&format!(" title=\"{}\"", escape(&messages.join("\n"))));
+        }
+        result.push_str(&format!(">{}</span>", escape(segment.text)));
+    }
+    result.push_str("</pre>");
+    result
+}
+
+/// Highlight the text with ANSI escape sequences for terminals. Text covered by one of the
+/// diagnostics is underlined, diagnostics without extent underline the character they point to.
+/// ```
+/// use cb_3::highlight::ansi;
+///
+/// assert_eq!(ansi("int x", &[]), "\x1b[36mint\x1b[0m x");
+/// ```
+pub fn ansi(text: &str, diagnostics: &[Diagnostic]) -> String {
+    let mut result = String::new();
+    for segment in segments(text, diagnostics) {
+        let mut parameters: Vec<&str> = segment.category.ansi().into_iter().collect();
+        if !segment.diagnostics.is_empty() {
+            parameters.push("4");
+        }
+        if parameters.is_empty() {
+            result.push_str(segment.text);
+        } else {
+            result.push_str(&format!(
+                "\x1b[{}m{}\x1b[0m",
+                parameters.join(";"),
+                segment.text
+            ));
+        }
+    }
+    result
+}
+
+/// Split the text into segments at all token boundaries and at the boundaries of the diagnostics
+fn segments<'a>(text: &'a str, diagnostics: &'a [Diagnostic]) -> Vec<Segment<'a>> {
+    let spans: Vec<(Range<usize>, &Diagnostic)> = diagnostics
+        .iter()
+        .map(|diagnostic| (span(text, diagnostic), diagnostic))
+        .collect();
+    let mut segments = Vec::new();
+    for token in C1Lexer::with_trivia(text) {
+        let token_span = token.span;
+        let mut boundaries = vec![token_span.start, token_span.end];
+        for (span, _) in &spans {
+            boundaries.extend(
+                [span.start, span.end]
+                    .into_iter()
+                    .filter(|offset| token_span.contains(offset)),
+            );
+        }
+        boundaries.sort_unstable();
+        boundaries.dedup();
+        for range in boundaries.windows(2) {
+            segments.push(Segment {
+                text: &text[range[0]..range[1]],
+                category: Category::of(token.kind),
+                diagnostics: spans
+                    .iter()
+                    .filter(|(span, _)| span.start <= range[0] && range[1] <= span.end)
+                    .map(|(_, diagnostic)| *diagnostic)
+                    .collect(),
+            });
+        }
+    }
+    segments
+}
+
+/// Return the byte range of the diagnostic. A diagnostic without extent covers the following
+/// character, or the last character at the end of the text.
+fn span(text: &str, diagnostic: &Diagnostic) -> Range<usize> {
+    let start = diagnostic.start.offset(text);
+    let end = diagnostic.end.offset(text).max(start);
+    if start < end {
+        return start..end;
+    }
+    match text[start..].chars().next() {
+        Some(char) => start..start + char.len_utf8(),
+        None => match text.chars().next_back() {
+            Some(char) => text.len() - char.len_utf8()..text.len(),
+            None => start..end,
+        },
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diagnostic::{Diagnostic, Position};
+    use crate::highlight::{ansi, html};
+
+    const EXAMPLE: &str = include_str!("../tests/data/beispiel.c-1");
+
+    /// Remove all tags and decode the entities of the HTML output
+    fn strip_tags(html: &str) -> String {
+        let mut text = String::new();
+        let mut in_tag = false;
+        for char in html.chars() {
+            match char {
+                '<' => in_tag = true,
+                '>' => in_tag = false,
+                _ if !in_tag => text.push(char),
+                _ => {}
+            }
+        }
+        text.replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&quot;", "\"")
+            .replace("&amp;", "&")
+    }
+
+    fn error(start: (usize, usize), end: (usize, usize)) -> Diagnostic {
+        Diagnostic::error(
+            "<problem>",
+            Position {
+                line: start.0,
+                column: start.1,
+            },
+            Position {
+                line: end.0,
+                column: end.1,
+            },
+        )
+    }
+
+    #[test]
+    fn the_text_is_preserved() {
+        assert_eq!(strip_tags(&html(EXAMPLE, &[])), EXAMPLE);
+        let text = "if (a < b && c) /* \"x\" */ printf(\"<&>\"); # $";
+        assert_eq!(strip_tags(&html(text, &[error((1, 5), (1, 20))])), text);
+        assert!(
+            html(text, &[]).contains("<span class=\"literal\">&quot;&lt;&amp;&gt;&quot;</span>")
+        );
+    }
+
+    #[test]
+    fn categories() {
+        let text = "void f() { // answer\n return 4.2 + true; }";
+        let html = html(text, &[]);
+        assert!(html.starts_with("<pre class=\"c1\"><span class=\"type\">void</span> "));
+        assert!(html.contains("<span class=\"comment\">// answer\n</span>"));
+        assert!(html.contains("<span class=\"keyword\">return</span>"));
+        assert!(html.contains("<span class=\"literal\">4.2</span>"));
+        assert!(html.contains("<span class=\"operator\">+</span>"));
+        assert!(html.contains("<span class=\"literal\">true</span>"));
+        assert!(html.ends_with("<span class=\"punctuation\">}</span></pre>"));
+        assert!(super::html("$", &[]).contains("<span class=\"invalid\">$</span>"));
+    }
+
+    #[test]
+    fn diagnostics_are_underlined() {
+        // The diagnostic starts inside of `blub` and ends behind the space
+        let html = html("int blub ();", &[error((1, 7), (1, 10))]);
+        assert!(html.contains(
+            "<span class=\"identifier\">bl</span>\
+             <span class=\"identifier underline-error\" title=\"&lt;problem&gt;\">ub</span>\
+             <span class=\"underline-error\" title=\"&lt;problem&gt;\"> </span>\
+             <span class=\"punctuation\">(</span>"
+        ));
+
+        // Without extent, the character at the position or the last character is underlined
+        let text = "x = 1";
+        assert!(super::html(text, &[error((1, 3), (1, 3))])
+            .contains("<span class=\"operator underline-error\""));
+        assert!(super::html(text, &[error((1, 6), (1, 6))])
+            .contains("<span class=\"literal underline-error\""));
+        assert_eq!(
+            super::html("", &[error((1, 1), (1, 1))]),
+            "<pre class=\"c1\"></pre>"
+        );
+    }
+
+    #[test]
+    fn ansi_escapes() {
+        assert_eq!(
+            ansi("x = 1; // y", &[error((1, 1), (1, 2))]),
+            "\x1b[4mx\x1b[0m \x1b[1m=\x1b[0m \x1b[33m1\x1b[0m; \x1b[90m// y\x1b[0m"
+        );
+    }
+}
@@ -0,0 +1,397 @@
+use crate::ast::{
+    Expression, ExpressionKind, FunctionDefinition, Program, Statement, StatementKind,
+};
+use crate::diagnostic::Diagnostic;
+use crate::lexer::C1Token;
+use crate::parser::C1Parser;
+use logos::Logos;
+use std::ops::Range;
+
+/// Replace the text in `range` by `replacement`
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Edit {
+    pub range: Range<usize>,
+    pub replacement: String,
+}
+
+/// What had to be done to bring a document up to date after an edit
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Update {
+    /// Number of tokens that were lexed again
+    pub relexed_tokens: usize,
+    /// Number of function definitions that were parsed again
+    pub reparsed_functions: usize,
+    /// Number of function definitions that were taken over from the previous tree
+    pub reused_functions: usize,
+}
+
+/// # Overview
+/// A source text together with its tokens and syntax tree, which are updated incrementally when the
+/// text is edited.
+///
+/// Only the tokens around the edit are lexed again: lexing starts at the first token that touches
+/// the edit and stops as soon as a token starts at the same place as before the edit. Only the
+/// function definitions that contain these tokens are parsed again, all other function definitions
+/// are reused and merely moved by the length difference of the edit. As long as the text contains
+/// a syntax error, the next edit parses the whole text again.
+///
+/// The tokens include trivia, i.e. whitespace, linebreaks and comments, so that they cover the
+/// complete text.
+///
+/// # Examples
+/// ```
+/// use cb_3::incremental::{Document, Edit};
+/// use cb_3::parser::C1Parser;
+///
+/// let mut document = Document::new("int f() { return 1; }\nint g() { return 2; }");
+/// let update = document.edit(&Edit { range: 17..18, replacement: String::from("42") });
+///
+/// assert_eq!(document.text(), "int f() { return 42; }\nint g() { return 2; }");
+/// assert_eq!((update.reparsed_functions, update.reused_functions), (1, 1));
+/// assert_eq!(document.program(), C1Parser::parse_ast(document.text()).as_ref().map_err(|e| &**e));
+/// ```
+pub struct Document {
+    text: String,
+    tokens: Vec<(C1Token, Range<usize>)>,
+    result: Result<Program, Box<Diagnostic>>,
+}
+
+impl Document {
+    /// Lex and parse the complete text
+    pub fn new(text: impl Into<String>) -> Document {
+        let text = text.into();
+        let tokens = lex(&text, 0).collect();
+        let result = C1Parser::parse_ast(&text);
+        Document {
+            text,
+            tokens,
+            result,
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Return all tokens of the text, including trivia
+    pub fn tokens(&self) -> &[(C1Token, Range<usize>)] {
+        &self.tokens
+    }
+
+    /// Return the syntax tree of the text or its first syntax error
+    pub fn program(&self) -> Result<&Program, &Diagnostic> {
+        self.result.as_ref().map_err(|diagnostic| &**diagnostic)
+    }
+
+    /// Apply the edit to the text and update the tokens and the syntax tree
+    pub fn edit(&mut self, edit: &Edit) -> Update {
+        let delta = edit.replacement.len() as isize - edit.range.len() as isize;
+        self.text
+            .replace_range(edit.range.clone(), &edit.replacement);
+        let mut update = Update::default();
+
+        // A token can depend on the text behind it: "1." is lexed differently from "1.5". Apart
+        // from comments, no token extends beyond a linebreak, so lexing restarts at the start of
+        // the line or at the start of a comment that reaches into it. An unclosed "/*" is an error
+        // token up to the end of the text, so an edit that may close it is covered as well.
+        let line_start = self.text[..edit.range.start]
+            .rfind('\n')
+            .map_or(0, |index| index + 1);
+        let first = self
+            .tokens
+            .partition_point(|(_, span)| span.end <= line_start);
+        let start = self
+            .tokens
+            .get(first)
+            .map_or(edit.range.start, |(_, span)| {
+                span.start.min(edit.range.start)
+            });
+        let edit_end = edit.range.start + edit.replacement.len();
+        let mut relexed = Vec::new();
+        let mut resume = self.tokens.len();
+        for (token, span) in lex(&self.text, start) {
+            // Lexing the rest would yield the same tokens as before as soon as a token after the
+            // edit starts where an old token started
+            if span.start >= edit_end {
+                let old_start = (span.start as isize - delta) as usize;
+                let index = self
+                    .tokens
+                    .partition_point(|(_, span)| span.start < old_start);
+                if index < self.tokens.len() && self.tokens[index].1.start == old_start {
+                    resume = index;
+                    break;
+                }
+            }
+            relexed.push((token, span));
+        }
+        let old_window = start
+            ..self
+                .tokens
+                .get(resume)
+                .map_or(self.old_length(delta), |(_, span)| span.start);
+        update.relexed_tokens = relexed.len();
+        let rest: Vec<(C1Token, Range<usize>)> = self.tokens[resume..]
+            .iter()
+            .map(|(token, span)| (*token, shift(span, delta)))
+            .collect();
+        self.tokens.truncate(first);
+        self.tokens.extend(relexed);
+        self.tokens.extend(rest);
+
+        self.reparse(old_window, delta, &mut update);
+        update
+    }
+
+    fn old_length(&self, delta: isize) -> usize {
+        (self.text.len() as isize - delta) as usize
+    }
+
+    /// Parse the function definitions that overlap with the given range of the old text again
+    fn reparse(&mut self, old_window: Range<usize>, delta: isize, update: &mut Update) {
+        let Ok(program) = &mut self.result else {
+            self.result = C1Parser::parse_ast(&self.text);
+            update.reparsed_functions = self
+                .result
+                .as_ref()
+                .map_or(0, |program| program.functions.len());
+            return;
+        };
+        let functions = std::mem::take(&mut program.functions);
+        // The functions that overlap with or touch the window are parsed again
+        let before = functions.partition_point(|function| function.span.end < old_window.start);
+        let after = functions.partition_point(|function| function.span.start <= old_window.end);
+        let region_start = functions[before..after]
+            .first()
+            .map_or(old_window.start, |function| {
+                function.span.start.min(old_window.start)
+            });
+        let region_end = functions[before..after]
+            .last()
+            .map_or(old_window.end, |function| {
+                function.span.end.max(old_window.end)
+            });
+        let mut region = region_start..(region_end as isize + delta) as usize;
+
+        let mut parsed = C1Parser::parse_range(&self.text, region.clone());
+        let mut reused_after = after;
+        if let Ok(parsed_functions) = &parsed {
+            // The last function definition consumed tokens of the following one, e.g. because its
+            // closing brace was removed, so everything up to the end has to be parsed again
+            if parsed_functions
+                .last()
+                .is_some_and(|function| function.span.end > region.end)
+            {
+                region.end = self.text.len();
+                parsed = C1Parser::parse_range(&self.text, region.clone());
+                reused_after = functions.len();
+            }
+        }
+
+        self.result = parsed.map(|parsed| {
+            update.reparsed_functions = parsed.len();
+            update.reused_functions = before + functions.len() - reused_after;
+            let mut functions = functions;
+            let mut rest = functions.split_off(reused_after);
+            functions.truncate(before);
+            for function in &mut rest {
+                shift_function(function, delta);
+            }
+            functions.extend(parsed);
+            functions.extend(rest);
+            Program {
+                functions,
+                file: None,
+            }
+        });
+    }
+}
+
+/// Lex the text from the given offset, which has to be the start of a token, to its end
+fn lex(text: &str, start: usize) -> impl Iterator<Item = (C1Token, Range<usize>)> + '_ {
+    let mut lexer = C1Token::lexer(text);
+    lexer.bump(start);
+    lexer.spanned()
+}
+
+fn shift(span: &Range<usize>, delta: isize) -> Range<usize> {
+    (span.start as isize + delta) as usize..(span.end as isize + delta) as usize
+}
+
+fn shift_function(function: &mut FunctionDefinition, delta: isize) {
+    function.span = shift(&function.span, delta);
+    function.name.span = shift(&function.name.span, delta);
+    for statement in &mut function.body {
+        shift_statement(statement, delta);
+    }
+}
+
+fn shift_statement(statement: &mut Statement, delta: isize) {
+    statement.span = shift(&statement.span, delta);
+    match &mut statement.kind {
+        StatementKind::Block(statements) => {
+            for statement in statements {
+                shift_statement(statement, delta);
+            }
+        }
+        StatementKind::If { condition, body } => {
+            shift_expression(condition, delta);
+            shift_statement(body, delta);
+        }
+        StatementKind::Return(None) => {}
+        StatementKind::Return(Some(value)) | StatementKind::Printf(value) => {
+            shift_expression(value, delta)
+        }
+        StatementKind::Assignment { target, value } => {
+            target.span = shift(&target.span, delta);
+            shift_expression(value, delta);
+        }
+        StatementKind::Call(function) => function.span = shift(&function.span, delta),
+    }
+}
+
+fn shift_expression(expression: &mut Expression, delta: isize) {
+    expression.span = shift(&expression.span, delta);
+    match &mut expression.kind {
+        ExpressionKind::Assignment { target, value } => {
+            target.span = shift(&target.span, delta);
+            shift_expression(value, delta);
+        }
+        ExpressionKind::Binary { left, right, .. } => {
+            shift_expression(left, delta);
+            shift_expression(right, delta);
+        }
+        ExpressionKind::Negation(operand) => shift_expression(operand, delta),
+        ExpressionKind::Literal(_) => {}
+        ExpressionKind::Call(identifier) | ExpressionKind::Variable(identifier) => {
+            identifier.span = shift(&identifier.span, delta)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::generator::Random;
+    use crate::incremental::{lex, Document, Edit};
+    use crate::parser::C1Parser;
+
+    const EXAMPLE: &str = include_str!("../tests/data/beispiel.c-1");
+
+    /// Check that the document is identical to a document created from scratch
+    fn assert_consistent(document: &Document) {
+        let tokens: Vec<_> = lex(document.text(), 0).collect();
+        assert_eq!(document.tokens(), tokens.as_slice(), "{}", document.text());
+        let expected = C1Parser::parse_ast(document.text());
+        assert_eq!(
+            document.program(),
+            expected.as_ref().map_err(|diagnostic| &**diagnostic),
+            "{}",
+            document.text()
+        );
+    }
+
+    fn edit(document: &mut Document, start: usize, end: usize, replacement: &str) {
+        document.edit(&Edit {
+            range: start..end,
+            replacement: replacement.to_owned(),
+        });
+        assert_consistent(document);
+    }
+
+    #[test]
+    fn only_the_enclosing_function_is_parsed() {
+        let mut document = Document::new(EXAMPLE);
+        // Rename blub2 to blub22 in the third line of blub
+        let update = document.edit(&Edit {
+            range: 32..32,
+            replacement: String::from("2"),
+        });
+        assert_consistent(&document);
+        assert_eq!(update.relexed_tokens, 2);
+        assert_eq!(update.reparsed_functions, 1);
+        assert_eq!(update.reused_functions, 2);
+    }
+
+    #[test]
+    fn edits_between_functions() {
+        let mut document = Document::new(EXAMPLE);
+        let gap = EXAMPLE.find("\n\nfloat").unwrap() + 1;
+        let function = "bool t() { return true; }";
+        // A new function between blub and blah
+        edit(&mut document, gap, gap, &format!("\n{}\n", function));
+        // A comment that swallows the new function and is then closed again
+        edit(&mut document, gap + 1, gap + 1, "/* ");
+        assert!(document.program().is_err());
+        let end = gap + 4 + function.len();
+        edit(&mut document, end, end, " */");
+        assert_eq!(document.program().unwrap().functions.len(), 3);
+        edit(&mut document, gap + 1, gap + 4, "");
+        edit(&mut document, end - 3, end, "");
+        assert_eq!(document.program().unwrap().functions.len(), 4);
+    }
+
+    #[test]
+    fn tokens_that_depend_on_later_text() {
+        let mut document = Document::new("void f() {\n  x = 1.;\n  /* a\n}");
+        // "1." becomes a float constant
+        edit(&mut document, 19, 19, "5");
+        // The comment is closed on the next line
+        edit(&mut document, 28, 28, " */");
+        assert!(document.program().is_ok());
+    }
+
+    #[test]
+    fn syntax_errors_are_recovered() {
+        let mut document = Document::new(EXAMPLE);
+        let end_of_blub = EXAMPLE.find("}\n\nfloat").unwrap();
+        // Removing the closing brace merges blub with blah, which is an error
+        edit(&mut document, end_of_blub, end_of_blub + 1, "");
+        assert!(document.program().is_err());
+        edit(&mut document, end_of_blub, end_of_blub, "}");
+        assert!(document.program().is_ok());
+        // Removing the opening brace of main
+        let main = document.text().find("main() {").unwrap() + 7;
+        edit(&mut document, main, main + 1, "");
+        edit(&mut document, main, main, "{");
+    }
+
+    #[test]
+    fn random_edits_match_a_full_reparse() {
+        const SNIPPETS: [&str; 12] = [
+            "",
+            "}",
+            "{",
+            ";",
+            "x = 1;",
+            "/*",
+            "*/",
+            "//",
+            "\n",
+            " ",
+            "if (a) ",
+            "int f() {}",
+        ];
+        let mut document = Document::new(EXAMPLE);
+        let mut random = Random::new(42);
+        let mut valid = 0;
+        for _ in 0..1000 {
+            let length = document.text().len();
+            let start = random.below(length + 1);
+            let end = (start + random.below(4)).min(length);
+            let replacement = SNIPPETS[random.below(SNIPPETS.len())];
+            let removed = document.text()[start..end].to_owned();
+            edit(&mut document, start, end, replacement);
+            // Edits that break the program are undone, so that every edit starts from a valid
+            // program and exercises the incremental path
+            if document.program().is_ok() {
+                valid += 1;
+            } else {
+                edit(&mut document, start, start + replacement.len(), &removed);
+            }
+        }
+        assert!(
+            valid > 100,
+            "only {} edits resulted in a valid program",
+            valid
+        );
+    }
+}
@@ -1,6 +1,29 @@
+use crate::diagnostic::{Diagnostic, Position};
+use crate::error_code::ErrorCode;
+use crate::source_map::{FileId, SourceMap};
 use logos::{Lexer, Logos};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::ops::Range;
+
+/// Settings that change how the text is split into tokens
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+pub struct LexerOptions {
+    /// Let `/*` inside a block comment open a nested comment, which has to be closed before the
+    /// outer one
+    pub nested_comments: bool,
+    /// Continue after a lexical error instead of reporting the erroneous token. A diagnostic is
+    /// recorded, see [`C1Lexer::diagnostics`], and the token is replaced with the one that was
+    /// most likely meant: unterminated comments and strings are closed at the end of the text or
+    /// line, malformed floats are read as float constants and unexpected characters are dropped.
+    pub recover: bool,
+    /// Allow identifiers that consist of Unicode characters as defined by Unicode Standard Annex
+    /// #31, e.g. `größe`. Otherwise identifiers only consist of ASCII letters, digits and `_`.
+    pub unicode_identifiers: bool,
+}
 
-#[derive(Logos, Debug, PartialEq, Copy, Clone)]
+#[derive(Logos, Serialize, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone)]
+#[logos(extras = LexerOptions)]
 pub enum C1Token {
     #[token("bool")]
     KwBoolean,
@@ -105,30 +128,70 @@ pub enum C1Token {
     /// }
     RightBrace,
 
-    #[regex("[0-9]+")]
+    // Decimal, octal, hexadecimal and binary integers with `_` as digit separator. An integer with
+    // invalid digits or that does not fit into 64 bits is reported as an error token.
+    #[regex("[0-9][0-9_]*", int_constant)]
+    #[regex("0[xXbB][0-9a-zA-Z_]*", int_constant)]
     ConstInt,
 
-    #[regex(r"(\d+\.\d+)|(\.\d+([eE]([-+])?\d+)?)|(\d+[eE]([-+])?\d+)")]
+    // Floats with `_` as digit separator like integers. A misplaced separator is reported as an
+    // error token.
+    #[regex(
+        r"(\d[\d_]*\.[\d_]*|\.\d[\d_]*)([eE][-+]?\d[\d_]*)?[fF]?",
+        float_constant
+    )]
+    #[regex(r"\d[\d_]*[eE][-+]?\d[\d_]*[fF]?", float_constant)]
+    // An exponent without digits is reported as an error token
+    #[regex(r"(\d[\d_]*\.[\d_]*|\.\d[\d_]*|\d[\d_]*)[eE][-+]?", |_| false)]
+    // Floats in hexadecimal or binary notation are not supported and reported as an error token
+    #[regex(r"0[xXbB][0-9a-zA-Z_]*\.([0-9a-zA-Z_]|[pP][-+])*", |_| false)]
     ConstFloat,
 
     #[regex("true|false")]
     ConstBoolean,
 
-    #[regex("\"[^\n\"]*\"")]
+    #[regex(r#""([^\n"\\]|\\[^\n])*""#)]
+    // A string that is not closed on the same line is reported as an error token
+    #[regex(r#""([^\n"\\]|\\[^\n])*\\?"#, |_| false)]
     ConstString,
 
-    #[regex("[a-zA-Z]+[0-9a-zA-Z]*")]
+    // Identifiers with non-ASCII characters are reported as an error token unless they are enabled
+    #[regex(r"[\p{XID_Start}_]\p{XID_Continue}*", identifier)]
     Identifier,
 
-    #[regex(r"/\*[^\*/]*\*/", logos::skip)]
+    #[token("/*", block_comment)]
     CComment,
 
-    #[regex("//[^\n]*(\n)?", logos::skip)]
+    #[regex("//[^\n]*(\n)?")]
     CPPComment,
 
+    /// A block comment that is not closed until the end of the text. The raw logos lexer yields
+    /// an [`C1Token::Error`] for it, [`C1Lexer`] reports this token instead.
+    UnterminatedComment,
+
+    /// A string constant that is not closed until the end of the line, reported by [`C1Lexer`]
+    UnterminatedString,
+
+    /// A float constant with an exponent that has no digits, e.g. `1e` or `2.5E+`, with a
+    /// misplaced digit separator, e.g. `1_.5`, or in hexadecimal or binary notation, e.g. `0x1.8`,
+    /// reported by [`C1Lexer`]
+    MalformedFloat,
+
+    /// An integer constant that does not fit into 64 bits, reported by [`C1Lexer`]
+    OverflowingInt,
+
+    /// An integer constant without digits after its prefix, with digits that are not valid in its
+    /// base or with a misplaced digit separator, e.g. `0x`, `09` or `1__000`, reported by
+    /// [`C1Lexer`]
+    MalformedInt,
+
+    /// An identifier with non-ASCII characters while Unicode identifiers are not enabled, see
+    /// [`LexerOptions::unicode_identifiers`], reported by [`C1Lexer`]
+    UnicodeIdentifier,
+
     // We can also use this variant to define whitespace,
     // or any other matches we wish to skip.
-    #[regex(r"[ \t\f]+", logos::skip)]
+    #[regex(r"[ \t\f]+")]
     Whitespace,
 
     #[regex(r"[\n]")]
@@ -140,10 +203,255 @@ pub enum C1Token {
     Error,
 }
 
+/// Check that the identifier only contains ASCII characters, unless Unicode identifiers are enabled
+fn identifier(lexer: &mut Lexer<C1Token>) -> bool {
+    lexer.extras.unicode_identifiers || lexer.slice().is_ascii()
+}
+
+/// Check that the text of an integer constant can be decoded
+fn int_constant(lexer: &mut Lexer<C1Token>) -> bool {
+    parse_int(lexer.slice()).is_ok()
+}
+
+/// Check that every digit separator in a float constant is placed between two digits
+fn float_constant(lexer: &mut Lexer<C1Token>) -> bool {
+    separators_between_digits(lexer.slice())
+}
+
+fn separators_between_digits(text: &str) -> bool {
+    let bytes = text.as_bytes();
+    let is_digit = |index: Option<usize>| {
+        index
+            .and_then(|index| bytes.get(index))
+            .is_some_and(u8::is_ascii_digit)
+    };
+    (0..bytes.len())
+        .filter(|index| bytes[*index] == b'_')
+        .all(|index| is_digit(index.checked_sub(1)) && is_digit(Some(index + 1)))
+}
+
+/// The reason why the text of an integer constant cannot be decoded
+#[derive(Debug, PartialEq, Eq)]
+enum IntError {
+    /// The text is not a valid constant, with a message that explains why
+    Malformed(String),
+    /// The value does not fit into 64 bits
+    Overflow,
+}
+
+/// Decode an integer constant in decimal, octal (with a leading `0`), hexadecimal (`0x`) or binary
+/// (`0b`) notation. Digits may be separated by single underscores.
+fn parse_int(text: &str) -> Result<i64, IntError> {
+    let (prefix, radix, notation) = match text.get(..2) {
+        Some("0x" | "0X") => (2, 16, "hexadecimal"),
+        Some("0b" | "0B") => (2, 2, "binary"),
+        Some(_) if text.starts_with('0') => (1, 8, "octal"),
+        _ => (0, 10, "decimal"),
+    };
+    let digits = &text[prefix..];
+    if digits.is_empty() {
+        let message = format!("Missing digits after '{}'", text);
+        return Err(IntError::Malformed(message));
+    }
+    if let Some(invalid) = digits.chars().find(|c| *c != '_' && !c.is_digit(radix)) {
+        let message = format!(
+            "Invalid digit '{}' in {} constant '{}'",
+            invalid, notation, text
+        );
+        return Err(IntError::Malformed(message));
+    }
+    if digits.starts_with('_') || digits.ends_with('_') || digits.contains("__") {
+        let message = format!("Misplaced digit separator in '{}'", text);
+        return Err(IntError::Malformed(message));
+    }
+    i64::from_str_radix(&digits.replace('_', ""), radix).map_err(|_| IntError::Overflow)
+}
+
+/// Scan the rest of a block comment after its opening `/*`. If the comment is not closed, the
+/// rest of the text becomes an error token.
+fn block_comment(lexer: &mut Lexer<C1Token>) -> bool {
+    let rest = lexer.remainder().as_bytes();
+    let mut depth = 1;
+    let mut index = 0;
+    while index + 1 < rest.len() {
+        match &rest[index..index + 2] {
+            b"*/" => {
+                index += 2;
+                depth -= 1;
+                if depth == 0 {
+                    lexer.bump(index);
+                    return true;
+                }
+            }
+            b"/*" if lexer.extras.nested_comments => {
+                index += 2;
+                depth += 1;
+            }
+            _ => index += 1,
+        }
+    }
+    lexer.bump(rest.len());
+    false
+}
+
+impl C1Token {
+    /// Check whether the token carries no meaning for the parser, i.e. whether it is whitespace, a
+    /// linebreak or a comment
+    pub fn is_trivia(&self) -> bool {
+        matches!(
+            self,
+            C1Token::Whitespace | C1Token::Linebreak | C1Token::CComment | C1Token::CPPComment
+        )
+    }
+
+    /// Check whether the token is a lexical error, i.e. a character that does not start a token or
+    /// a token that is not complete
+    pub fn is_error(&self) -> bool {
+        matches!(
+            self,
+            C1Token::Error
+                | C1Token::UnterminatedComment
+                | C1Token::UnterminatedString
+                | C1Token::MalformedFloat
+                | C1Token::OverflowingInt
+                | C1Token::MalformedInt
+                | C1Token::UnicodeIdentifier
+        )
+    }
+
+    /// Return the token that replaces a lexical error when the lexer recovers from it, or `None`
+    /// if the erroneous text is dropped
+    fn recovered(&self) -> Option<C1Token> {
+        match self {
+            C1Token::UnterminatedComment => Some(C1Token::CComment),
+            C1Token::UnterminatedString => Some(C1Token::ConstString),
+            C1Token::MalformedFloat => Some(C1Token::ConstFloat),
+            C1Token::OverflowingInt | C1Token::MalformedInt => Some(C1Token::ConstInt),
+            C1Token::UnicodeIdentifier => Some(C1Token::Identifier),
+            _ => None,
+        }
+    }
+
+    /// Return a short human readable description of the token, e.g. for error messages
+    /// ```
+    /// use cb_3::C1Token;
+    ///
+    /// assert_eq!(C1Token::Semicolon.description(), "';'");
+    /// assert_eq!(C1Token::Identifier.description(), "identifier");
+    /// ```
+    pub fn description(&self) -> &'static str {
+        match self {
+            C1Token::KwBoolean => "'bool'",
+            C1Token::KwDo => "'do'",
+            C1Token::KwElse => "'else'",
+            C1Token::KwFloat => "'float'",
+            C1Token::KwFor => "'for'",
+            C1Token::KwIf => "'if'",
+            C1Token::KwInt => "'int'",
+            C1Token::KwPrintf => "'printf'",
+            C1Token::KwReturn => "'return'",
+            C1Token::KwVoid => "'void'",
+            C1Token::KwWhile => "'while'",
+            C1Token::Plus => "'+'",
+            C1Token::Minus => "'-'",
+            C1Token::Asterisk => "'*'",
+            C1Token::Slash => "'/'",
+            C1Token::Assign => "'='",
+            C1Token::Equal => "'=='",
+            C1Token::NotEqual => "'!='",
+            C1Token::Less => "'<'",
+            C1Token::Greater => "'>'",
+            C1Token::LessEqual => "'<='",
+            C1Token::GreaterEqual => "'>='",
+            C1Token::And => "'&&'",
+            C1Token::Or => "'||'",
+            C1Token::Comma => "','",
+            C1Token::Semicolon => "';'",
+            C1Token::LeftParenthesis => "'('",
+            C1Token::RightParenthesis => "')'",
+            C1Token::LeftBrace => "'{'",
+            C1Token::RightBrace => "'}'",
+            C1Token::ConstInt => "integer constant",
+            C1Token::ConstFloat => "float constant",
+            C1Token::ConstBoolean => "boolean constant",
+            C1Token::ConstString => "string constant",
+            C1Token::Identifier => "identifier",
+            C1Token::CComment | C1Token::CPPComment => "comment",
+            C1Token::Whitespace => "whitespace",
+            C1Token::Linebreak => "linebreak",
+            C1Token::UnterminatedComment => "unterminated comment",
+            C1Token::UnterminatedString => "unterminated string constant",
+            C1Token::MalformedFloat => "malformed float constant",
+            C1Token::OverflowingInt => "integer constant out of range",
+            C1Token::MalformedInt => "malformed integer constant",
+            C1Token::UnicodeIdentifier => "identifier with non-ASCII characters",
+            C1Token::Error => "invalid token",
+        }
+    }
+}
+
+/// The value of a constant token, decoded from its text
+#[derive(Debug, PartialEq, Clone)]
+pub enum LiteralValue {
+    Int(i64),
+    Float(f64),
+    Boolean(bool),
+    /// The content of a string constant without the quotes and with escape sequences replaced
+    String(String),
+}
+
+impl LiteralValue {
+    /// Decode the text of a token of the given kind, or return `None` if the token is not a
+    /// constant. Erroneous constants have the value of the constant they are recovered to, except
+    /// for malformed integers, integers that do not fit into 64 bits and floats that are not in
+    /// decimal notation, which have no value.
+    fn decode(token: C1Token, text: &str) -> Option<LiteralValue> {
+        match token {
+            C1Token::ConstInt => parse_int(text).ok().map(LiteralValue::Int),
+            C1Token::ConstFloat | C1Token::MalformedFloat => {
+                let text = text.replace('_', "");
+                let number = text.trim_end_matches(['f', 'F']);
+                let mantissa = number.trim_end_matches(['e', 'E', '+', '-']);
+                mantissa.parse().ok().map(LiteralValue::Float)
+            }
+            C1Token::ConstBoolean => Some(LiteralValue::Boolean(text == "true")),
+            C1Token::ConstString => Some(LiteralValue::String(unescape(&text[1..text.len() - 1]))),
+            C1Token::UnterminatedString => Some(LiteralValue::String(unescape(&text[1..]))),
+            _ => None,
+        }
+    }
+}
+
+/// Replace the escape sequences `\n`, `\t`, `\"` and `\\` in the content of a string constant.
+/// A backslash followed by any other character is kept as it is.
+fn unescape(content: &str) -> String {
+    let mut value = String::with_capacity(content.len());
+    let mut chars = content.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => value.push('\n'),
+            Some('t') => value.push('\t'),
+            Some(escaped @ ('"' | '\\')) => value.push(escaped),
+            Some(other) => {
+                value.push('\\');
+                value.push(other);
+            }
+            None => value.push('\\'),
+        }
+    }
+    value
+}
+
 /// # Overview
 /// Extended lexer based on the logos crate. The lexer keeps track of the current token and the next token
-/// in the lexed text. Furthermore, the lexer keeps track of the line number in which each token is
-/// located, and of the text associated with each token.
+/// in the lexed text, and reads further ahead on demand with [`C1Lexer::peek_nth`]. Furthermore, the
+/// lexer keeps track of the line number in which each token is located, and of the text associated
+/// with each token. A position can be saved with [`C1Lexer::checkpoint`] and returned to with
+/// [`C1Lexer::rewind`].
 ///
 /// # Examples
 /// ```
@@ -177,21 +485,131 @@ pub enum C1Token {
 pub struct C1Lexer<'a> {
     logos_lexer: Lexer<'a, C1Token>,
     logos_line_number: usize,
-    current_token: Option<TokenData<'a>>,
-    peek_token: Option<TokenData<'a>>,
+    /// The column of `logos_position`, counted in characters and starting at 1
+    logos_column: usize,
+    logos_position: usize,
+    /// The current token followed by the tokens that have been read ahead, `None` after the end
+    /// of the text. It always holds at least the current and the next token.
+    tokens: VecDeque<Option<Token<'a>>>,
+    previous_span: Option<Range<usize>>,
+    keep_trivia: bool,
+    diagnostics: Vec<Diagnostic>,
+    /// The file of the lexed text, if it belongs to a [`SourceMap`]
+    file: Option<(&'a SourceMap, FileId)>,
+}
+
+/// A position of a [`C1Lexer`] that it can return to with [`C1Lexer::rewind`], e.g. to parse a
+/// construct speculatively
+#[derive(Clone)]
+pub struct Checkpoint<'a> {
+    logos_lexer: Lexer<'a, C1Token>,
+    logos_line_number: usize,
+    logos_column: usize,
+    logos_position: usize,
+    tokens: VecDeque<Option<Token<'a>>>,
+    previous_span: Option<Range<usize>>,
+    diagnostics: usize,
 }
 
 impl<'a> C1Lexer<'a> {
     /// Initialize a new C1Lexer for the given string slice
-    pub fn new(text: &'a str) -> C1Lexer {
+    pub fn new(text: &'a str) -> C1Lexer<'a> {
+        Self::initialize(text, false)
+    }
+
+    /// Initialize a new C1Lexer that does not skip whitespace, linebreaks and comments. Concatenating
+    /// the text of all tokens yields the complete input.
+    /// ```
+    /// use cb_3::{C1Lexer, C1Token};
+    /// let mut lexer = C1Lexer::with_trivia("x // y");
+    ///
+    /// assert_eq!(lexer.current_token(), Some(C1Token::Identifier));
+    /// lexer.eat();
+    /// assert_eq!(lexer.current_token(), Some(C1Token::Whitespace));
+    /// lexer.eat();
+    /// assert_eq!(lexer.current_token(), Some(C1Token::CPPComment));
+    /// ```
+    pub fn with_trivia(text: &'a str) -> C1Lexer<'a> {
+        Self::initialize(text, true)
+    }
+
+    /// Initialize a new C1Lexer that starts at the given byte offset, which has to be the start of a
+    /// token. Spans and line numbers still refer to the complete text.
+    /// ```
+    /// use cb_3::{C1Lexer, C1Token};
+    /// let lexer = C1Lexer::starting_at("int x;\nfloat y;", 7);
+    ///
+    /// assert_eq!(lexer.current_token(), Some(C1Token::KwFloat));
+    /// assert_eq!(lexer.current_span(), Some(7..12));
+    /// assert_eq!(lexer.current_line_number(), Some(2));
+    /// ```
+    pub fn starting_at(text: &'a str, offset: usize) -> C1Lexer<'a> {
+        let mut logos_lexer = C1Token::lexer(text);
+        logos_lexer.bump(offset);
+        Self::from_logos(logos_lexer, false, None)
+    }
+
+    /// Initialize a new C1Lexer with the given options
+    /// ```
+    /// use cb_3::{C1Lexer, C1Token, LexerOptions};
+    ///
+    /// let text = "/* outer /* inner */ still a comment */ x";
+    /// let options = LexerOptions { nested_comments: true, ..LexerOptions::default() };
+    /// let lexer = C1Lexer::with_options(text, options);
+    /// assert_eq!(lexer.current_token(), Some(C1Token::Identifier));
+    ///
+    /// let lexer = C1Lexer::new(text);
+    /// assert_eq!(lexer.current_text(), Some("still"));
+    /// ```
+    pub fn with_options(text: &'a str, options: LexerOptions) -> C1Lexer<'a> {
+        Self::from_logos(C1Token::lexer_with_extras(text, options), false, None)
+    }
+
+    /// Initialize a new C1Lexer for a file of a source map. The tokens and the diagnostics of
+    /// lexical errors refer to the file, while spans, lines and columns are local to its text.
+    /// ```
+    /// use cb_3::source_map::SourceMap;
+    /// use cb_3::C1Lexer;
+    ///
+    /// let mut sources = SourceMap::new();
+    /// sources.add("a.c-1", "int a() { return 1; }");
+    /// let b = sources.add("b.c-1", "void b() {}");
+    ///
+    /// let token = C1Lexer::for_file(&sources, b).nth(1).unwrap();
+    /// assert_eq!(token.file, Some(b));
+    /// assert_eq!(sources.describe(sources.global(b, token.span.start)), "b.c-1:1:6");
+    /// ```
+    pub fn for_file(sources: &'a SourceMap, file: FileId) -> C1Lexer<'a> {
+        let logos_lexer = C1Token::lexer(sources.file(file).text());
+        Self::from_logos(logos_lexer, false, Some((sources, file)))
+    }
+
+    /// Return the file of the lexed text, if it belongs to a [`SourceMap`]
+    pub fn file(&self) -> Option<FileId> {
+        self.file.map(|(_, file)| file)
+    }
+
+    fn initialize(text: &'a str, keep_trivia: bool) -> C1Lexer<'a> {
+        Self::from_logos(C1Token::lexer(text), keep_trivia, None)
+    }
+
+    fn from_logos(
+        logos_lexer: Lexer<'a, C1Token>,
+        keep_trivia: bool,
+        file: Option<(&'a SourceMap, FileId)>,
+    ) -> C1Lexer<'a> {
         let mut lexer = C1Lexer {
-            logos_lexer: C1Token::lexer(text),
+            logos_lexer,
             logos_line_number: 1,
-            current_token: None,
-            peek_token: None,
+            logos_column: 1,
+            logos_position: 0,
+            tokens: VecDeque::new(),
+            previous_span: None,
+            keep_trivia,
+            diagnostics: Vec::new(),
+            file,
         };
-        lexer.current_token = lexer.next_token();
-        lexer.peek_token = lexer.next_token();
+        lexer.read_ahead(1);
         lexer
     }
 
@@ -207,7 +625,7 @@ impl<'a> C1Lexer<'a> {
     /// assert_eq!(lexer.current_text(), Some("current"));
     /// ```
     pub fn current_token(&self) -> Option<C1Token> {
-        self.current_token.token_type()
+        self.tokens[0].token_type()
     }
 
     /// Return the C1Token variant of the next token without consuming it.
@@ -222,27 +640,132 @@ impl<'a> C1Lexer<'a> {
     /// assert_eq!(lexer.peek_text(), Some("next"));
     /// ```
     pub fn peek_token(&self) -> Option<C1Token> {
-        self.peek_token.token_type()
+        self.tokens[1].token_type()
     }
 
     /// Return the text of the current token
     pub fn current_text(&self) -> Option<&str> {
-        self.current_token.text()
+        self.tokens[0].text()
     }
 
     /// Return the text of the next token
     pub fn peek_text(&self) -> Option<&str> {
-        self.peek_token.text()
+        self.tokens[1].text()
+    }
+
+    /// Return the value of the current token if it is a constant
+    /// ```
+    /// use cb_3::{C1Lexer, LiteralValue};
+    /// let mut lexer = C1Lexer::new(r#"42 2.5e1 true "a\tb""#);
+    ///
+    /// assert_eq!(lexer.current_value(), Some(&LiteralValue::Int(42)));
+    /// lexer.eat();
+    /// assert_eq!(lexer.current_value(), Some(&LiteralValue::Float(25.0)));
+    /// lexer.eat();
+    /// assert_eq!(lexer.current_value(), Some(&LiteralValue::Boolean(true)));
+    /// assert_eq!(lexer.peek_value(), Some(&LiteralValue::String(String::from("a\tb"))));
+    /// ```
+    pub fn current_value(&self) -> Option<&LiteralValue> {
+        self.tokens[0].value()
+    }
+
+    /// Return the value of the next token if it is a constant
+    pub fn peek_value(&self) -> Option<&LiteralValue> {
+        self.tokens[1].value()
     }
 
     /// Return the line number where the current token is located
     pub fn current_line_number(&self) -> Option<usize> {
-        self.current_token.line_number()
+        self.tokens[0].line_number()
     }
 
     /// Return the line number where the next token is located
     pub fn peek_line_number(&self) -> Option<usize> {
-        self.peek_token.line_number()
+        self.tokens[1].line_number()
+    }
+
+    /// Return the column (counted in characters, starting at 1) where the current token is located
+    pub fn current_column(&self) -> Option<usize> {
+        self.tokens[0].column()
+    }
+
+    /// Return the column (counted in characters, starting at 1) where the next token is located
+    pub fn peek_column(&self) -> Option<usize> {
+        self.tokens[1].column()
+    }
+
+    /// Return the byte range of the current token in the lexed text
+    /// ```
+    /// use cb_3::C1Lexer;
+    /// let mut lexer = C1Lexer::new("int x");
+    ///
+    /// assert_eq!(lexer.current_span(), Some(0..3));
+    /// assert_eq!(lexer.peek_span(), Some(4..5));
+    /// assert_eq!(lexer.previous_span(), None);
+    ///
+    /// lexer.eat();
+    /// assert_eq!(lexer.previous_span(), Some(0..3));
+    /// ```
+    pub fn current_span(&self) -> Option<Range<usize>> {
+        self.tokens[0].span()
+    }
+
+    /// Return the byte range of the next token in the lexed text
+    pub fn peek_span(&self) -> Option<Range<usize>> {
+        self.tokens[1].span()
+    }
+
+    /// Return the byte range of the token that was consumed by the last call to [`C1Lexer::eat`]
+    pub fn previous_span(&self) -> Option<Range<usize>> {
+        self.previous_span.clone()
+    }
+
+    /// Return the complete text the lexer operates on
+    pub fn source(&self) -> &'a str {
+        self.logos_lexer.source()
+    }
+
+    /// Return the diagnostic for the current token if it is a lexical error
+    /// ```
+    /// use cb_3::C1Lexer;
+    /// let lexer = C1Lexer::new("x = 1.5e;");
+    ///
+    /// assert_eq!(lexer.current_error(), None);
+    /// let lexer = C1Lexer::new("1.5e;");
+    /// let error = lexer.current_error().unwrap();
+    /// assert_eq!(error.to_string(), "1:1: error[E0019]: Malformed float exponent '1.5e'");
+    /// ```
+    pub fn current_error(&self) -> Option<Diagnostic> {
+        match (self.current_token(), self.current_span()) {
+            (Some(token), Some(span)) => self.lexical_error(token, span),
+            _ => None,
+        }
+    }
+
+    /// Return the diagnostics for the lexical errors the lexer has recovered from so far, see
+    /// [`LexerOptions::recover`]. The next token has already been read, so its errors are
+    /// included.
+    /// ```
+    /// use cb_3::{C1Lexer, C1Token, LexerOptions};
+    ///
+    /// let options = LexerOptions { recover: true, ..LexerOptions::default() };
+    /// let mut lexer = C1Lexer::with_options("x $= 1e;", options);
+    /// lexer.eat();
+    /// assert_eq!(lexer.current_token(), Some(C1Token::Assign));
+    /// lexer.eat();
+    /// assert_eq!(lexer.current_token(), Some(C1Token::ConstFloat));
+    ///
+    /// let messages: Vec<String> = lexer.diagnostics().iter().map(ToString::to_string).collect();
+    /// assert_eq!(
+    ///     messages,
+    ///     vec![
+    ///         "1:3: error[E0016]: Unexpected character '$'",
+    ///         "1:6: error[E0019]: Malformed float exponent '1e'",
+    ///     ]
+    /// );
+    /// ```
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
     }
 
     /// Drop the current token and retrieve the next token in the text.
@@ -266,69 +789,356 @@ impl<'a> C1Lexer<'a> {
     /// assert_eq!(lexer.peek_text(), None);
     /// ```
     pub fn eat(&mut self) {
-        self.current_token = self.peek_token.take();
-        self.peek_token = self.next_token();
+        self.advance();
+    }
+
+    /// Private method for consuming the current token and returning it
+    fn advance(&mut self) -> Option<Token<'a>> {
+        let token = self.tokens.pop_front().flatten();
+        if let Some(token) = &token {
+            self.previous_span = Some(token.span.clone());
+        }
+        self.read_ahead(1);
+        token
+    }
+
+    /// Return the C1Token variant of the token `n` positions after the current token without
+    /// consuming any token. `peek_nth(0)` is the current token and `peek_nth(1)` the next token.
+    /// ```
+    /// use cb_3::{C1Lexer, C1Token};
+    /// let mut lexer = C1Lexer::new("int x = 1;");
+    ///
+    /// assert_eq!(lexer.peek_nth(2), Some(C1Token::Assign));
+    /// assert_eq!(lexer.peek_nth_text(3), Some("1"));
+    /// assert_eq!(lexer.peek_nth(5), None);
+    /// assert_eq!(lexer.current_token(), Some(C1Token::KwInt));
+    /// ```
+    pub fn peek_nth(&mut self, n: usize) -> Option<C1Token> {
+        self.lookahead(n).token_type()
+    }
+
+    /// Return the text of the token `n` positions after the current token, see
+    /// [`C1Lexer::peek_nth`]
+    pub fn peek_nth_text(&mut self, n: usize) -> Option<&str> {
+        self.lookahead(n).text()
+    }
+
+    /// Return the byte range of the token `n` positions after the current token, see
+    /// [`C1Lexer::peek_nth`]
+    pub fn peek_nth_span(&mut self, n: usize) -> Option<Range<usize>> {
+        self.lookahead(n).span()
+    }
+
+    /// Save the current position, so that the lexer can return to it later with
+    /// [`C1Lexer::rewind`]
+    /// ```
+    /// use cb_3::{C1Lexer, C1Token};
+    /// let mut lexer = C1Lexer::new("x = f();");
+    ///
+    /// let checkpoint = lexer.checkpoint();
+    /// lexer.eat();
+    /// lexer.eat();
+    /// assert_eq!(lexer.current_text(), Some("f"));
+    ///
+    /// lexer.rewind(checkpoint);
+    /// assert_eq!(lexer.current_text(), Some("x"));
+    /// assert_eq!(lexer.previous_span(), None);
+    /// ```
+    pub fn checkpoint(&self) -> Checkpoint<'a> {
+        Checkpoint {
+            logos_lexer: self.logos_lexer.clone(),
+            logos_line_number: self.logos_line_number,
+            logos_column: self.logos_column,
+            logos_position: self.logos_position,
+            tokens: self.tokens.clone(),
+            previous_span: self.previous_span.clone(),
+            diagnostics: self.diagnostics.len(),
+        }
+    }
+
+    /// Return to a position that was saved by [`C1Lexer::checkpoint`] of this lexer. Diagnostics
+    /// of lexical errors that were recovered from after the checkpoint are dropped, they are
+    /// recorded again when the lexer reads the tokens again.
+    pub fn rewind(&mut self, checkpoint: Checkpoint<'a>) {
+        self.logos_lexer = checkpoint.logos_lexer;
+        self.logos_line_number = checkpoint.logos_line_number;
+        self.logos_column = checkpoint.logos_column;
+        self.logos_position = checkpoint.logos_position;
+        self.tokens = checkpoint.tokens;
+        self.previous_span = checkpoint.previous_span;
+        self.diagnostics.truncate(checkpoint.diagnostics);
+    }
+
+    /// Private method for reading tokens until the token `n` positions after the current token is
+    /// known. After the end of the text, at most two `None` entries are kept, which is enough for
+    /// the current and the next token.
+    fn read_ahead(&mut self, n: usize) {
+        while self.tokens.len() <= n {
+            if self.tokens.len() >= 2 && matches!(self.tokens.back(), Some(None)) {
+                break;
+            }
+            let token = self.next_token();
+            self.tokens.push_back(token);
+        }
+    }
+
+    /// Private method for returning the token `n` positions after the current token, `None` if it
+    /// lies behind the end of the text
+    fn lookahead(&mut self, n: usize) -> &Option<Token<'a>> {
+        self.read_ahead(n);
+        &self.tokens[n.min(self.tokens.len() - 1)]
     }
 
     /// Private method for reading the next token from the logos::Lexer and extracting the required data
     /// from it
-    fn next_token(&mut self) -> Option<TokenData<'a>> {
+    fn next_token(&mut self) -> Option<Token<'a>> {
         // Retrieve the next token from the internal lexer
-        if let Some(c1_token) = self.logos_lexer.next() {
-            match c1_token {
-                C1Token::Linebreak => {
-                    // If the token is a linebreak, increase the line number and get the next token
-                    self.logos_line_number += 1;
-                    self.next_token()
+        while let Some(c1_token) = self.logos_lexer.next() {
+            // Count the linebreaks up to the start of the token. The line number is increased as
+            // soon as the lexer advances past a linebreak.
+            let span = self.logos_lexer.span();
+            self.advance_to(span.start);
+            let lexed = match c1_token {
+                C1Token::Error => error_kind(self.logos_lexer.slice()),
+                token => token,
+            };
+            let mut kind = lexed;
+            if kind.is_error() && self.logos_lexer.extras.recover {
+                let diagnostic = self.lexical_error(kind, span.clone());
+                self.diagnostics.extend(diagnostic);
+                match kind.recovered() {
+                    Some(recovered) => kind = recovered,
+                    None => continue,
                 }
-                _ => Some(TokenData {
-                    // If the token is not a linebreak, initialize and return a TokenData instance
-                    token_type: c1_token,
-                    token_text: self.logos_lexer.slice(),
-                    token_line: self.logos_line_number,
-                }),
             }
-        } else {
-            None
+            // Skip linebreaks, whitespace and comments unless they were requested
+            if kind.is_trivia() && !self.keep_trivia {
+                continue;
+            }
+            return Some(Token {
+                kind,
+                text: self.logos_lexer.slice(),
+                line: self.logos_line_number,
+                column: self.logos_column,
+                span,
+                file: self.file(),
+                value: LiteralValue::decode(lexed, self.logos_lexer.slice()),
+            });
+        }
+        None
+    }
+
+    /// Private method for creating the diagnostic of a lexical error, which refers to the file of
+    /// the lexed text if there is one
+    fn lexical_error(&self, token: C1Token, span: Range<usize>) -> Option<Diagnostic> {
+        let diagnostic = lexical_error(self.source(), token, span)?;
+        Some(match self.file {
+            Some((sources, file)) => diagnostic.in_source_file(sources, file),
+            None => diagnostic,
+        })
+    }
+
+    /// Private method for moving the line and column bookkeeping forward to the given byte offset.
+    /// Only the text since the last call is looked at, so lexing stays linear in long lines.
+    fn advance_to(&mut self, offset: usize) {
+        let skipped = &self.logos_lexer.source()[self.logos_position..offset];
+        match skipped.rfind('\n') {
+            Some(index) => {
+                self.logos_line_number += skipped.matches('\n').count();
+                self.logos_column = skipped[index + 1..].chars().count() + 1;
+            }
+            None => self.logos_column += skipped.chars().count(),
+        }
+        self.logos_position = offset;
+    }
+}
+
+/// Iterating over the lexer consumes the tokens starting at the current token. It can be combined
+/// with the other methods of the lexer, e.g. to look at the tokens that follow.
+/// ```
+/// use cb_3::{C1Lexer, C1Token};
+/// let mut lexer = C1Lexer::new("int x = 1;");
+///
+/// let kinds: Vec<C1Token> = lexer.by_ref().take(2).map(|token| token.kind).collect();
+/// assert_eq!(kinds, vec![C1Token::KwInt, C1Token::Identifier]);
+///
+/// let token = lexer.next().unwrap();
+/// assert_eq!((token.text, token.span, token.column), ("=", 6..7, 7));
+/// assert_eq!(lexer.current_text(), Some("1"));
+/// ```
+impl<'a> Iterator for C1Lexer<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Token<'a>> {
+        self.advance()
+    }
+}
+
+/// Split the text into its tokens. Whitespace, linebreaks and comments are skipped, lexical errors
+/// are included as error tokens. Use [`C1Lexer::with_trivia`] or [`C1Lexer::with_options`] and
+/// iterate over the lexer to get all tokens or to use other options.
+/// ```
+/// use cb_3::{tokenize, C1Token, LiteralValue};
+///
+/// let tokens = tokenize("x = 0x10; // sixteen");
+/// assert_eq!(tokens.len(), 4);
+/// assert_eq!(tokens[2].kind, C1Token::ConstInt);
+/// assert_eq!(tokens[2].value, Some(LiteralValue::Int(16)));
+/// ```
+pub fn tokenize(text: &str) -> Vec<Token<'_>> {
+    C1Lexer::new(text).collect()
+}
+
+/// Tell apart the lexical errors that logos reports as [`C1Token::Error`] by their text
+fn error_kind(text: &str) -> C1Token {
+    match text.chars().next() {
+        Some('/') if text.starts_with("/*") => C1Token::UnterminatedComment,
+        Some('"') => C1Token::UnterminatedString,
+        // Characters that cannot start a token are reported one by one, while the identifier
+        // pattern matches complete names
+        Some(c) if (c.is_alphabetic() || c == '_') && !text.is_ascii() => {
+            C1Token::UnicodeIdentifier
+        }
+        // Errors of the float patterns always contain an exponent or a '.'
+        Some('0'..='9')
+            if (matches!(text.get(..2), Some("0x" | "0X" | "0b" | "0B"))
+                && !text.contains('.'))
+                || text
+                    .bytes()
+                    .all(|byte| byte.is_ascii_digit() || byte == b'_') =>
+        {
+            match parse_int(text) {
+                Err(IntError::Overflow) => C1Token::OverflowingInt,
+                _ => C1Token::MalformedInt,
+            }
         }
+        Some('0'..='9' | '.') if text.len() > 1 => C1Token::MalformedFloat,
+        _ => C1Token::Error,
     }
 }
 
-/// Hidden struct for capsuling the data associated with a token.
-struct TokenData<'a> {
-    token_type: C1Token,
-    token_text: &'a str,
-    token_line: usize,
+/// Create the diagnostic for a lexical error at the given byte range, or return `None` if the
+/// token is not an error. Unterminated comments and strings are reported at their opening
+/// delimiter, because they extend to the end of the text or line.
+fn lexical_error(source: &str, token: C1Token, span: Range<usize>) -> Option<Diagnostic> {
+    let text = &source[span.clone()];
+    let (code, message, end) = match token {
+        C1Token::Error => (
+            ErrorCode::UnexpectedCharacter,
+            format!("Unexpected character '{}'", text),
+            span.end,
+        ),
+        C1Token::UnterminatedComment => (
+            ErrorCode::UnterminatedComment,
+            String::from("Unterminated block comment"),
+            span.start + 2,
+        ),
+        C1Token::UnterminatedString => (
+            ErrorCode::UnterminatedString,
+            String::from("Unterminated string literal"),
+            span.start + 1,
+        ),
+        C1Token::MalformedFloat => {
+            let message = if text.contains(['x', 'X', 'b', 'B']) {
+                format!("Float constant '{}' is not in decimal notation", text)
+            } else if !separators_between_digits(text) {
+                format!("Misplaced digit separator in '{}'", text)
+            } else {
+                format!("Malformed float exponent '{}'", text)
+            };
+            (ErrorCode::MalformedFloat, message, span.end)
+        }
+        C1Token::OverflowingInt => (
+            ErrorCode::IntegerOverflow,
+            format!("Integer constant '{}' does not fit into 64 bits", text),
+            span.end,
+        ),
+        C1Token::UnicodeIdentifier => {
+            let character = text.chars().find(|c| !c.is_ascii()).unwrap_or_default();
+            let message = format!(
+                "Identifier '{}' contains the non-ASCII character '{}'",
+                text, character
+            );
+            (ErrorCode::UnicodeIdentifier, message, span.end)
+        }
+        C1Token::MalformedInt => {
+            let message = match parse_int(text) {
+                Err(IntError::Malformed(message)) => message,
+                _ => format!("Malformed integer constant '{}'", text),
+            };
+            (ErrorCode::MalformedInt, message, span.end)
+        }
+        _ => return None,
+    };
+    let start = Position::at(source, span.start);
+    let end = Position::at(source, end);
+    Some(Diagnostic::error(message, start, end).with_code(code))
+}
+
+/// A token of the lexed text together with its position, as produced by iterating over a
+/// [`C1Lexer`] or by [`tokenize`]
+#[derive(Debug, PartialEq, Clone)]
+pub struct Token<'a> {
+    pub kind: C1Token,
+    pub text: &'a str,
+    /// Byte range of the token in the lexed text
+    pub span: Range<usize>,
+    /// Line number, starting at 1
+    pub line: usize,
+    /// Column counted in characters, starting at 1
+    pub column: usize,
+    /// The file of the token, if the lexed text belongs to a [`SourceMap`]
+    pub file: Option<FileId>,
+    /// The decoded value if the token is a constant
+    pub value: Option<LiteralValue>,
 }
 
 /// Hidden trait that makes it possible to implemented the required getter functionality directly for
-/// Option<TokenData>.
-trait TokenDataProvider<'a> {
+/// Option<Token>.
+trait TokenProvider<'a> {
     /// Return the type of the token, aka. its C1Token variant.
     fn token_type(&self) -> Option<C1Token>;
     /// Return the text of the token
     fn text(&self) -> Option<&str>;
     /// Return the line number of the token
     fn line_number(&self) -> Option<usize>;
+    /// Return the column of the token
+    fn column(&self) -> Option<usize>;
+    /// Return the byte range of the token
+    fn span(&self) -> Option<Range<usize>>;
+    /// Return the decoded value of the token
+    fn value(&self) -> Option<&LiteralValue>;
 }
 
-impl<'a> TokenDataProvider<'a> for Option<TokenData<'a>> {
+impl<'a> TokenProvider<'a> for Option<Token<'a>> {
     fn token_type(&self) -> Option<C1Token> {
-        self.as_ref().map(|data| data.token_type)
+        self.as_ref().map(|data| data.kind)
     }
 
     fn text(&self) -> Option<&'a str> {
-        self.as_ref().map(|data| data.token_text)
+        self.as_ref().map(|data| data.text)
     }
 
     fn line_number(&self) -> Option<usize> {
-        self.as_ref().map(|data| data.token_line)
+        self.as_ref().map(|data| data.line)
+    }
+
+    fn column(&self) -> Option<usize> {
+        self.as_ref().map(|data| data.column)
+    }
+
+    fn span(&self) -> Option<Range<usize>> {
+        self.as_ref().map(|data| data.span.clone())
+    }
+
+    fn value(&self) -> Option<&LiteralValue> {
+        self.as_ref().and_then(|data| data.value.as_ref())
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::lexer::C1Lexer;
+    use crate::lexer::{tokenize, C1Lexer, LexerOptions, LiteralValue};
     use crate::C1Token;
 
     #[test]
@@ -358,6 +1168,446 @@ mod tests {
         assert_eq!(lexer2.peek_line_number(), Some(1));
     }
 
+    #[test]
+    fn lines_are_counted_through_comments() {
+        let mut lexer = C1Lexer::new("a // comment\nb /* multi\nline */ c");
+        assert_eq!(lexer.current_line_number(), Some(1));
+        lexer.eat();
+        assert_eq!(lexer.current_line_number(), Some(2));
+        lexer.eat();
+        assert_eq!(lexer.current_text(), Some("c"));
+        assert_eq!(lexer.current_line_number(), Some(3));
+        assert_eq!(lexer.current_column(), Some(9));
+    }
+
+    #[test]
+    fn columns_are_counted() {
+        let mut lexer = C1Lexer::new("int  main\n\tx");
+        assert_eq!(lexer.current_column(), Some(1));
+        assert_eq!(lexer.peek_column(), Some(6));
+        lexer.eat();
+        lexer.eat();
+        assert_eq!(lexer.current_line_number(), Some(2));
+        assert_eq!(lexer.current_column(), Some(2));
+    }
+
+    #[test]
+    fn trivia_is_kept() {
+        let text = "int /* a */ x;\n\t// b\n";
+        let mut lexer = C1Lexer::with_trivia(text);
+        let mut tokens = Vec::new();
+        let mut reconstructed = String::new();
+        while let Some(token) = lexer.current_token() {
+            tokens.push(token);
+            reconstructed.push_str(lexer.current_text().unwrap());
+            lexer.eat();
+        }
+        assert_eq!(reconstructed, text);
+        assert_eq!(
+            tokens,
+            vec![
+                C1Token::KwInt,
+                C1Token::Whitespace,
+                C1Token::CComment,
+                C1Token::Whitespace,
+                C1Token::Identifier,
+                C1Token::Semicolon,
+                C1Token::Linebreak,
+                C1Token::Whitespace,
+                C1Token::CPPComment,
+            ]
+        );
+    }
+
+    #[test]
+    fn block_comments_with_any_content() {
+        let text = "/* a * b */ /* see http://x */ /** doc **/ /*/ x */";
+        let mut lexer = C1Lexer::with_trivia(text);
+        let mut comments = Vec::new();
+        while let (Some(token), Some(span)) = (lexer.current_token(), lexer.current_span()) {
+            if token != C1Token::Whitespace {
+                assert_eq!(token, C1Token::CComment);
+                comments.push(&text[span]);
+            }
+            lexer.eat();
+        }
+        assert_eq!(
+            comments,
+            vec![
+                "/* a * b */",
+                "/* see http://x */",
+                "/** doc **/",
+                "/*/ x */"
+            ]
+        );
+    }
+
+    #[test]
+    fn unterminated_block_comment() {
+        let mut lexer = C1Lexer::new(
+            "x = 1;
+  /* open * /
+int f() {}",
+        );
+        for _ in 0..4 {
+            lexer.eat();
+        }
+        assert_eq!(lexer.current_token(), Some(C1Token::UnterminatedComment));
+        assert_eq!(lexer.current_line_number(), Some(2));
+        assert_eq!(lexer.current_column(), Some(3));
+        assert_eq!(lexer.current_text(), Some("/* open * /\nint f() {}"));
+        assert_eq!(lexer.peek_token(), None);
+    }
+
+    #[test]
+    fn nested_block_comments() {
+        let options = LexerOptions {
+            nested_comments: true,
+            ..LexerOptions::default()
+        };
+        let lexer = C1Lexer::with_options("/* a /* b */ c */ x", options);
+        assert_eq!(lexer.current_text(), Some("x"));
+        let lexer = C1Lexer::with_options("/* a /* b */ c", options);
+        assert_eq!(lexer.current_token(), Some(C1Token::UnterminatedComment));
+        let lexer = C1Lexer::new("/* a /* b */ c");
+        assert_eq!(lexer.current_text(), Some("c"));
+    }
+
+    #[test]
+    fn lexical_errors() {
+        let text = "a $ \"open\n1e+ .5E 2.5e-3 \"closed\"";
+        let mut lexer = C1Lexer::new(text);
+        let mut tokens = Vec::new();
+        while let (Some(token), Some(text)) = (lexer.current_token(), lexer.current_text()) {
+            tokens.push((token, text.to_owned()));
+            lexer.eat();
+        }
+        let expected = [
+            (C1Token::Identifier, "a"),
+            (C1Token::Error, "$"),
+            (C1Token::UnterminatedString, "\"open"),
+            (C1Token::MalformedFloat, "1e+"),
+            (C1Token::MalformedFloat, ".5E"),
+            (C1Token::ConstFloat, "2.5e-3"),
+            (C1Token::ConstString, "\"closed\""),
+        ];
+        assert_eq!(
+            tokens,
+            expected.map(|(token, text)| (token, text.to_owned()))
+        );
+    }
+
+    #[test]
+    fn lexer_recovers_from_errors() {
+        let options = LexerOptions {
+            recover: true,
+            ..LexerOptions::default()
+        };
+        let mut lexer = C1Lexer::with_options("€ x = 1e; \"y\n/* z", options);
+        let mut tokens = Vec::new();
+        while let Some(token) = lexer.current_token() {
+            tokens.push(token);
+            lexer.eat();
+        }
+        assert_eq!(
+            tokens,
+            vec![
+                C1Token::Identifier,
+                C1Token::Assign,
+                C1Token::ConstFloat,
+                C1Token::Semicolon,
+                C1Token::ConstString,
+            ]
+        );
+        let messages: Vec<String> = lexer.diagnostics().iter().map(|d| d.to_string()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "1:1: error[E0016]: Unexpected character '€'",
+                "1:7: error[E0019]: Malformed float exponent '1e'",
+                "1:11: error[E0018]: Unterminated string literal",
+                "2:1: error[E0017]: Unterminated block comment",
+            ]
+        );
+    }
+
+    #[test]
+    fn constants_are_decoded() {
+        let text = r#"0 9223372036854775807 .5 1E3 false "" "say \"hi\"\n" "a\\b\q" "open\"#;
+        let mut lexer = C1Lexer::new(text);
+        let mut values = Vec::new();
+        while lexer.current_token().is_some() {
+            values.push(lexer.current_value().cloned());
+            lexer.eat();
+        }
+        assert_eq!(
+            values,
+            vec![
+                Some(LiteralValue::Int(0)),
+                Some(LiteralValue::Int(i64::MAX)),
+                Some(LiteralValue::Float(0.5)),
+                Some(LiteralValue::Float(1000.0)),
+                Some(LiteralValue::Boolean(false)),
+                Some(LiteralValue::String(String::new())),
+                Some(LiteralValue::String(String::from("say \"hi\"\n"))),
+                Some(LiteralValue::String(String::from("a\\b\\q"))),
+                Some(LiteralValue::String(String::from("open\\"))),
+            ]
+        );
+        let lexer = C1Lexer::new("x");
+        assert_eq!(lexer.current_value(), None);
+    }
+
+    #[test]
+    fn integer_overflow() {
+        let lexer = C1Lexer::new("9223372036854775808");
+        assert_eq!(lexer.current_token(), Some(C1Token::OverflowingInt));
+        assert_eq!(lexer.current_value(), None);
+        assert_eq!(
+            lexer.current_error().unwrap().message,
+            "Integer constant '9223372036854775808' does not fit into 64 bits"
+        );
+
+        let options = LexerOptions {
+            recover: true,
+            ..LexerOptions::default()
+        };
+        let lexer = C1Lexer::with_options("99999999999999999999 1.5e", options);
+        assert_eq!(lexer.current_token(), Some(C1Token::ConstInt));
+        assert_eq!(lexer.current_value(), None);
+        assert_eq!(lexer.peek_value(), Some(&LiteralValue::Float(1.5)));
+        assert_eq!(lexer.diagnostics().len(), 2);
+    }
+
+    #[test]
+    fn integer_notations() {
+        let text = "0 017 0x1F 0XfF 0b1010 1_000_000 0x_1";
+        let mut lexer = C1Lexer::new(text);
+        let mut values = Vec::new();
+        while let Some(token) = lexer.current_token() {
+            values.push((token, lexer.current_value().cloned()));
+            lexer.eat();
+        }
+        let expected = [0, 15, 31, 255, 10, 1_000_000]
+            .map(|value| (C1Token::ConstInt, Some(LiteralValue::Int(value))));
+        assert_eq!(values[..6], expected);
+        assert_eq!(values[6], (C1Token::MalformedInt, None));
+
+        let errors = [
+            ("0x", "Missing digits after '0x'"),
+            ("0b", "Missing digits after '0b'"),
+            ("09", "Invalid digit '9' in octal constant '09'"),
+            ("0b102", "Invalid digit '2' in binary constant '0b102'"),
+            ("0x1G", "Invalid digit 'G' in hexadecimal constant '0x1G'"),
+            ("1__0", "Misplaced digit separator in '1__0'"),
+            ("1_", "Misplaced digit separator in '1_'"),
+        ];
+        for (text, message) in errors {
+            let lexer = C1Lexer::new(text);
+            assert_eq!(
+                lexer.current_token(),
+                Some(C1Token::MalformedInt),
+                "{}",
+                text
+            );
+            assert_eq!(lexer.current_error().unwrap().message, message);
+        }
+        let lexer = C1Lexer::new("0x8000000000000000");
+        assert_eq!(lexer.current_token(), Some(C1Token::OverflowingInt));
+    }
+
+    #[test]
+    fn float_notations() {
+        let text = "1. 1.5f 1.5e3 2.F .5e1f 3e2F 1.e1 1_000.5 .2_5 1e1_0 1_0.0_1e+0_1";
+        let mut lexer = C1Lexer::new(text);
+        let mut values = Vec::new();
+        while let Some(token) = lexer.current_token() {
+            assert_eq!(token, C1Token::ConstFloat);
+            values.push(lexer.current_value().cloned());
+            lexer.eat();
+        }
+        let expected = [
+            1.0, 1.5, 1500.0, 2.0, 5.0, 300.0, 10.0, 1000.5, 0.25, 1e10, 100.1,
+        ]
+        .map(|value| Some(LiteralValue::Float(value)));
+        assert_eq!(values, expected);
+
+        for text in ["1_.5", "1._5", "1.5_", "1__0.5", "1_e5", ".5_e1"] {
+            let lexer = C1Lexer::new(text);
+            assert_eq!(lexer.current_token(), Some(C1Token::MalformedFloat));
+            assert_eq!(lexer.peek_token(), None, "{}", text);
+            let message = format!("Misplaced digit separator in '{}'", text);
+            assert_eq!(lexer.current_error().unwrap().message, message);
+        }
+
+        let lexer = C1Lexer::new("1.e");
+        assert_eq!(lexer.current_token(), Some(C1Token::MalformedFloat));
+        let lexer = C1Lexer::new("1f");
+        assert_eq!(lexer.current_token(), Some(C1Token::ConstInt));
+        assert_eq!(lexer.peek_token(), Some(C1Token::Identifier));
+
+        // Hexadecimal and binary floats are one erroneous token without a value
+        let options = LexerOptions {
+            recover: true,
+            ..LexerOptions::default()
+        };
+        for text in ["0x1.5", "0X.8p-3", "0b1.", "0x1.8p+"] {
+            let lexer = C1Lexer::new(text);
+            assert_eq!(lexer.current_token(), Some(C1Token::MalformedFloat));
+            assert_eq!(lexer.peek_token(), None, "{}", text);
+            let message = format!("Float constant '{}' is not in decimal notation", text);
+            assert_eq!(lexer.current_error().unwrap().message, message);
+            let lexer = C1Lexer::with_options(text, options);
+            assert_eq!(lexer.current_token(), Some(C1Token::ConstFloat));
+            assert_eq!(lexer.current_value(), None);
+        }
+    }
+
+    #[test]
+    fn identifiers_with_underscores() {
+        let text = "_tmp max_value __x1 _ if_ _if printfx true_ falsey printf(true)";
+        let mut lexer = C1Lexer::new(text);
+        let mut tokens = Vec::new();
+        while let (Some(token), Some(span)) = (lexer.current_token(), lexer.current_span()) {
+            tokens.push((token, &text[span]));
+            lexer.eat();
+        }
+        let mut expected: Vec<(C1Token, &str)> = text
+            .split(' ')
+            .take(9)
+            .map(|name| (C1Token::Identifier, name))
+            .collect();
+        expected.extend([
+            (C1Token::KwPrintf, "printf"),
+            (C1Token::LeftParenthesis, "("),
+            (C1Token::ConstBoolean, "true"),
+            (C1Token::RightParenthesis, ")"),
+        ]);
+        assert_eq!(tokens, expected);
+    }
+
+    #[test]
+    fn unicode_identifiers() {
+        let text = "größe = 1; if ünd";
+        let lexer = C1Lexer::new(text);
+        assert_eq!(lexer.current_token(), Some(C1Token::UnicodeIdentifier));
+        assert_eq!(
+            lexer.current_error().unwrap().message,
+            "Identifier 'größe' contains the non-ASCII character 'ö'"
+        );
+
+        let options = LexerOptions {
+            unicode_identifiers: true,
+            ..LexerOptions::default()
+        };
+        let mut lexer = C1Lexer::with_options(text, options);
+        let mut tokens = Vec::new();
+        while let (Some(token), Some(span)) = (lexer.current_token(), lexer.current_span()) {
+            tokens.push((token, &text[span]));
+            lexer.eat();
+        }
+        assert_eq!(
+            tokens,
+            vec![
+                (C1Token::Identifier, "größe"),
+                (C1Token::Assign, "="),
+                (C1Token::ConstInt, "1"),
+                (C1Token::Semicolon, ";"),
+                (C1Token::KwIf, "if"),
+                (C1Token::Identifier, "ünd"),
+            ]
+        );
+        // Characters that cannot be part of an identifier are still unexpected
+        let lexer = C1Lexer::with_options("x€", options);
+        assert_eq!(lexer.peek_token(), Some(C1Token::Error));
+    }
+
+    #[test]
+    fn arbitrary_lookahead() {
+        let mut lexer = C1Lexer::new("a\nb c");
+        assert_eq!(lexer.peek_nth(2), Some(C1Token::Identifier));
+        assert_eq!(lexer.peek_nth_span(2), Some(4..5));
+        assert_eq!(lexer.peek_nth(3), None);
+        assert_eq!(lexer.peek_nth(100), None);
+        // Nothing is buffered for positions behind the end of the text
+        assert_eq!(lexer.peek_nth_text(usize::MAX), None);
+        assert_eq!(lexer.tokens.len(), 4);
+        // Reading ahead does not change the current and the next token
+        assert_eq!(lexer.current_text(), Some("a"));
+        assert_eq!(lexer.peek_text(), Some("b"));
+        assert_eq!(lexer.peek_line_number(), Some(2));
+        lexer.eat();
+        lexer.eat();
+        assert_eq!(lexer.current_text(), Some("c"));
+        assert_eq!(lexer.current_line_number(), Some(2));
+        assert_eq!(lexer.peek_nth(1), None);
+    }
+
+    #[test]
+    fn rewind_to_checkpoint() {
+        let options = LexerOptions {
+            recover: true,
+            ..LexerOptions::default()
+        };
+        let mut lexer = C1Lexer::with_options("a $ b\nc $ d", options);
+        lexer.eat();
+        let checkpoint = lexer.checkpoint();
+        assert_eq!(lexer.diagnostics().len(), 1);
+        while lexer.current_token().is_some() {
+            lexer.eat();
+        }
+        assert_eq!(lexer.diagnostics().len(), 2);
+        assert_eq!(lexer.previous_span(), Some(10..11));
+
+        lexer.rewind(checkpoint.clone());
+        assert_eq!(lexer.diagnostics().len(), 1);
+        assert_eq!(lexer.previous_span(), Some(0..1));
+        assert_eq!(lexer.current_text(), Some("b"));
+        lexer.eat();
+        assert_eq!(lexer.current_text(), Some("c"));
+        assert_eq!(lexer.current_line_number(), Some(2));
+        assert_eq!(lexer.diagnostics().len(), 2);
+
+        // A checkpoint can be used more than once
+        lexer.rewind(checkpoint);
+        assert_eq!(lexer.peek_nth_text(2), Some("d"));
+    }
+
+    #[test]
+    fn token_stream() {
+        let text = "x /* c */\n  = 2.5;";
+        let tokens = tokenize(text);
+        let expected = [
+            (C1Token::Identifier, "x", 0..1, 1, 1),
+            (C1Token::Assign, "=", 12..13, 2, 3),
+            (C1Token::ConstFloat, "2.5", 14..17, 2, 5),
+            (C1Token::Semicolon, ";", 17..18, 2, 8),
+        ];
+        for (token, (kind, text, span, line, column)) in tokens.iter().zip(expected) {
+            assert_eq!(
+                (
+                    token.kind,
+                    token.text,
+                    token.span.clone(),
+                    token.line,
+                    token.column
+                ),
+                (kind, text, span, line, column)
+            );
+        }
+        assert_eq!(tokens.len(), 4);
+        assert_eq!(tokens[2].value, Some(LiteralValue::Float(2.5)));
+
+        // All tokens including the trivia cover the complete text
+        let all: String = C1Lexer::with_trivia(text).map(|token| token.text).collect();
+        assert_eq!(all, text);
+
+        let mut lexer = C1Lexer::new(text);
+        lexer.next();
+        assert_eq!(lexer.previous_span(), Some(0..1));
+        assert_eq!(lexer.count(), 3);
+    }
+
     #[test]
     fn float_recognition() {
         let lexer = C1Lexer::new("1.2");
@@ -1,12 +1,39 @@
+pub mod ast;
+pub mod checker;
+pub mod completion;
+pub mod cst;
+pub mod diagnostic;
+pub mod differential;
+pub mod dot;
+pub mod error_code;
+pub mod formatter;
+pub mod generator;
+pub mod grammar;
+pub mod grammar_analysis;
+pub mod highlight;
+pub mod incremental;
 mod lexer;
+pub mod lsp;
+pub mod minimizer;
 pub mod parser;
+pub mod recognizer;
+pub mod rename;
+pub mod source_map;
+pub mod trace;
 
 // Type definition for the Result that is being used by the parser. You may change it to anything
 // you want
-pub type ParseResult = Result<(), String>;
+pub type ParseResult = Result<(), Box<Diagnostic>>;
 
+pub use diagnostic::Diagnostic;
+
+pub use lexer::tokenize;
 pub use lexer::C1Lexer;
 pub use lexer::C1Token;
+pub use lexer::Checkpoint;
+pub use lexer::LexerOptions;
+pub use lexer::LiteralValue;
+pub use lexer::Token;
 
 // You will need a re-export of your C1Parser definition. Here is an example:
 // mod parser;
@@ -0,0 +1,454 @@
+use crate::checker::{self, Analysis, Symbol};
+use crate::completion::{self, CompletionKind};
+use crate::diagnostic::{Diagnostic, Position};
+use crate::lexer::{C1Lexer, C1Token};
+use crate::parser::C1Parser;
+use crate::rename;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::ops::Range;
+
+/// The semantic token types reported to the editor, indexed by [`token_type`]
+pub const TOKEN_TYPES: [&str; 8] = [
+    "keyword", "type", "function", "variable", "number", "string", "operator", "comment",
+];
+
+/// # Overview
+/// A language server for C(-1) that speaks the Language Server Protocol (JSON-RPC) over any pair of
+/// streams, usually stdin and stdout. Documents are synchronized in full on every change. The
+/// server offers diagnostics, go-to-definition, hover with inferred types, completion, rename,
+/// document symbols and semantic tokens.
+///
+/// # Examples
+/// ```
+/// use cb_3::lsp::Server;
+/// use serde_json::json;
+///
+/// let mut server = Server::new();
+/// let responses = server.handle(&json!({"jsonrpc": "2.0", "id": 1, "method": "shutdown"}));
+/// assert_eq!(responses, vec![json!({"jsonrpc": "2.0", "id": 1, "result": null})]);
+/// ```
+#[derive(Debug, Default)]
+pub struct Server {
+    /// The text of every open document by its URI
+    documents: HashMap<String, String>,
+    /// Set by the exit notification
+    exited: bool,
+}
+
+impl Server {
+    pub fn new() -> Server {
+        Server::default()
+    }
+
+    /// Read messages from `input` and write the responses to `output` until the client sends the
+    /// exit notification or closes the input stream
+    pub fn run(&mut self, mut input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+        while !self.exited {
+            let responses = match read_message(&mut input)? {
+                None => break,
+                Some(Ok(message)) => self.handle(&message),
+                Some(Err(error)) => vec![error_response(&Value::Null, -32700, &error.to_string())],
+            };
+            for response in responses {
+                write_message(&mut output, &response)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Handle a single request or notification and return the messages to send to the client
+    pub fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = &message["params"];
+        let uri = params["textDocument"]["uri"]
+            .as_str()
+            .unwrap_or_default()
+            .to_owned();
+        let result = match method {
+            "initialize" => json!({
+                "capabilities": {
+                    "textDocumentSync": 1,
+                    "definitionProvider": true,
+                    "hoverProvider": true,
+                    "completionProvider": {},
+                    "renameProvider": true,
+                    "documentSymbolProvider": true,
+                    "semanticTokensProvider": {
+                        "legend": { "tokenTypes": TOKEN_TYPES, "tokenModifiers": [] },
+                        "full": true
+                    }
+                },
+                "serverInfo": { "name": "c1-lsp" }
+            }),
+            "shutdown" => Value::Null,
+            "exit" => {
+                self.exited = true;
+                return Vec::new();
+            }
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                self.documents.insert(uri.clone(), text.to_owned());
+                return vec![self.publish_diagnostics(&uri)];
+            }
+            "textDocument/didChange" => {
+                // Full synchronization: the last change contains the whole text
+                if let Some(text) = params["contentChanges"]
+                    .as_array()
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change["text"].as_str())
+                {
+                    self.documents.insert(uri.clone(), text.to_owned());
+                }
+                return vec![self.publish_diagnostics(&uri)];
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                return vec![notification(
+                    "textDocument/publishDiagnostics",
+                    json!({ "uri": uri, "diagnostics": [] }),
+                )];
+            }
+            "textDocument/definition" => self.definition(&uri, &params["position"]),
+            "textDocument/hover" => self.hover(&uri, &params["position"]),
+            "textDocument/completion" => self.completion(&uri, &params["position"]),
+            "textDocument/rename" => match self.rename(&uri, params) {
+                Ok(result) => result,
+                Err(error) => return vec![error_response(&message["id"], -32803, &error)],
+            },
+            "textDocument/documentSymbol" => self.document_symbols(&uri),
+            "textDocument/semanticTokens/full" => self.semantic_tokens(&uri),
+            // Notifications that are not supported are ignored
+            _ if message.get("id").is_none() => return Vec::new(),
+            _ => {
+                let error = format!("Unsupported method '{}'", method);
+                return vec![error_response(&message["id"], -32601, &error)];
+            }
+        };
+        if message.get("id").is_none() {
+            return Vec::new();
+        }
+        vec![json!({ "jsonrpc": "2.0", "id": message["id"], "result": result })]
+    }
+
+    fn publish_diagnostics(&self, uri: &str) -> Value {
+        let text = self.documents.get(uri).map_or("", String::as_str);
+        let diagnostics: Vec<Value> = checker::diagnostics(text)
+            .iter()
+            .map(|diagnostic| lsp_diagnostic(text, diagnostic))
+            .collect();
+        notification(
+            "textDocument/publishDiagnostics",
+            json!({ "uri": uri, "diagnostics": diagnostics }),
+        )
+    }
+
+    /// Parse and check an open document. Nothing is known about documents with syntax errors.
+    fn analyze(&self, uri: &str) -> Option<(&str, Analysis)> {
+        let text = self.documents.get(uri)?;
+        let program = C1Parser::parse_ast(text).ok()?;
+        Some((text, checker::check(text, &program)))
+    }
+
+    fn definition(&self, uri: &str, position: &Value) -> Value {
+        let Some((text, analysis)) = self.analyze(uri) else {
+            return Value::Null;
+        };
+        match analysis.symbol_at(offset(text, position)) {
+            Some(symbol) => {
+                json!({ "uri": uri, "range": range(text, &analysis.definition(symbol)) })
+            }
+            None => Value::Null,
+        }
+    }
+
+    fn hover(&self, uri: &str, position: &Value) -> Value {
+        let Some((text, analysis)) = self.analyze(uri) else {
+            return Value::Null;
+        };
+        let offset = offset(text, position);
+        let contents = match (analysis.symbol_at(offset), analysis.type_at(offset)) {
+            (Some(symbol), _) => analysis.signature(symbol),
+            (None, Some(ty)) => ty.to_string(),
+            (None, None) => return Value::Null,
+        };
+        json!({ "contents": { "kind": "markdown", "value": format!("```c\n{}\n```", contents) } })
+    }
+
+    fn completion(&self, uri: &str, position: &Value) -> Value {
+        let text = self.documents.get(uri).map_or("", String::as_str);
+        let items: Vec<Value> = completion::complete(text, offset(text, position))
+            .into_iter()
+            .map(|completion| {
+                let kind = match completion.kind {
+                    CompletionKind::Function => 3,
+                    CompletionKind::Variable => 6,
+                    CompletionKind::Type | CompletionKind::Keyword => 14,
+                };
+                json!({ "label": completion.label, "kind": kind })
+            })
+            .collect();
+        json!(items)
+    }
+
+    /// Rename a symbol in all open documents
+    fn rename(&self, uri: &str, params: &Value) -> Result<Value, String> {
+        let text = self.documents.get(uri).map_or("", String::as_str);
+        let files: Vec<(&str, &str)> = self
+            .documents
+            .iter()
+            .map(|(uri, text)| (uri.as_str(), text.as_str()))
+            .collect();
+        let new_name = params["newName"].as_str().unwrap_or_default();
+        let edit = rename::rename(&files, uri, offset(text, &params["position"]), new_name)
+            .map_err(|error| error.to_string())?;
+        let changes: serde_json::Map<String, Value> = edit
+            .changes
+            .iter()
+            .map(|(uri, edits)| {
+                let text = &self.documents[uri];
+                let edits: Vec<Value> = edits
+                    .iter()
+                    .map(|edit| json!({ "range": range(text, &edit.range), "newText": edit.new_text }))
+                    .collect();
+                (uri.clone(), json!(edits))
+            })
+            .collect();
+        Ok(json!({ "changes": changes }))
+    }
+
+    fn document_symbols(&self, uri: &str) -> Value {
+        let Some((text, analysis)) = self.analyze(uri) else {
+            return json!([]);
+        };
+        let symbols: Vec<Value> = analysis
+            .functions
+            .iter()
+            .enumerate()
+            .map(|(index, function)| {
+                let variables: Vec<Value> = analysis
+                    .variables
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, variable)| variable.function == index)
+                    .map(|(variable, _)| {
+                        let span = analysis.definition(Symbol::Variable(variable));
+                        json!({
+                            "name": analysis.name(Symbol::Variable(variable)),
+                            "detail": analysis.signature(Symbol::Variable(variable)),
+                            "kind": 13,
+                            "range": range(text, &span),
+                            "selectionRange": range(text, &span),
+                        })
+                    })
+                    .collect();
+                json!({
+                    "name": function.name,
+                    "detail": analysis.signature(Symbol::Function(index)),
+                    "kind": 12,
+                    "range": range(text, &function.definition),
+                    "selectionRange": range(text, &function.span),
+                    "children": variables,
+                })
+            })
+            .collect();
+        json!(symbols)
+    }
+
+    /// Classify every token of the document. Identifiers followed by '(' are functions, all other
+    /// identifiers are variables. Tokens that span multiple lines are split into one token per line.
+    fn semantic_tokens(&self, uri: &str) -> Value {
+        let text = self.documents.get(uri).map_or("", String::as_str);
+        let tokens: Vec<(C1Token, Range<usize>)> = C1Lexer::with_trivia(text)
+            .map(|token| (token.kind, token.span))
+            .collect();
+
+        let mut data = Vec::new();
+        let (mut previous_line, mut previous_start) = (0, 0);
+        for (index, (token, span)) in tokens.iter().enumerate() {
+            let next = tokens[index + 1..]
+                .iter()
+                .find(|(token, _)| !token.is_trivia());
+            let is_call = next.is_some_and(|(token, _)| *token == C1Token::LeftParenthesis);
+            let Some(token_type) = token_type(*token, is_call) else {
+                continue;
+            };
+            let mut line_start = span.start;
+            for line in text[span.clone()].split_inclusive('\n') {
+                let content = line.trim_end_matches('\n');
+                let start = lsp_position(text, line_start);
+                if !content.is_empty() {
+                    let delta_line = start.line - previous_line;
+                    let delta_start = if delta_line == 0 {
+                        start.column - previous_start
+                    } else {
+                        start.column
+                    };
+                    let length = content.encode_utf16().count();
+                    data.extend([delta_line, delta_start, length, token_type, 0]);
+                    previous_line = start.line;
+                    previous_start = start.column;
+                }
+                line_start += line.len();
+            }
+        }
+        json!({ "data": data })
+    }
+}
+
+/// Return the index of the semantic token type in [`TOKEN_TYPES`], or `None` for tokens that are
+/// not highlighted
+pub fn token_type(token: C1Token, is_call: bool) -> Option<usize> {
+    match token {
+        C1Token::KwDo
+        | C1Token::KwElse
+        | C1Token::KwFor
+        | C1Token::KwIf
+        | C1Token::KwPrintf
+        | C1Token::KwReturn
+        | C1Token::KwWhile
+        | C1Token::ConstBoolean => Some(0),
+        C1Token::KwBoolean | C1Token::KwFloat | C1Token::KwInt | C1Token::KwVoid => Some(1),
+        C1Token::Identifier if is_call => Some(2),
+        C1Token::Identifier => Some(3),
+        C1Token::ConstInt | C1Token::ConstFloat => Some(4),
+        C1Token::ConstString => Some(5),
+        C1Token::Plus
+        | C1Token::Minus
+        | C1Token::Asterisk
+        | C1Token::Slash
+        | C1Token::Assign
+        | C1Token::Equal
+        | C1Token::NotEqual
+        | C1Token::Less
+        | C1Token::Greater
+        | C1Token::LessEqual
+        | C1Token::GreaterEqual
+        | C1Token::And
+        | C1Token::Or => Some(6),
+        C1Token::CComment | C1Token::CPPComment => Some(7),
+        _ => None,
+    }
+}
+
+/// Read one message with its `Content-Length` header. `None` is returned at the end of the input.
+/// A header block without a valid `Content-Length` is skipped and returned as an error, like
+/// content that is not valid JSON.
+pub fn read_message(input: &mut impl BufRead) -> io::Result<Option<serde_json::Result<Value>>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let Some(length) = length else {
+        let error = serde::de::Error::custom("Missing Content-Length header");
+        return Ok(Some(Err(error)));
+    };
+    let mut content = vec![0; length];
+    input.read_exact(&mut content)?;
+    Ok(Some(serde_json::from_slice(&content)))
+}
+
+/// Write one message with its `Content-Length` header
+pub fn write_message(output: &mut impl Write, message: &Value) -> io::Result<()> {
+    let content = message.to_string();
+    write!(
+        output,
+        "Content-Length: {}\r\n\r\n{}",
+        content.len(),
+        content
+    )?;
+    output.flush()
+}
+
+fn notification(method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "method": method, "params": params })
+}
+
+fn error_response(id: &Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+fn lsp_diagnostic(text: &str, diagnostic: &Diagnostic) -> Value {
+    let span = diagnostic.start.offset(text)..diagnostic.end.offset(text);
+    json!({
+        "range": range(text, &span),
+        "severity": 1,
+        "code": diagnostic.code.map(|code| code.code()),
+        "source": "c1",
+        "message": diagnostic.message,
+    })
+}
+
+/// Convert a byte offset to a zero-based line and a column in UTF-16 code units, as used by LSP
+fn lsp_position(text: &str, offset: usize) -> Position {
+    let before = &text[..offset];
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+    Position {
+        line: before.matches('\n').count(),
+        column: before[line_start..].encode_utf16().count(),
+    }
+}
+
+fn range(text: &str, span: &Range<usize>) -> Value {
+    let start = lsp_position(text, span.start);
+    let end = lsp_position(text, span.end);
+    json!({
+        "start": { "line": start.line, "character": start.column },
+        "end": { "line": end.line, "character": end.column },
+    })
+}
+
+/// Convert an LSP position to a byte offset. Positions beyond the end of a line are clamped.
+fn offset(text: &str, position: &Value) -> usize {
+    let line = position["line"].as_u64().unwrap_or_default() as usize;
+    let character = position["character"].as_u64().unwrap_or_default() as usize;
+    let line_start = line_start(text, line);
+    let mut units = 0;
+    for (index, char) in text[line_start..].char_indices() {
+        if units >= character || char == '\n' {
+            return line_start + index;
+        }
+        units += char.len_utf16();
+    }
+    text.len()
+}
+
+fn line_start(text: &str, line: usize) -> usize {
+    if line == 0 {
+        return 0;
+    }
+    text.match_indices('\n')
+        .nth(line - 1)
+        .map_or(text.len(), |(index, _)| index + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diagnostic::Position;
+    use crate::lsp::{lsp_position, offset};
+    use serde_json::json;
+
+    #[test]
+    fn positions_are_converted() {
+        let text = "int ä() {\n  x = 1;\n}";
+        assert_eq!(lsp_position(text, 12), Position { line: 1, column: 1 });
+        assert_eq!(offset(text, &json!({"line": 1, "character": 2})), 13);
+        assert_eq!(offset(text, &json!({"line": 0, "character": 5})), 6);
+        assert_eq!(offset(text, &json!({"line": 0, "character": 99})), 10);
+        assert_eq!(Position { line: 1, column: 6 }.offset(text), 6);
+        assert_eq!(Position { line: 3, column: 2 }.offset(text), text.len());
+    }
+}
@@ -0,0 +1,231 @@
+use crate::ast::{Expression, ExpressionKind, Statement, StatementKind};
+use crate::formatter;
+use crate::lexer::C1Lexer;
+use crate::parser::C1Parser;
+use std::ops::Range;
+
+/// Literals that an expression is replaced with, one of them usually has the right type
+const LITERALS: [&str; 3] = ["0", "true", "0.0"];
+
+/// # Overview
+/// Reduce a program to a small one for which the predicate still holds, e.g. one that still
+/// crashes the parser or on which backends still disagree. The predicate must hold for the given
+/// program.
+///
+/// The reduction alternates two passes until neither of them makes progress:
+///
+/// - [`reduce_ast`] removes functions and statements, replaces if statements with their body, and
+///   replaces expressions with one of their operands or with a literal. This pass needs a program that can be parsed.
+/// - [`reduce_tokens`] removes tokens by delta debugging, and works on any text.
+///
+/// The result is formatted with the [formatter](crate::formatter) if the predicate still holds for
+/// the formatted program.
+///
+/// To reduce a program that makes the parser panic, the predicate can call the parser inside
+/// [`std::panic::catch_unwind`].
+///
+/// # Examples
+/// ```
+/// use cb_3::minimizer;
+/// use cb_3::parser::C1Parser;
+///
+/// let text = "int f() { x = 1; return x; }\nvoid main() { printf(f() + 2.5 * 3); }";
+/// let reduced = minimizer::reduce(text, |program| {
+///     C1Parser::parse(program).is_ok() && program.contains("2.5")
+/// });
+/// assert_eq!(reduced, "void main() {\n    printf(2.5);\n}\n");
+/// ```
+pub fn reduce(text: &str, predicate: impl Fn(&str) -> bool) -> String {
+    let mut program = text.to_owned();
+    loop {
+        let reduced = reduce_tokens(&reduce_ast(&program, &predicate), &predicate);
+        if reduced == program {
+            break;
+        }
+        program = reduced;
+    }
+    match formatter::format(&program) {
+        Ok(formatted) if predicate(&formatted) => formatted,
+        _ => program,
+    }
+}
+
+/// Remove functions and statements, unwrap if statements and simplify expressions, as long as the
+/// predicate holds.
+/// The text is returned unchanged if it cannot be parsed.
+pub fn reduce_ast(text: &str, predicate: impl Fn(&str) -> bool) -> String {
+    let mut program = text.to_owned();
+    'reduce: loop {
+        for (span, replacements) in candidates(&program) {
+            for replacement in replacements {
+                if program[span.clone()] == replacement {
+                    continue;
+                }
+                let mut candidate = program.clone();
+                candidate.replace_range(span.clone(), &replacement);
+                if predicate(&candidate) {
+                    program = candidate;
+                    continue 'reduce;
+                }
+            }
+        }
+        return program;
+    }
+}
+
+/// Return the spans of all functions, statements and expressions, outer ones first, together with
+/// the texts to try in their place
+fn candidates(text: &str) -> Vec<(Range<usize>, Vec<String>)> {
+    let Ok(program) = C1Parser::parse_ast(text) else {
+        return Vec::new();
+    };
+    let mut candidates = Vec::new();
+    let mut statements: Vec<&Statement> = Vec::new();
+    for function in &program.functions {
+        candidates.push((function.span.clone(), vec![String::new()]));
+        statements.extend(&function.body);
+    }
+    let mut expressions: Vec<&Expression> = Vec::new();
+    while !statements.is_empty() {
+        let mut nested = Vec::new();
+        for statement in statements {
+            let mut replacements = vec![String::new()];
+            match &statement.kind {
+                StatementKind::Block(body) => nested.extend(body),
+                StatementKind::If { condition, body } => {
+                    replacements.push(text[body.span.clone()].to_owned());
+                    expressions.push(condition);
+                    nested.push(body.as_ref());
+                }
+                StatementKind::Return(value) => expressions.extend(value),
+                StatementKind::Printf(value) | StatementKind::Assignment { value, .. } => {
+                    expressions.push(value)
+                }
+                StatementKind::Call(_) => {}
+            }
+            candidates.push((statement.span.clone(), replacements));
+        }
+        statements = nested;
+    }
+    while !expressions.is_empty() {
+        let mut nested = Vec::new();
+        for expression in expressions {
+            let operands: Vec<&Expression> = match &expression.kind {
+                ExpressionKind::Assignment { value, .. } => vec![value],
+                ExpressionKind::Binary { left, right, .. } => vec![left, right],
+                ExpressionKind::Negation(operand) => vec![operand],
+                ExpressionKind::Literal(_) => continue,
+                ExpressionKind::Call(_) | ExpressionKind::Variable(_) => Vec::new(),
+            };
+            // Operators in an operand that takes the place of the expression keep their precedence
+            let mut replacements: Vec<String> = operands
+                .iter()
+                .map(|operand| match operand.kind {
+                    ExpressionKind::Binary { .. }
+                    | ExpressionKind::Negation(_)
+                    | ExpressionKind::Assignment { .. } => {
+                        format!("({})", &text[operand.span.clone()])
+                    }
+                    _ => text[operand.span.clone()].to_owned(),
+                })
+                .collect();
+            replacements.extend(LITERALS.map(String::from));
+            candidates.push((expression.span.clone(), replacements));
+            nested.extend(operands);
+        }
+        expressions = nested;
+    }
+    candidates
+}
+
+/// Remove tokens by delta debugging as long as the predicate holds, and return the remaining
+/// tokens separated by spaces. Whitespace and comments are dropped first, so the text is
+/// returned unchanged if the predicate depends on them.
+/// ```
+/// use cb_3::minimizer;
+///
+/// let reduced = minimizer::reduce_tokens("int f() { return 1 + 2; }", |text| text.contains("+ 2"));
+/// assert_eq!(reduced, "+ 2");
+/// ```
+pub fn reduce_tokens(text: &str, predicate: impl Fn(&str) -> bool) -> String {
+    let mut tokens: Vec<&str> = C1Lexer::new(text).map(|token| token.text).collect();
+    if !predicate(&tokens.join(" ")) {
+        return text.to_owned();
+    }
+
+    // Remove the complement of one of the chunks, and split into smaller chunks when none of
+    // them can be removed
+    let mut chunks = 2;
+    while !tokens.is_empty() {
+        let size = tokens.len().div_ceil(chunks);
+        let reduced = (0..tokens.len()).step_by(size).find_map(|start| {
+            let mut complement = tokens[..start].to_vec();
+            complement.extend(&tokens[(start + size).min(tokens.len())..]);
+            predicate(&complement.join(" ")).then_some(complement)
+        });
+        match reduced {
+            Some(complement) => {
+                tokens = complement;
+                chunks = (chunks - 1).max(2);
+            }
+            None if size == 1 => break,
+            None => chunks = (chunks * 2).min(tokens.len()),
+        }
+    }
+    tokens.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::checker;
+    use crate::minimizer::{reduce, reduce_ast, reduce_tokens};
+    use crate::parser::C1Parser;
+    use std::panic;
+
+    const EXAMPLE: &str = include_str!("../tests/data/beispiel.c-1");
+
+    #[test]
+    fn tokens_are_removed() {
+        let predicate = |text: &str| text.contains("blub4") && text.contains("3.14159");
+        assert_eq!(reduce_tokens(EXAMPLE, predicate), "blub4 3.14159");
+        // Nothing can be removed if the predicate needs all tokens
+        assert_eq!(reduce_tokens("a b", |text| text == "a b"), "a b");
+        assert_eq!(reduce_tokens("a b", |text| text == "a  b"), "a b");
+    }
+
+    #[test]
+    fn valid_programs_stay_valid() {
+        let predicate = |text: &str| checker::diagnostics(text).is_empty() && text.contains("3.14");
+        assert_eq!(
+            reduce(EXAMPLE, predicate),
+            "float blah() {\n    return 3.14159;\n}\n"
+        );
+    }
+
+    #[test]
+    fn expressions_are_simplified() {
+        let text = "void main() { x = (1 + 2) * (3 - y()); }";
+        let predicate = |text: &str| C1Parser::parse(text).is_ok() && text.contains('*');
+        assert_eq!(
+            reduce_ast(text, predicate),
+            "void main() { x = (1) * (3); }"
+        );
+        let predicate = |text: &str| C1Parser::parse(text).is_ok() && text.contains("y()");
+        assert_eq!(reduce_ast(text, predicate), "void main() { x = (y()); }");
+    }
+
+    #[test]
+    fn reduces_crashes() {
+        // A consumer of the syntax tree that crashes on nested negations
+        let crashes = |text: &str| {
+            C1Parser::parse_ast(text).is_ok_and(|program| {
+                panic::catch_unwind(|| assert!(!program.dump().contains("(- (- "))).is_err()
+            })
+        };
+        let reduced = reduce(
+            "void f() { printf(1); }\nint main() { if (true) return 2 * (-(-(3) * 2)); return 4; }",
+            crashes,
+        );
+        assert_eq!(reduced, "int main() {\n    return -(-3);\n}\n");
+    }
+}
@@ -1,108 +1,343 @@
- use crate::lexer::{C1Lexer, C1Token};
+ use crate::ast::{
+     BinaryOperator, Expression, ExpressionKind, FunctionDefinition, Identifier, Literal, LiteralKind, Program,
+     Statement, StatementKind, Type,
+ };
+ use crate::diagnostic::{Diagnostic, Fix, Position};
+ use crate::error_code::ErrorCode;
+ use crate::lexer::{C1Lexer, C1Token, LexerOptions};
+ use crate::source_map::{FileId, SourceMap};
+ use crate::trace::Trace;
  use crate::ParseResult;
- use std::ops::{Deref, DerefMut};
+ use serde::Serialize;
+ use std::ops::{Deref, DerefMut, Range};
+
+ /// Result of a single grammar rule: the parsed syntax tree node or the first syntax error
+ type RuleResult<T> = Result<T, Box<Diagnostic>>;
+
+ /// The return types, which start a function definition
+ const TYPES: &[C1Token] = &[C1Token::KwBoolean, C1Token::KwFloat, C1Token::KwInt, C1Token::KwVoid];
+
+ /// Tokens at which parsing continues after a syntax error in a statement
+ const STATEMENT_RECOVERY: &[C1Token] = &[
+     C1Token::Semicolon, C1Token::LeftBrace, C1Token::RightBrace, C1Token::KwIf, C1Token::KwReturn, C1Token::KwPrintf,
+     C1Token::KwBoolean, C1Token::KwFloat, C1Token::KwInt, C1Token::KwVoid,
+ ];
+
+ /// The rules of the C(-1) grammar, each of them is implemented by a method of the parser
+ #[derive(Serialize, Debug, PartialEq, Eq, Copy, Clone, Hash)]
+ #[serde(rename_all = "snake_case")]
+ pub enum Rule {
+     Program,
+     FunctionDefinition,
+     FunctionCall,
+     StatementList,
+     Block,
+     Statement,
+     IfStatement,
+     ReturnStatement,
+     Printf,
+     Type,
+     StatAssignment,
+     Assignment,
+     Expr,
+     SimpExpr,
+     Term,
+     Factor,
+ }
+
+ impl Rule {
+     /// Return the name of the rule as used in the grammar
+     pub fn name(&self) -> &'static str {
+         match self {
+             Rule::Program => "program",
+             Rule::FunctionDefinition => "function_definition",
+             Rule::FunctionCall => "function_call",
+             Rule::StatementList => "statement_list",
+             Rule::Block => "block",
+             Rule::Statement => "statement",
+             Rule::IfStatement => "if_statement",
+             Rule::ReturnStatement => "return_statement",
+             Rule::Printf => "printf",
+             Rule::Type => "type",
+             Rule::StatAssignment => "stat_assignment",
+             Rule::Assignment => "assignment",
+             Rule::Expr => "expr",
+             Rule::SimpExpr => "simp_expr",
+             Rule::Term => "term",
+             Rule::Factor => "factor",
+         }
+     }
+ }
+
+ /// A step of the derivation of the parsed text. The events of a successful parse form a well nested
+ /// sequence, after a syntax error the rules that were active are not exited.
+ #[derive(Debug, PartialEq, Eq, Clone)]
+ pub enum Event {
+     Enter(Rule),
+     Token { token: C1Token, span: Range<usize> },
+     Exit(Rule),
+ }
 
- pub struct C1Parser<'a>(C1Lexer<'a>);
+ pub struct C1Parser<'a> {
+     lexer: C1Lexer<'a>,
+     events: Option<Vec<Event>>,
+     trace: Option<Trace>,
+     /// Syntax errors the parser has recovered from. `None` if parsing stops at the first error.
+     errors: Option<Vec<Diagnostic>>,
+ }
  // Implement Deref and DerefMut to enable the direct use of the lexer's methods
  impl<'a> Deref for C1Parser<'a> {
      type Target = C1Lexer<'a>;
 
      fn deref(&self) -> &Self::Target {
-         &self.0
+         &self.lexer
      }
  }
 
  impl<'a> DerefMut for C1Parser<'a> {
      fn deref_mut(&mut self) -> &mut Self::Target {
-         &mut self.0
+         &mut self.lexer
      }
  }
 
  impl<'a> C1Parser<'a> {
      pub fn parse(text: &str) -> ParseResult {
+         Self::parse_ast(text).map(|_| ())
+     }
+
+     /// Parse the given text into an abstract syntax tree
+     pub fn parse_ast(text: &str) -> RuleResult<Program> {
          let mut parser = Self::initialize_parser(text);
          parser.program()
      }
 
-     fn initialize_parser(text: &str) -> C1Parser {
-         C1Parser(C1Lexer::new(text))
+     /// Parse a file of a source map into an abstract syntax tree. Spans in the tree are local to
+     /// the text of the file, which the tree and a syntax error refer to.
+     /// ```
+     /// use cb_3::parser::C1Parser;
+     /// use cb_3::source_map::SourceMap;
+     ///
+     /// let mut sources = SourceMap::new();
+     /// let lib = sources.add("lib.c-1", "int one() { return 1; }");
+     /// let main = sources.add("main.c-1", "void main() {\n    printf(one())\n}");
+     ///
+     /// let program = C1Parser::parse_file(&sources, lib).unwrap();
+     /// assert_eq!(program.file, Some(lib));
+     /// assert_eq!(sources.global_span(lib, program.functions[0].name.span.clone()), 4..7);
+     ///
+     /// let error = C1Parser::parse_file(&sources, main).unwrap_err();
+     /// assert_eq!(error.to_string(), "main.c-1:3:1: error[E0001]: Expected ';' after statement, found '}'");
+     /// assert_eq!(sources.span_of(&error), Some(56..57));
+     /// ```
+     pub fn parse_file(sources: &SourceMap, file: FileId) -> RuleResult<Program> {
+         let mut parser = C1Parser { lexer: C1Lexer::for_file(sources, file), events: None, trace: None, errors: None };
+         parser.program().map_err(|diagnostic| Box::new(diagnostic.in_source_file(sources, file)))
+     }
+
+     /// Parse the given text into an abstract syntax tree and record the derivation as a list of events
+     pub fn parse_with_events(text: &str) -> (RuleResult<Program>, Vec<Event>) {
+         let mut parser = Self::initialize_parser(text);
+         parser.events = Some(Vec::new());
+         let result = parser.program();
+         (result, parser.events.unwrap_or_default())
+     }
+
+     /// Parse the given text into an abstract syntax tree and record a [`Trace`] of all rule
+     /// applications, decisions and consumed tokens
+     pub fn parse_traced(text: &str) -> (RuleResult<Program>, Trace) {
+         let mut parser = Self::initialize_parser(text);
+         parser.trace = Some(Trace::default());
+         let result = parser.program();
+         let mut trace = parser.trace.unwrap_or_default();
+         if let Err(diagnostic) = &result {
+             trace.fail(&diagnostic.message);
+         }
+         (result, trace)
+     }
+
+     /// Parse the given text and return all problems that were found. An empty list means that the
+     /// text is a valid C(-1) program. Parsing stops at the first syntax error, but all lexical
+     /// errors of the text are reported.
+     /// ```
+     /// use cb_3::parser::C1Parser;
+     ///
+     /// let diagnostics = C1Parser::diagnostics("void main() { x = 1 # 2; }\n/* end");
+     /// let messages: Vec<String> = diagnostics.iter().map(ToString::to_string).collect();
+     /// assert_eq!(
+     ///     messages,
+     ///     vec![
+     ///         "1:21: error[E0016]: Unexpected character '#'",
+     ///         "1:23: error[E0001]: Expected ';' after statement, found '2'",
+     ///         "2:1: error[E0017]: Unterminated block comment",
+     ///     ]
+     /// );
+     /// ```
+     pub fn diagnostics(text: &str) -> Vec<Diagnostic> {
+         let mut parser = Self::initialize_recovering_parser(text);
+         let errors = parser.program().err().into_iter().map(|diagnostic| *diagnostic).collect();
+         while parser.current_token().is_some() {
+             parser.lexer.eat();
+         }
+         parser.with_lexical_errors(errors)
+     }
+
+     /// Parse the given text without stopping at syntax errors. Missing semicolons, parentheses and
+     /// braces are inserted, and statements or function definitions that cannot be parsed are
+     /// skipped. The returned tree contains everything that could be parsed, which makes this
+     /// suitable for incomplete programs in an editor.
+     /// ```
+     /// use cb_3::parser::C1Parser;
+     ///
+     /// let (program, errors) = C1Parser::parse_recovering("void main() { x = ; y = 1 }");
+     /// assert_eq!(program.dump(), "(void main\n  (= y 1))\n");
+     /// assert_eq!(errors.len(), 2);
+     /// ```
+     pub fn parse_recovering(text: &str) -> (Program, Vec<Diagnostic>) {
+         let mut parser = Self::initialize_recovering_parser(text);
+         parser.errors = Some(Vec::new());
+         let program = parser.program().unwrap_or_else(|diagnostic| {
+             parser.errors.get_or_insert_with(Vec::new).push(*diagnostic);
+             Program { functions: Vec::new(), file: None }
+         });
+         let errors = parser.errors.take().unwrap_or_default();
+         (program, parser.with_lexical_errors(errors))
+     }
+
+     /// Parse the function definitions in the given byte range of the text, which has to start at a
+     /// token. Parsing continues until the first token at or after the end of the range, so the last
+     /// function definition may extend beyond it. Spans and diagnostics refer to the complete text.
+     pub fn parse_range(text: &str, range: Range<usize>) -> RuleResult<Vec<FunctionDefinition>> {
+         let mut parser = C1Parser { lexer: C1Lexer::starting_at(text, range.start), events: None, trace: None, errors: None };
+         let mut functions = Vec::new();
+         while parser.current_token().is_some() && parser.current_start() < range.end {
+             functions.push(parser.function_definition()?);
+         }
+         Ok(functions)
+     }
+
+     fn initialize_parser(text: &str) -> C1Parser<'_> {
+         C1Parser { lexer: C1Lexer::new(text), events: None, trace: None, errors: None }
+     }
+
+     /// Initialize a parser whose lexer recovers from lexical errors, so that parsing can continue
+     /// after them
+     fn initialize_recovering_parser(text: &str) -> C1Parser<'_> {
+         let options = LexerOptions { recover: true, ..LexerOptions::default() };
+         C1Parser { lexer: C1Lexer::with_options(text, options), events: None, trace: None, errors: None }
+     }
+
+     /// Combine the given syntax errors with the lexical errors the lexer has recovered from, in
+     /// the order of their positions. A syntax error at a token that already has a lexical error
+     /// is a consequence of it and is dropped.
+     fn with_lexical_errors(&self, mut errors: Vec<Diagnostic>) -> Vec<Diagnostic> {
+         let mut diagnostics = self.lexer.diagnostics().to_vec();
+         errors.retain(|error| diagnostics.iter().all(|lexical| lexical.start != error.start));
+         diagnostics.append(&mut errors);
+         diagnostics.sort_by_key(|diagnostic| (diagnostic.start.line, diagnostic.start.column));
+         diagnostics
      }
 
      /// program ::= ( function_definition )* <EOF>
-     fn program(&mut self) -> ParseResult {
+     fn program(&mut self) -> RuleResult<Program> {
+         self.enter(Rule::Program);
+         let mut functions = Vec::new();
          while self.current_token().is_some() {
-             self.function_definition()?;
+             self.decide("function_definition");
+             let start = self.current_start();
+             match self.function_definition() {
+                 Ok(function) => functions.push(function),
+                 Err(diagnostic) => self.recover(diagnostic, start, TYPES)?,
+             }
          }
-         Ok(())
+         self.decide("end");
+         let file = self.lexer.file();
+         self.exit(Rule::Program, Program { functions, file })
      }
 
      /// function_definition  ::= type <ID> "(" ")" "{" statement_list "}"
-     fn function_definition(&mut self) -> ParseResult {
-         self.return_type()?;
-         self.identifier()?;
+     fn function_definition(&mut self) -> RuleResult<FunctionDefinition> {
+         self.enter(Rule::FunctionDefinition);
+         let start = self.current_start();
+         let return_type = self.return_type()?;
+         let name = self.identifier()?;
          self.check_and_eat_token(&C1Token::LeftParenthesis, "Expected '(' after function name")?;
          self.check_and_eat_token(&C1Token::RightParenthesis, "Expected ')' after function parameters")?;
          self.check_and_eat_token(&C1Token::LeftBrace, "Expected '{' after function name")?;
-         self.statement_list()?;
+         let body = self.statement_list()?;
          self.check_and_eat_token(&C1Token::RightBrace, "Expected '}' after function parameters")?;
-         Ok(())
+         let span = self.span_from(start);
+         self.exit(Rule::FunctionDefinition, FunctionDefinition { return_type, name, body, span })
      }
 
      ///block ::= "{" statement_list "}"
      ///         | statement
-     fn block(&mut self) -> ParseResult {
-         if self.current_matches(&C1Token::LeftBrace) {
-             self.block_first_part()?;
+     fn block(&mut self) -> RuleResult<Statement> {
+         self.enter(Rule::Block);
+         let block = if self.current_matches(&C1Token::LeftBrace) {
+             self.decide("\"{\" statement_list \"}\"");
+             self.block_first_part()?
          }
          else {
-             self.block_second_part()?;
-         }
-         Ok(())
+             self.decide("statement");
+             self.block_second_part()?
+         };
+         self.exit(Rule::Block, block)
      }
 
      ///block ::= "{" statement_list "}"
-     fn block_first_part(&mut self) -> ParseResult {
+     fn block_first_part(&mut self) -> RuleResult<Statement> {
+         let start = self.current_start();
          self.check_and_eat_token(&C1Token::LeftBrace, "Expected '{' before statement list")?;
-         self.statement_list()?;
+         let statements = self.statement_list()?;
          self.check_and_eat_token(&C1Token::RightBrace, "Expected '}' after statement list")?;
-         Ok(())
+         Ok(Statement { kind: StatementKind::Block(statements), span: self.span_from(start) })
      }
 
      ///block ::= statement
-     fn block_second_part(&mut self) -> ParseResult {
-         self.statement()?;
-         Ok(())
+     fn block_second_part(&mut self) -> RuleResult<Statement> {
+         self.statement()
      }
 
      ///function_call ::= <ID> "(" ")"
-     fn function_call(&mut self) -> ParseResult {
-         self.identifier()?;
+     fn function_call(&mut self) -> RuleResult<Identifier> {
+         self.enter(Rule::FunctionCall);
+         let name = self.identifier()?;
          self.check_and_eat_token(&C1Token::LeftParenthesis, "Expected '(' after function name")?;
          self.check_and_eat_token(&C1Token::RightParenthesis, "Expected ')' after function name")?;
-         Ok(())
+         self.exit(Rule::FunctionCall, name)
      }
 
      ///statement_list ::= ( block )*
-     fn statement_list(&mut self) -> ParseResult {
+     fn statement_list(&mut self) -> RuleResult<Vec<Statement>> {
          let statement: &[C1Token] = &[C1Token::KwIf, C1Token::KwReturn, C1Token::KwPrintf, C1Token::Identifier];
+         let mut statements = Vec::new();
+         self.enter(Rule::StatementList);
          loop {
-             //if Token is a normal statement or maybe the special case: function_call
-             if self.any_match_current(statement) || (self.current_matches(&C1Token::Identifier) && self.next_matches(&C1Token::LeftParenthesis)) {
-                 self.block()?;
+             let start = self.current_start();
+             //if Token is a normal statement or a nested statement list starting with "{"
+             let result = if self.any_match_current(statement) || self.current_matches(&C1Token::LeftBrace) {
+                 self.decide("block");
+                 self.block().map(|block| statements.push(block))
              }
-             //or if token is a statement list and hence starts with "{" and then the same as first condition
-             else if self.current_matches(&C1Token::LeftBrace) {
-                 self.check_and_eat_token(&C1Token::LeftBrace, "\"{\" ist missing")?;
-                 self.statement_list()?;
-                 self.check_and_eat_token(&C1Token::RightBrace, "\"}\" ist missing")?;
+             else if self.current_matches(&C1Token::RightBrace) || self.current_token().is_none() {
+                 self.decide("end");
+                 return self.exit(Rule::StatementList, statements);
              }
-             else if self.current_matches(&C1Token::RightBrace) || !self.current_token().is_some(){
-                 return Ok(());
+             else if self.errors.is_some() && self.any_match_current(TYPES) {
+                 // The next function definition starts, the closing brace is reported by the caller
+                 self.decide("end");
+                 return self.exit(Rule::StatementList, statements);
              }
              else {
-                 return Err(self.error_message_current("Invalid statement list"));
+                 self.decide("error");
+                 Err(self.error_message_current(ErrorCode::InvalidStatement, "Invalid statement list"))
+             };
+             if let Err(diagnostic) = result {
+                 self.recover(diagnostic, start, STATEMENT_RECOVERY)?;
+                 if self.current_matches(&C1Token::Semicolon) {
+                     self.eat();
+                 }
              }
-             //
          }
      }
 
@@ -111,44 +346,69 @@
      ///             | printf ";"
      ///             | stat_assignment ";"
      ///             | function_call ";"
-     fn statement(&mut self) -> ParseResult {
-         let last_token: Option<C1Token>  = self.current_token().clone();
-         match self.current_token() {
-             Some(C1Token::KwIf) => self.if_statement()?,
-             Some(C1Token::KwReturn) => self.return_statement()?,
-             Some(C1Token::KwPrintf) => self.printf()?,
+     fn statement(&mut self) -> RuleResult<Statement> {
+         self.enter(Rule::Statement);
+         let start = self.current_start();
+         let kind = match self.current_token() {
+             Some(C1Token::KwIf) => {
+                 self.decide("if_statement");
+                 self.if_statement()?
+             },
+             Some(C1Token::KwReturn) => {
+                 self.decide("return_statement");
+                 self.return_statement()?
+             },
+             Some(C1Token::KwPrintf) => {
+                 self.decide("printf");
+                 self.printf()?
+             },
              Some(C1Token::Identifier) => {
                  match self.peek_token() {
-                     Some(C1Token::Assign) => self.stat_assignment()?,
-                     Some(C1Token::LeftParenthesis) => self.function_call()?,
-                     _ => Err(self.error_message_current("Invalid statement after identifier, it should be an assignment or a function call!"))?,
+                     Some(C1Token::Assign) => {
+                         self.decide("stat_assignment");
+                         self.stat_assignment()?
+                     },
+                     Some(C1Token::LeftParenthesis) => {
+                         self.decide("function_call");
+                         StatementKind::Call(self.function_call()?)
+                     },
+                     _ => {
+                         self.decide("error");
+                         Err(self.error_message_current(ErrorCode::InvalidIdentifierStatement, "Invalid statement after identifier, it should be an assignment or a function call!"))?
+                     },
                  }
              },
-             _ => Err(self.error_message_current("Invalid statement"))?,
-         }
-         if last_token != Some(C1Token::KwIf) {
+             _ => {
+                 self.decide("error");
+                 Err(self.error_message_current(ErrorCode::InvalidStatement, "Invalid statement"))?
+             },
+         };
+         if !matches!(kind, StatementKind::If { .. }) {
              self.check_and_eat_token(&C1Token::Semicolon, "Expected ';' after statement")?;
          }
-         Ok(())
+         let span = self.span_from(start);
+         self.exit(Rule::Statement, Statement { kind, span })
      }
 
      ///if_statement ::= <KW_IF> "(" assignment ")" block
-     fn if_statement(&mut self) -> ParseResult {
-         let token = self.current_token().ok_or_else(|| self.error_message_current("Expected an if statement"))?;
+     fn if_statement(&mut self) -> RuleResult<StatementKind> {
+         self.enter(Rule::IfStatement);
+         let token = self.current_token().ok_or_else(|| self.error_message_current(ErrorCode::InvalidStatement, "Expected an if statement"))?;
          match token {
              C1Token::KwIf => {
                  self.eat();
-                 self.assignment_in_parenthesis()?;
-                 self.block()?;
-                 Ok(())
+                 let condition = self.assignment_in_parenthesis()?;
+                 let body = Box::new(self.block()?);
+                 self.exit(Rule::IfStatement, StatementKind::If { condition, body })
              }
-             _ => Err(self.error_message_current("Invalid if statement")),
+             _ => Err(self.error_message_current(ErrorCode::InvalidStatement, "Invalid if statement")),
          }
      }
 
      ///return_statement ::= <KW_RETURN> ( assignment )?
-     fn return_statement(&mut self) -> ParseResult {
-         let token = self.current_token().ok_or_else(|| self.error_message_current("Expected a return statement"))?;
+     fn return_statement(&mut self) -> RuleResult<StatementKind> {
+         self.enter(Rule::ReturnStatement);
+         let token = self.current_token().ok_or_else(|| self.error_message_current(ErrorCode::InvalidStatement, "Expected a return statement"))?;
          match token {
              C1Token::KwReturn => {
                  self.eat();
@@ -158,115 +418,142 @@
                  || self.current_matches(&C1Token::ConstBoolean)
                  || self.current_matches(&C1Token::LeftParenthesis)
                  || self.current_matches(&C1Token::Minus) {
-                     self.assignment()?;
+                     self.decide("assignment");
+                     let value = self.assignment()?;
+                     return self.exit(Rule::ReturnStatement, StatementKind::Return(Some(value)));
                  }
-                 Ok(())
+                 self.decide("no value");
+                 self.exit(Rule::ReturnStatement, StatementKind::Return(None))
              }
-             _ => Err(self.error_message_current("Invalid return statement")),
+             _ => Err(self.error_message_current(ErrorCode::InvalidStatement, "Invalid return statement")),
          }
      }
 
      ///printf ::= <KW_PRINTF> "(" assignment ")"
-     fn printf(&mut self) -> ParseResult {
-         let token = self.current_token().ok_or_else(|| self.error_message_current("Expected a printf statement"))?;
+     fn printf(&mut self) -> RuleResult<StatementKind> {
+         self.enter(Rule::Printf);
+         let token = self.current_token().ok_or_else(|| self.error_message_current(ErrorCode::InvalidStatement, "Expected a printf statement"))?;
          match token {
              C1Token::KwPrintf => {
                  self.eat();
-                 self.assignment_in_parenthesis()?;
-                 Ok(())
+                 let value = self.assignment_in_parenthesis()?;
+                 self.exit(Rule::Printf, StatementKind::Printf(value))
              }
-             _ => Err(self.error_message_current("Invalid printf statement")),
+             _ => Err(self.error_message_current(ErrorCode::InvalidStatement, "Invalid printf statement")),
          }
      }
 
      ///stat_assignment ::= <ID> "=" assignment
-     fn stat_assignment(&mut self) -> ParseResult {
-         self.identifier()?;
-         let token = self.current_token().ok_or_else(|| self.error_message_current("Expected a \"=\""))?;
+     fn stat_assignment(&mut self) -> RuleResult<StatementKind> {
+         self.enter(Rule::StatAssignment);
+         let target = self.identifier()?;
+         let token = self.current_token().ok_or_else(|| self.error_message_current(ErrorCode::ExpectedAssign, "Expected a \"=\""))?;
          match token {
              C1Token::Assign => {
                  self.eat();
-                 self.assignment()?;
-                 Ok(())
+                 let value = self.assignment()?;
+                 self.exit(Rule::StatAssignment, StatementKind::Assignment { target, value })
              }
-             _ => Err(self.error_message_current("Invalid stat assignment")),
+             _ => Err(self.error_message_current(ErrorCode::ExpectedAssign, "Invalid stat assignment")),
          }
      }
 
      ///assignment ::= ( ( <ID> "=" assignment ) | expr )
-     fn assignment(&mut self) -> ParseResult {
+     fn assignment(&mut self) -> RuleResult<Expression> {
+         self.enter(Rule::Assignment);
          if self.current_matches(&C1Token::Identifier) && self.next_matches(&C1Token::Assign) {
-            self.identifier()?;
-            let token = self.current_token().ok_or_else(|| self.error_message_current("Expected a \"=\""))?;
+            self.decide("<ID> \"=\" assignment");
+            let start = self.current_start();
+            let target = self.identifier()?;
+            let token = self.current_token().ok_or_else(|| self.error_message_current(ErrorCode::ExpectedAssign, "Expected a \"=\""))?;
             match token {
                 C1Token::Assign => {
                     self.eat();
-                    self.assignment()?;
-                    Ok(())
+                    let value = Box::new(self.assignment()?);
+                    let span = self.span_from(start);
+                    self.exit(Rule::Assignment, Expression { kind: ExpressionKind::Assignment { target, value }, span })
                 }
-                _ => Err(self.error_message_current("Invalid assignment")),
+                _ => Err(self.error_message_current(ErrorCode::ExpectedAssign, "Invalid assignment")),
             }
         }
         else {
-            self.expr()?;
-            Ok(())
+            self.decide("expr");
+            let expression = self.expr()?;
+            self.exit(Rule::Assignment, expression)
         }
      }
 
      ///assignment_in_parenthesis ::= "(" assignment ")"
-     fn assignment_in_parenthesis(&mut self) -> ParseResult {
+     fn assignment_in_parenthesis(&mut self) -> RuleResult<Expression> {
          self.check_and_eat_token(&C1Token::LeftParenthesis, "Expected '(' after statement")?;
-         self.assignment()?;
+         let assignment = self.assignment()?;
          self.check_and_eat_token(&C1Token::RightParenthesis, "Expected ')' after statement")?;
-         Ok(())
+         Ok(assignment)
      }
 
      ///expr ::= simpexpr ( ( "==" | "!=" | "<=" | ">=" | "<" | ">" ) simpexpr )?
-     fn expr(&mut self) -> ParseResult {
-         self.simp_expr()?;
-         let operators: &[C1Token] = &[C1Token::Equal, C1Token::NotEqual, C1Token::LessEqual, C1Token::GreaterEqual, C1Token::Less, C1Token::Greater];
-         if let Some(operator_index) = operators
-             .iter()
-             .position(|&operator| self.current_matches(&operator))
-         {
-             match self.current_token() {
-                 Some(operator) => {
-                     self.check_and_eat_token(&operators[operator_index], &format!("Expected {:?}", operator))?;
-                     self.simp_expr()?;
-                 }
-                 _ => return Err(self.error_message_current("Invalid expression")),
+     fn expr(&mut self) -> RuleResult<Expression> {
+         self.enter(Rule::Expr);
+         let start = self.current_start();
+         let left = self.simp_expr()?;
+         let operators: &[(C1Token, BinaryOperator)] = &[
+             (C1Token::Equal, BinaryOperator::Equal),
+             (C1Token::NotEqual, BinaryOperator::NotEqual),
+             (C1Token::LessEqual, BinaryOperator::LessEqual),
+             (C1Token::GreaterEqual, BinaryOperator::GreaterEqual),
+             (C1Token::Less, BinaryOperator::Less),
+             (C1Token::Greater, BinaryOperator::Greater),
+         ];
+         let expression = match self.eat_operator(operators) {
+             Some(operator) => {
+                 let right = self.simp_expr()?;
+                 self.binary(start, operator, left, right)
              }
-         }
-         Ok(())
+             None => left,
+         };
+         self.exit(Rule::Expr, expression)
      }
 
      ///simp_expr ::= ( "-" )? term ( ( "+" | "-" | "||" ) term )*
-     fn simp_expr(&mut self) -> ParseResult {
-         if self.current_matches(&C1Token::Minus) {
+     fn simp_expr(&mut self) -> RuleResult<Expression> {
+         self.enter(Rule::SimpExpr);
+         let start = self.current_start();
+         let mut expression = if self.current_matches(&C1Token::Minus) {
+             self.decide("\"-\" term");
              self.check_and_eat_token(&C1Token::Minus, "Expected -")?;
+             let operand = Box::new(self.term()?);
+             Expression { kind: ExpressionKind::Negation(operand), span: self.span_from(start) }
+         } else {
+             self.decide("term");
+             self.term()?
+         };
+         let operators: &[(C1Token, BinaryOperator)] = &[
+             (C1Token::Plus, BinaryOperator::Plus),
+             (C1Token::Minus, BinaryOperator::Minus),
+             (C1Token::Or, BinaryOperator::Or),
+         ];
+         while let Some(operator) = self.eat_operator(operators) {
+             let right = self.term()?;
+             expression = self.binary(start, operator, expression, right);
          }
-         self.term()?;
-         let operators: &[C1Token] = &[C1Token::Plus, C1Token::Minus, C1Token::Or];
-         loop {
-             if let Err(_) = self.any_match_and_eat(operators, &"Expected \"+\", \"-\" or \"||\"".to_owned()) {
-                 break;
-             }
-             self.term()?;
-         }
-         Ok(())
+         self.exit(Rule::SimpExpr, expression)
      }
 
      ///term ::= factor ( ( "*" | "/" | "&&" ) factor )*
-     fn term(&mut self) -> ParseResult {
-         self.factor()?;
-         let operators: &[C1Token] = &[C1Token::Asterisk, C1Token::Slash, C1Token::And];
-         loop {
-             if let Err(_) = self.any_match_and_eat(operators, &"Expected \"*\", \"/\" or \"&&\"".to_owned()) {
-                 break;
-             }
-             self.factor()?;
+     fn term(&mut self) -> RuleResult<Expression> {
+         self.enter(Rule::Term);
+         let start = self.current_start();
+         let mut expression = self.factor()?;
+         let operators: &[(C1Token, BinaryOperator)] = &[
+             (C1Token::Asterisk, BinaryOperator::Times),
+             (C1Token::Slash, BinaryOperator::Divide),
+             (C1Token::And, BinaryOperator::And),
+         ];
+         while let Some(operator) = self.eat_operator(operators) {
+             let right = self.factor()?;
+             expression = self.binary(start, operator, expression, right);
          }
-         Ok(())
+         self.exit(Rule::Term, expression)
      }
 
      ///factor ::= <CONST_INT>
@@ -275,44 +562,143 @@
      ///      | functioncall
      ///      | <ID>
      ///      | "(" assignment ")"
-     fn factor(&mut self) -> ParseResult {
-         match self.current_token() {
-             Some(C1Token::ConstInt) => self.check_and_eat_token(&C1Token::ConstInt, "Expected an Integer")?,
-             Some(C1Token::ConstFloat) => self.check_and_eat_token(&C1Token::ConstFloat, "Expected a Float")?,
-             Some(C1Token::ConstBoolean) => self.check_and_eat_token(&C1Token::ConstBoolean, "Expected a Boolean")?,
-             Some(C1Token::LeftParenthesis) => self.assignment_in_parenthesis()?,
+     fn factor(&mut self) -> RuleResult<Expression> {
+         self.enter(Rule::Factor);
+         let start = self.current_start();
+         let alternative = match (self.current_token(), self.peek_token()) {
+             (Some(C1Token::ConstInt), _) => "<CONST_INT>",
+             (Some(C1Token::ConstFloat), _) => "<CONST_FLOAT>",
+             (Some(C1Token::ConstBoolean), _) => "<CONST_BOOLEAN>",
+             (Some(C1Token::LeftParenthesis), _) => "\"(\" assignment \")\"",
+             (Some(C1Token::Identifier), Some(C1Token::LeftParenthesis)) => "function_call",
+             (Some(C1Token::Identifier), _) => "<ID>",
+             _ => "error",
+         };
+         self.decide(alternative);
+         let kind = match self.current_token() {
+             Some(C1Token::ConstInt) => ExpressionKind::Literal(self.literal(LiteralKind::Int)),
+             Some(C1Token::ConstFloat) => ExpressionKind::Literal(self.literal(LiteralKind::Float)),
+             Some(C1Token::ConstBoolean) => ExpressionKind::Literal(self.literal(LiteralKind::Boolean)),
+             Some(C1Token::LeftParenthesis) => {
+                 let expression = self.assignment_in_parenthesis()?;
+                 return self.exit(Rule::Factor, expression);
+             },
              Some(C1Token::Identifier) => {
                  match self.peek_token() {
-                     Some(C1Token::LeftParenthesis) => self.function_call()?,
-                     _ => self.identifier()?,
+                     Some(C1Token::LeftParenthesis) => ExpressionKind::Call(self.function_call()?),
+                     _ => ExpressionKind::Variable(self.identifier()?),
                  }
              },
-             _ => Err(self.error_message_current("Invalid factor"))?,
-         }
-         Ok(())
+             _ => Err(self.error_message_current(ErrorCode::InvalidFactor, "Invalid factor"))?,
+         };
+         let span = self.span_from(start);
+         self.exit(Rule::Factor, Expression { kind, span })
      }
 
-     fn return_type(&mut self) -> ParseResult {
-         let token = self.current_token().ok_or_else(|| self.error_message_current("Expected a return type"))?;
-         match token {
-             C1Token::KwVoid | C1Token::KwBoolean | C1Token::KwInt | C1Token::KwFloat => {
-                 self.eat();
-                 Ok(())
-             }
-             _ => Err(self.error_message_current("Invalid return type")),
-         }
+     ///type ::= <KW_BOOLEAN> | <KW_FLOAT> | <KW_INT> | <KW_VOID>
+     fn return_type(&mut self) -> RuleResult<Type> {
+         self.enter(Rule::Type);
+         let token = self.current_token().ok_or_else(|| self.error_message_current(ErrorCode::InvalidReturnType, "Expected a return type"))?;
+         let return_type = match token {
+             C1Token::KwVoid => Type::Void,
+             C1Token::KwBoolean => Type::Boolean,
+             C1Token::KwInt => Type::Int,
+             C1Token::KwFloat => Type::Float,
+             _ => {
+                 self.decide("error");
+                 return Err(self.error_message_current(ErrorCode::InvalidReturnType, "Invalid return type"));
+             },
+         };
+         self.decide(return_type.to_string());
+         self.eat();
+         self.exit(Rule::Type, return_type)
      }
 
-     fn identifier(&mut self) -> ParseResult {
-         match self.current_token().ok_or_else(|| self.error_message_current("Expected an identifier"))? {
+     fn identifier(&mut self) -> RuleResult<Identifier> {
+         match self.current_token().ok_or_else(|| self.error_message_current(ErrorCode::ExpectedIdentifier, "Expected an identifier"))? {
              C1Token::Identifier => {
+                 let identifier = Identifier {
+                     name: self.current_text().unwrap().to_owned(),
+                     span: self.current_span().unwrap(),
+                 };
                  self.eat();
-                 Ok(())
+                 Ok(identifier)
              }
-             _ => Err(self.error_message_current("Invalid identifier")),
+             _ => Err(self.error_message_current(ErrorCode::ExpectedIdentifier, "Invalid identifier")),
+         }
+     }
+
+     /// Consume the current token. When events are recorded or traced, the token is recorded as well.
+     fn eat(&mut self) {
+         if let (Some(events), Some(token), Some(span)) =
+             (self.events.as_mut(), self.lexer.current_token(), self.lexer.current_span())
+         {
+             events.push(Event::Token { token, span });
+         }
+         if let (Some(trace), Some(token), Some(text), Some(line)) = (
+             self.trace.as_mut(),
+             self.lexer.current_token(),
+             self.lexer.current_text(),
+             self.lexer.current_line_number(),
+         ) {
+             trace.token(token, text, line);
+         }
+         self.lexer.eat();
+     }
+
+     /// Record that the parser starts to apply the given rule
+     fn enter(&mut self, rule: Rule) {
+         if let Some(events) = self.events.as_mut() {
+             events.push(Event::Enter(rule));
+         }
+         if let Some(trace) = self.trace.as_mut() {
+             trace.enter(rule);
+         }
+     }
+
+     /// Record that the given rule has been applied successfully and return the node it produced
+     fn exit<T>(&mut self, rule: Rule, node: T) -> RuleResult<T> {
+         if let Some(events) = self.events.as_mut() {
+             events.push(Event::Exit(rule));
+         }
+         if let Some(trace) = self.trace.as_mut() {
+             trace.exit(rule);
+         }
+         Ok(node)
+     }
+
+     /// When tracing, record which alternative the current rule chooses based on the current and
+     /// the next token
+     fn decide(&mut self, alternative: impl Into<String>) {
+         if let Some(trace) = self.trace.as_mut() {
+             trace.decision(self.lexer.current_token(), self.lexer.peek_token(), alternative);
          }
      }
 
+     /// Consume the current constant token and return it as a literal of the given kind
+     fn literal(&mut self, kind: LiteralKind) -> Literal {
+         let literal = Literal { kind, text: self.current_text().unwrap_or_default().to_owned() };
+         self.eat();
+         literal
+     }
+
+     /// Combine two operands into a binary expression that starts at the given offset
+     fn binary(&self, start: usize, operator: BinaryOperator, left: Expression, right: Expression) -> Expression {
+         let kind = ExpressionKind::Binary { operator, left: Box::new(left), right: Box::new(right) };
+         Expression { kind, span: self.span_from(start) }
+     }
+
+     /// Return the byte offset where the current token starts, or the end of the text after the
+     /// last token
+     fn current_start(&self) -> usize {
+         self.current_span().map_or(self.source().len(), |span| span.start)
+     }
+
+     /// Return the byte range from the given offset up to the end of the last consumed token
+     fn span_from(&self, start: usize) -> Range<usize> {
+         start..self.previous_span().map_or(start, |span| span.end)
+     }
+
      /// Check whether the current token is equal to the given token. If yes, consume it, otherwise
      /// return an error with the given error message
      fn check_and_eat_token(&mut self, token: &C1Token, error_message: &str) -> ParseResult {
@@ -320,24 +706,49 @@
              self.eat();
              Ok(())
          }
+         else if let Some(diagnostic) = self.current_error() {
+             Err(Box::new(diagnostic))
+         }
          else {
-             Err(String::from(error_message))
+             let mut diagnostic = self
+                 .diagnostic_at_current(ErrorCode::expecting(token), error_message)
+                 .expecting(token.description());
+             // Closing tokens are commonly forgotten, so suggest to insert them after the last token
+             if let (C1Token::Semicolon | C1Token::RightParenthesis | C1Token::RightBrace, Some(previous)) =
+                 (token, self.previous_span())
+             {
+                 let position = Position::at(self.source(), previous.end);
+                 diagnostic = diagnostic.with_fix(Fix {
+                     message: format!("insert {}", token.description()),
+                     start: position,
+                     end: position,
+                     replacement: token.description().trim_matches('\'').to_owned(),
+                 });
+                 // When recovering, the parser continues as if the token had been inserted
+                 if let Some(errors) = self.errors.as_mut() {
+                     errors.push(diagnostic);
+                     return Ok(());
+                 }
+             }
+             Err(Box::new(diagnostic))
          }
      }
 
-     /// For each token in the given slice, check whether the token is equal to the current token,
-     /// consume the current token, and check the next token in the slice against the next token
-     /// provided by the lexer.
-     fn check_and_eat_tokens(&mut self, token: &[C1Token], error_message: &str) -> ParseResult {
-         match token
-             .iter()
-             .map(|t| self.check_and_eat_token(t, error_message))
-             .filter(ParseResult::is_err)
-             .last()
-         {
-             None => Ok(()),
-             Some(err) => err,
+     /// Record a syntax error and skip all tokens up to one of the given synchronization tokens.
+     /// At least one token is skipped if the failed rule did not consume any token after `start`.
+     /// Without error recovery, the error is returned instead.
+     fn recover(&mut self, diagnostic: Box<Diagnostic>, start: usize, synchronize: &[C1Token]) -> ParseResult {
+         match self.errors.as_mut() {
+             Some(errors) => errors.push(*diagnostic),
+             None => return Err(diagnostic),
          }
+         if self.current_start() == start && self.current_token().is_some() {
+             self.eat();
+         }
+         while self.current_token().is_some() && !self.any_match_current(synchronize) {
+             self.eat();
+         }
+         Ok(())
      }
 
      /// Check whether the given token matches the current token
@@ -361,39 +772,39 @@
          token.iter().any(|t| self.current_matches(t))
      }
 
-     /// Check whether any of the tokens matches the current token, then consume it
-     fn any_match_and_eat(&mut self, token: &[C1Token], error_message: &String) -> ParseResult {
-         if token
-             .iter()
-             .any(|t| self.check_and_eat_token(t, "").is_ok())
-         {
-             Ok(())
-         } else {
-             Err(String::from(error_message))
-         }
+     /// Check whether any of the operator tokens matches the current token. If yes, consume it and
+     /// return the corresponding operator.
+     fn eat_operator(&mut self, operators: &[(C1Token, BinaryOperator)]) -> Option<BinaryOperator> {
+         let Some((_, operator)) = operators.iter().find(|(token, _)| self.current_matches(token)) else {
+             self.decide("end");
+             return None;
+         };
+         self.decide(format!("\"{}\"", operator.symbol()));
+         self.eat();
+         Some(*operator)
      }
 
-     fn error_message_current(&self, reason: &'static str) -> String {
-         match self.current_token() {
-             None => format!("{}. Reached EOF", reason),
-             Some(_) => format!(
-                 "{} at line {:?} with text: '{}'",
-                 reason,
-                 self.current_line_number().unwrap(),
-                 self.current_text().unwrap()
-             ),
-         }
+     fn error_message_current(&self, code: ErrorCode, reason: &str) -> Box<Diagnostic> {
+         Box::new(self.diagnostic_at_current(code, reason))
      }
 
-     fn error_message_peek(&mut self, reason: &'static str) -> String {
-         match self.peek_token() {
-             None => format!("{}. Reached EOF", reason),
-             Some(_) => format!(
-                 "{} at line {:?} with text: '{}'",
-                 reason,
-                 self.peek_line_number().unwrap(),
-                 self.peek_text().unwrap()
-             ),
+     /// Create an error diagnostic that covers the current token, or the end of the text if all
+     /// tokens have been consumed. If the current token is a lexical error, the lexical error is
+     /// reported instead.
+     fn diagnostic_at_current(&self, code: ErrorCode, reason: &str) -> Diagnostic {
+         if let Some(diagnostic) = self.current_error() {
+             return diagnostic;
+         }
+         match (self.current_span(), self.current_text()) {
+             (Some(span), Some(text)) => {
+                 let start = Position::at(self.source(), span.start);
+                 let end = Position::at(self.source(), span.end);
+                 Diagnostic::error(format!("{}, found '{}'", reason, text), start, end).with_code(code)
+             }
+             _ => {
+                 let end = Position::at(self.source(), self.source().len());
+                 Diagnostic::error(format!("{}. Reached EOF", reason), end, end).with_code(code)
+             }
          }
      }
  }
@@ -0,0 +1,270 @@
+use crate::diagnostic::{Diagnostic, Position};
+use crate::grammar::{Bnf, Grammar, Lookahead, Symbol, Terminal};
+use crate::lexer::C1Lexer;
+use crate::parser::C1Parser;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// # Overview
+/// A table-driven LL(1) recognizer generated from a [`Grammar`]. The parse table maps a
+/// nonterminal and the current token to the productions that can derive it. The recognizer keeps
+/// a stack of expected symbols: terminals on top are matched against the current token, and
+/// nonterminals are replaced by the production the table predicts.
+///
+/// The C(-1) grammar is not LL(1): a statement, an assignment and a factor can all start with an
+/// identifier in more than one way. For such conflicts the recognizer looks at the next token as
+/// well, just like the hand-written parser, and chooses the production whose LL(2) lookahead set
+/// contains both tokens. If that is still ambiguous, the first of these productions is chosen.
+///
+/// The recognizer only accepts or rejects texts. Running it side by side with [`C1Parser`] with
+/// [`Recognizer::compare`] detects where the hand-written parser drifts from the grammar.
+///
+/// # Examples
+/// ```
+/// use cb_3::recognizer::Recognizer;
+///
+/// let recognizer = Recognizer::c1();
+/// assert!(recognizer.recognize("void main() { x = 1 + f(); }").is_ok());
+/// let error = recognizer.recognize("void main() { x = 1 +; }").unwrap_err();
+/// assert_eq!(error.start.column, 22);
+/// ```
+pub struct Recognizer {
+    bnf: Bnf,
+    /// The productions for every nonterminal and current token, in grammar order
+    table: BTreeMap<(usize, Terminal), Vec<usize>>,
+    /// The LL(2) lookahead set of every production
+    lookahead: Vec<BTreeSet<Lookahead>>,
+}
+
+/// A text that only one of the hand-written parser and the recognizer accepts
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Divergence {
+    /// The parser accepts the text, but the recognizer reports the error
+    OnlyParserAccepts(Diagnostic),
+    /// The recognizer accepts the text, but the parser reports the error
+    OnlyRecognizerAccepts(Diagnostic),
+}
+
+impl Recognizer {
+    /// Generate the recognizer for the grammar
+    pub fn new(grammar: &Grammar) -> Recognizer {
+        let bnf = grammar.to_bnf();
+        let mut table: BTreeMap<(usize, Terminal), Vec<usize>> = BTreeMap::new();
+        for (index, lookahead) in bnf.lookahead_sets(1).iter().enumerate() {
+            let nonterminal = bnf.productions[index].nonterminal;
+            for terminal in lookahead.iter().filter_map(|lookahead| lookahead.first()) {
+                table
+                    .entry((nonterminal, *terminal))
+                    .or_default()
+                    .push(index);
+            }
+        }
+        Recognizer {
+            lookahead: bnf.lookahead_sets(2),
+            bnf,
+            table,
+        }
+    }
+
+    /// Generate the recognizer for the grammar of `c-1-syntax.ebnf`
+    pub fn c1() -> Recognizer {
+        Recognizer::new(&Grammar::c1())
+    }
+
+    /// Check whether the text is derived by the grammar. The error covers the first token that
+    /// does not fit, and lists the tokens that would have been accepted instead.
+    pub fn recognize(&self, text: &str) -> Result<(), Box<Diagnostic>> {
+        let mut lexer = C1Lexer::new(text);
+        let mut stack = vec![Symbol::Nonterminal(0)];
+        while let Some(symbol) = stack.pop() {
+            let current = lexer.current_token().map_or(Terminal::Eof, Terminal::Token);
+            match symbol {
+                Symbol::Terminal(terminal) if terminal == current => lexer.eat(),
+                Symbol::Terminal(terminal) => return Err(error(&lexer, false, &[terminal])),
+                Symbol::Nonterminal(nonterminal) => {
+                    let production = self.predict(&lexer, nonterminal)?;
+                    stack.extend(self.bnf.productions[production].symbols.iter().rev());
+                }
+            }
+        }
+        match lexer.current_token() {
+            Some(_) => Err(error(&lexer, false, &[Terminal::Eof])),
+            None => Ok(()),
+        }
+    }
+
+    /// Parse the text with the hand-written parser and with the recognizer, and return the error
+    /// of the one that rejects it if the other one accepts it. The positions of errors are not
+    /// compared: the recognizer may notice an error one token earlier, because it decides between
+    /// conflicting productions with two tokens of lookahead.
+    /// ```
+    /// use cb_3::recognizer::Recognizer;
+    ///
+    /// let recognizer = Recognizer::c1();
+    /// assert_eq!(recognizer.compare("int f() { return f(); }"), None);
+    /// assert_eq!(recognizer.compare("int f() { return f; ; }"), None);
+    /// ```
+    pub fn compare(&self, text: &str) -> Option<Divergence> {
+        match (C1Parser::parse(text), self.recognize(text)) {
+            (Ok(()), Ok(())) => None,
+            (Ok(()), Err(recognizer)) => Some(Divergence::OnlyParserAccepts(*recognizer)),
+            (Err(parser), Ok(())) => Some(Divergence::OnlyRecognizerAccepts(*parser)),
+            (Err(_), Err(_)) => None,
+        }
+    }
+
+    /// Choose the production of the nonterminal for the current token from the parse table.
+    /// Conflicts are resolved with the next token and the LL(2) lookahead sets.
+    fn predict(&self, lexer: &C1Lexer, nonterminal: usize) -> Result<usize, Box<Diagnostic>> {
+        let current = lexer.current_token().map_or(Terminal::Eof, Terminal::Token);
+        let Some(candidates) = self.table.get(&(nonterminal, current)) else {
+            let expected: Vec<Terminal> = self
+                .table
+                .keys()
+                .filter(|(other, _)| *other == nonterminal)
+                .map(|(_, terminal)| *terminal)
+                .collect();
+            return Err(error(lexer, false, &expected));
+        };
+        if let [production] = candidates[..] {
+            return Ok(production);
+        }
+        let peek = lexer.peek_token().map_or(Terminal::Eof, Terminal::Token);
+        // Nothing follows the end of the input
+        let lookahead: &[Terminal] = match current {
+            Terminal::Eof => &[Terminal::Eof],
+            _ => &[current, peek],
+        };
+        if let Some(production) = candidates
+            .iter()
+            .copied()
+            .find(|&production| self.lookahead[production].contains(lookahead))
+        {
+            return Ok(production);
+        }
+        let expected: BTreeSet<Terminal> = candidates
+            .iter()
+            .flat_map(|&production| &self.lookahead[production])
+            .filter_map(|lookahead| lookahead.get(1).copied())
+            .collect();
+        Err(error(lexer, true, &Vec::from_iter(expected)))
+    }
+}
+
+/// Create an error that covers the current token and lists the expected terminals. If
+/// `after_current` is set, the terminals were expected after the current token instead.
+fn error(lexer: &C1Lexer, after_current: bool, expected: &[Terminal]) -> Box<Diagnostic> {
+    let text = lexer.source();
+    let span = lexer.current_span().unwrap_or(text.len()..text.len());
+    let quote = |token: Option<&str>| {
+        token.map_or(String::from("end of file"), |text| format!("'{}'", text))
+    };
+    let found = if after_current {
+        format!(
+            "{} after {}",
+            quote(lexer.peek_text()),
+            quote(lexer.current_text())
+        )
+    } else {
+        quote(lexer.current_text())
+    };
+    let expected_list: Vec<String> = expected.iter().map(Terminal::to_string).collect();
+    let mut diagnostic = Diagnostic::error(
+        format!(
+            "Unexpected {}, expected {}",
+            found,
+            expected_list.join(" or ")
+        ),
+        Position::at(text, span.start),
+        Position::at(text, span.end),
+    );
+    for expected in expected_list {
+        diagnostic = diagnostic.expecting(expected);
+    }
+    Box::new(diagnostic)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::generator::Random;
+    use crate::grammar::{Grammar, C1_GRAMMAR};
+    use crate::lexer::C1Lexer;
+    use crate::recognizer::{Divergence, Recognizer};
+
+    const EXAMPLE: &str = include_str!("../tests/data/beispiel.c-1");
+
+    #[test]
+    fn recognizes_valid_programs() {
+        let recognizer = Recognizer::c1();
+        assert!(recognizer.recognize(EXAMPLE).is_ok());
+        assert!(recognizer.recognize("").is_ok());
+        assert!(recognizer
+            .recognize("bool f() { if (a = b = -c || 1 < 2) { { return; } } g(); }")
+            .is_ok());
+    }
+
+    #[test]
+    fn reports_the_first_unexpected_token() {
+        let recognizer = Recognizer::c1();
+        let error = recognizer.recognize("int f() {\n  x;\n}").unwrap_err();
+        assert_eq!((error.start.line, error.start.column), (2, 3));
+        assert_eq!(
+            error.message,
+            "Unexpected ';' after 'x', expected '=' or '('"
+        );
+        let error = recognizer.recognize("int f() {").unwrap_err();
+        assert!(error
+            .message
+            .starts_with("Unexpected end of file, expected 'if' or "));
+        assert!(error.expected.contains(&String::from("'}'")));
+        let error = recognizer.recognize("int f() {}}").unwrap_err();
+        assert_eq!(
+            error.message,
+            "Unexpected '}', expected 'bool' or 'float' or 'int' or 'void' or end of file"
+        );
+    }
+
+    #[test]
+    fn divergence_from_a_changed_grammar() {
+        let grammar = C1_GRAMMAR.replace("returnstatement \";\"", "returnstatement");
+        let recognizer = Recognizer::new(&Grammar::parse(&grammar).unwrap());
+        let divergence = recognizer.compare("int f() { return 1 }").unwrap();
+        let Divergence::OnlyRecognizerAccepts(error) = divergence else {
+            panic!("{:?}", divergence);
+        };
+        assert_eq!(error.message, "Expected ';' after statement, found '}'");
+        assert!(matches!(
+            recognizer.compare("int f() { return 1; }"),
+            Some(Divergence::OnlyParserAccepts(_))
+        ));
+    }
+
+    #[test]
+    fn agrees_with_the_parser_on_mutated_programs() {
+        let recognizer = Recognizer::c1();
+        let mut lexer = C1Lexer::new(EXAMPLE);
+        let mut tokens = Vec::new();
+        while let Some(span) = lexer.current_span() {
+            tokens.push(&EXAMPLE[span]);
+            lexer.eat();
+        }
+        let mut random = Random::new(37);
+        let mut rejected = 0;
+        for _ in 0..2000 {
+            let mut mutated = tokens.clone();
+            for _ in 0..1 + random.below(3) {
+                let position = random.below(mutated.len());
+                match random.below(3) {
+                    0 => {
+                        mutated.remove(position);
+                    }
+                    1 => mutated.insert(position, tokens[random.below(tokens.len())]),
+                    _ => mutated[position] = tokens[random.below(tokens.len())],
+                }
+            }
+            let text = mutated.join(" ");
+            assert_eq!(recognizer.compare(&text), None, "{}", text);
+            rejected += usize::from(recognizer.recognize(&text).is_err());
+        }
+        assert!(rejected > 1000);
+    }
+}
@@ -0,0 +1,365 @@
+use crate::ast::{Expression, ExpressionKind, Identifier, Program, Statement, StatementKind};
+use crate::checker::{self, Analysis, Symbol};
+use crate::lexer::{C1Lexer, C1Token};
+use crate::parser::C1Parser;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::Range;
+
+/// Replace the text in `range` by `new_text`
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TextEdit {
+    pub range: Range<usize>,
+    pub new_text: String,
+}
+
+/// The edits of a refactoring for every affected file, ordered by their position in the file
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct WorkspaceEdit {
+    pub changes: BTreeMap<String, Vec<TextEdit>>,
+}
+
+impl WorkspaceEdit {
+    /// Apply the edits of the given file to its text
+    pub fn apply(&self, file: &str, text: &str) -> String {
+        let mut result = text.to_owned();
+        for edit in self.changes.get(file).into_iter().flatten().rev() {
+            result.replace_range(edit.range.clone(), &edit.new_text);
+        }
+        result
+    }
+}
+
+/// Reasons why a rename is refused
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum RenameError {
+    /// The file is not part of the workspace
+    UnknownFile(String),
+    /// A file of the workspace cannot be parsed, so not all uses can be found
+    SyntaxError(String),
+    /// There is no function or variable name at the given position
+    NoSymbol,
+    /// The new name is not an identifier
+    InvalidName(String),
+    /// The new name is a keyword or a boolean constant
+    Keyword(String),
+    /// A function with the new name exists already
+    FunctionExists(String),
+    /// A variable with the new name exists in a scope that overlaps with the renamed symbol
+    VariableExists(String),
+}
+
+impl fmt::Display for RenameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenameError::UnknownFile(file) => write!(f, "'{}' is not part of the workspace", file),
+            RenameError::SyntaxError(file) => {
+                write!(
+                    f,
+                    "'{}' contains syntax errors, fix them before renaming",
+                    file
+                )
+            }
+            RenameError::NoSymbol => write!(f, "No function or variable at this position"),
+            RenameError::InvalidName(name) => write!(f, "'{}' is not a valid identifier", name),
+            RenameError::Keyword(name) => write!(f, "'{}' is a keyword", name),
+            RenameError::FunctionExists(name) => {
+                write!(f, "A function named '{}' already exists", name)
+            }
+            RenameError::VariableExists(name) => {
+                write!(
+                    f,
+                    "A variable named '{}' already exists in this scope",
+                    name
+                )
+            }
+        }
+    }
+}
+
+/// # Overview
+/// Rename the function or variable at the given byte offset of `file` to `new_name`. The
+/// workspace consists of `files`, pairs of a file name and its text.
+///
+/// Functions are visible in the whole workspace, so every definition and call of a function with
+/// the same name is renamed in all files. A variable is only visible in its function, so only the
+/// uses in that function are renamed.
+///
+/// The rename is refused if the new name is not an identifier, if it is a keyword, or if it
+/// collides with another symbol whose scope overlaps: a function collides with every function and every
+/// variable, a variable collides with every function and every variable of its function.
+///
+/// # Examples
+/// ```
+/// use cb_3::rename::rename;
+///
+/// let files = [
+///     ("lib.c-1", "int blub() { return 1; }"),
+///     ("main.c-1", "void main() { printf(blub()); }"),
+/// ];
+/// let edit = rename(&files, "main.c-1", 22, "answer").unwrap();
+/// assert_eq!(edit.apply("lib.c-1", files[0].1), "int answer() { return 1; }");
+/// assert_eq!(edit.apply("main.c-1", files[1].1), "void main() { printf(answer()); }");
+/// ```
+pub fn rename(
+    files: &[(&str, &str)],
+    file: &str,
+    offset: usize,
+    new_name: &str,
+) -> Result<WorkspaceEdit, RenameError> {
+    check_name(new_name)?;
+    let mut programs = Vec::new();
+    for (name, text) in files {
+        let program =
+            C1Parser::parse_ast(text).map_err(|_| RenameError::SyntaxError(name.to_string()))?;
+        let analysis = checker::check(text, &program);
+        programs.push((*name, program, analysis));
+    }
+    let (_, program, analysis) = programs
+        .iter()
+        .find(|(name, _, _)| *name == file)
+        .ok_or_else(|| RenameError::UnknownFile(file.to_owned()))?;
+
+    let mut edit = WorkspaceEdit::default();
+    if let Some(function) = function_at(program, offset) {
+        // Functions may be defined in another file, so they are matched by name
+        let old_name = &function.name;
+        for (_, program, analysis) in &programs {
+            // The function does not collide with its own definitions when the name is unchanged
+            if program
+                .functions
+                .iter()
+                .any(|function| function.name.name == new_name && function.name.name != *old_name)
+            {
+                return Err(RenameError::FunctionExists(new_name.to_owned()));
+            }
+            if analysis
+                .variables
+                .iter()
+                .any(|variable| variable.name == new_name)
+            {
+                return Err(RenameError::VariableExists(new_name.to_owned()));
+            }
+        }
+        for (name, program, _) in &programs {
+            let edits: Vec<TextEdit> = function_names(program)
+                .into_iter()
+                .filter(|identifier| &identifier.name == old_name)
+                .map(|identifier| TextEdit {
+                    range: identifier.span.clone(),
+                    new_text: new_name.to_owned(),
+                })
+                .collect();
+            if !edits.is_empty() {
+                edit.changes.insert(name.to_string(), edits);
+            }
+        }
+    } else {
+        let Some(Symbol::Variable(variable)) = analysis.symbol_at(offset) else {
+            return Err(RenameError::NoSymbol);
+        };
+        check_variable_name(&programs, analysis, variable, new_name)?;
+        let edits = analysis
+            .references(Symbol::Variable(variable))
+            .into_iter()
+            .map(|range| TextEdit {
+                range,
+                new_text: new_name.to_owned(),
+            })
+            .collect();
+        edit.changes.insert(file.to_owned(), edits);
+    }
+    Ok(edit)
+}
+
+/// Check that the name is a single identifier token
+fn check_name(name: &str) -> Result<(), RenameError> {
+    let mut lexer = C1Lexer::new(name);
+    let token = lexer.current_token();
+    let length = lexer.current_text().map_or(0, str::len);
+    lexer.eat();
+    if length != name.len() || lexer.current_token().is_some() {
+        return Err(RenameError::InvalidName(name.to_owned()));
+    }
+    match token {
+        Some(C1Token::Identifier) => Ok(()),
+        Some(
+            C1Token::KwBoolean
+            | C1Token::KwDo
+            | C1Token::KwElse
+            | C1Token::KwFloat
+            | C1Token::KwFor
+            | C1Token::KwIf
+            | C1Token::KwInt
+            | C1Token::KwPrintf
+            | C1Token::KwReturn
+            | C1Token::KwVoid
+            | C1Token::KwWhile
+            | C1Token::ConstBoolean,
+        ) => Err(RenameError::Keyword(name.to_owned())),
+        _ => Err(RenameError::InvalidName(name.to_owned())),
+    }
+}
+
+fn check_variable_name(
+    programs: &[(&str, Program, Analysis)],
+    analysis: &Analysis,
+    variable: usize,
+    new_name: &str,
+) -> Result<(), RenameError> {
+    let function = analysis.variables[variable].function;
+    let exists = analysis.variables.iter().enumerate().any(|(index, other)| {
+        index != variable && other.function == function && other.name == new_name
+    });
+    if exists {
+        return Err(RenameError::VariableExists(new_name.to_owned()));
+    }
+    let exists = programs.iter().any(|(_, program, _)| {
+        program
+            .functions
+            .iter()
+            .any(|function| function.name.name == new_name)
+    });
+    if exists {
+        return Err(RenameError::FunctionExists(new_name.to_owned()));
+    }
+    Ok(())
+}
+
+/// Return the function name in a definition or call at the given offset. An offset directly
+/// behind a name also refers to it.
+fn function_at(program: &Program, offset: usize) -> Option<&Identifier> {
+    function_names(program)
+        .into_iter()
+        .find(|identifier| identifier.span.start <= offset && offset <= identifier.span.end)
+}
+
+/// Return the names of all function definitions and calls in text order
+fn function_names(program: &Program) -> Vec<&Identifier> {
+    let mut names = Vec::new();
+    for function in &program.functions {
+        names.push(&function.name);
+        for statement in &function.body {
+            statement_calls(statement, &mut names);
+        }
+    }
+    names
+}
+
+fn statement_calls<'a>(statement: &'a Statement, names: &mut Vec<&'a Identifier>) {
+    match &statement.kind {
+        StatementKind::Block(statements) => {
+            for statement in statements {
+                statement_calls(statement, names);
+            }
+        }
+        StatementKind::If { condition, body } => {
+            expression_calls(condition, names);
+            statement_calls(body, names);
+        }
+        StatementKind::Return(None) => {}
+        StatementKind::Return(Some(value))
+        | StatementKind::Printf(value)
+        | StatementKind::Assignment { value, .. } => expression_calls(value, names),
+        StatementKind::Call(function) => names.push(function),
+    }
+}
+
+fn expression_calls<'a>(expression: &'a Expression, names: &mut Vec<&'a Identifier>) {
+    match &expression.kind {
+        ExpressionKind::Assignment { value, .. } => expression_calls(value, names),
+        ExpressionKind::Binary { left, right, .. } => {
+            expression_calls(left, names);
+            expression_calls(right, names);
+        }
+        ExpressionKind::Negation(operand) => expression_calls(operand, names),
+        ExpressionKind::Call(function) => names.push(function),
+        ExpressionKind::Literal(_) | ExpressionKind::Variable(_) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rename::{rename, RenameError};
+
+    const EXAMPLE: &str = include_str!("../tests/data/beispiel.c-1");
+
+    #[test]
+    fn functions_are_renamed_everywhere() {
+        let other = "int twice() { return blub() + blub(); }";
+        let files = [("beispiel.c-1", EXAMPLE), ("other.c-1", other)];
+        let edit = rename(&files, "beispiel.c-1", 5, "answer").unwrap();
+        assert_eq!(edit.changes["beispiel.c-1"].len(), 6);
+        assert_eq!(
+            edit.apply("other.c-1", other),
+            "int twice() { return answer() + answer(); }"
+        );
+        assert!(!edit.apply("beispiel.c-1", EXAMPLE).contains("blub("));
+    }
+
+    #[test]
+    fn variables_are_renamed_in_their_function() {
+        let text = "void f() { a = 1; a = a + 1; } void g() { a = 2; }";
+        let edit = rename(&[("f.c-1", text)], "f.c-1", 18, "b").unwrap();
+        assert_eq!(
+            edit.apply("f.c-1", text),
+            "void f() { b = 1; b = b + 1; } void g() { a = 2; }"
+        );
+    }
+
+    #[test]
+    fn collisions_are_refused() {
+        let files = [("beispiel.c-1", EXAMPLE)];
+        let rename = |offset, name| rename(&files, "beispiel.c-1", offset, name);
+        // blub
+        assert_eq!(
+            rename(5, "blah"),
+            Err(RenameError::FunctionExists("blah".into()))
+        );
+        assert_eq!(
+            rename(5, "blub1"),
+            Err(RenameError::VariableExists("blub1".into()))
+        );
+        assert_eq!(
+            rename(5, "while"),
+            Err(RenameError::Keyword("while".into()))
+        );
+        assert_eq!(rename(5, "true"), Err(RenameError::Keyword("true".into())));
+        assert_eq!(rename(5, "2x"), Err(RenameError::InvalidName("2x".into())));
+        assert_eq!(
+            rename(5, "a b"),
+            Err(RenameError::InvalidName("a b".into()))
+        );
+        // blub1 in the first assignment
+        assert_eq!(
+            rename(14, "blub2"),
+            Err(RenameError::VariableExists("blub2".into()))
+        );
+        assert_eq!(
+            rename(14, "main"),
+            Err(RenameError::FunctionExists("main".into()))
+        );
+        // a is defined in blah as well, but that scope does not overlap
+        assert!(rename(14, "a").is_ok());
+        assert_eq!(rename(12, "x"), Err(RenameError::NoSymbol));
+    }
+
+    #[test]
+    fn unchanged_names_are_no_op() {
+        let files = [("beispiel.c-1", EXAMPLE)];
+        for (offset, name) in [(5, "blub"), (14, "blub1")] {
+            let edit = rename(&files, "beispiel.c-1", offset, name).unwrap();
+            assert!(!edit.changes["beispiel.c-1"].is_empty());
+            assert_eq!(edit.apply("beispiel.c-1", EXAMPLE), EXAMPLE);
+        }
+    }
+
+    #[test]
+    fn files_must_be_valid() {
+        let files = [("a.c-1", "void f() {}"), ("b.c-1", "void g() {")];
+        let result = rename(&files, "a.c-1", 5, "h");
+        assert_eq!(result, Err(RenameError::SyntaxError("b.c-1".into())));
+        let result = rename(&files[..1], "b.c-1", 5, "h");
+        assert_eq!(result, Err(RenameError::UnknownFile("b.c-1".into())));
+    }
+}
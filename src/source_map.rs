@@ -0,0 +1,291 @@
+use crate::diagnostic::{self, Diagnostic, Position};
+use serde::Serialize;
+use std::fmt;
+use std::ops::Range;
+
+/// Identifies a source file in a [`SourceMap`]
+#[derive(Serialize, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone)]
+pub struct FileId(usize);
+
+/// A source text together with its name and its place in a [`SourceMap`]
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    name: String,
+    text: String,
+    /// Global byte offset of the first byte of the text
+    start: usize,
+    /// Byte offsets in the text where a line starts
+    line_starts: Vec<usize>,
+}
+
+impl SourceFile {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Return the range of global byte offsets that the text occupies. The end of the text is a
+    /// valid offset as well, e.g. for an error at the end of the file.
+    pub fn span(&self) -> Range<usize> {
+        self.start..self.start + self.text.len()
+    }
+
+    /// Compute the line and column of a byte offset in the text. An offset inside of a character
+    /// refers to the character, like in [`Position::at`].
+    pub fn position(&self, offset: usize) -> Position {
+        let offset = diagnostic::char_boundary(&self.text, offset);
+        let line = self.line_starts.partition_point(|start| *start <= offset);
+        let line_start = self.line_starts[line - 1];
+        Position {
+            line,
+            column: self.text[line_start..offset].chars().count() + 1,
+        }
+    }
+}
+
+/// The file, line and column of a global byte offset
+#[derive(Serialize, Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Location {
+    pub file: FileId,
+    pub position: Position,
+}
+
+/// # Overview
+/// The source texts of a program that spans several files. Every file is assigned a [`FileId`]
+/// and a range of global byte offsets, so a single offset identifies a place in any of the files.
+/// The files are laid out one after another with a gap of one byte, which keeps the end of a file
+/// apart from the start of the next one.
+///
+/// Lexers and parsers work on the text of one file, see [`C1Lexer::for_file`] and
+/// [`C1Parser::parse_file`]. Their byte offsets, lines and columns are local to the file, which
+/// they carry along: tokens in [`Token::file`], syntax trees in [`Program::file`] and diagnostics
+/// in [`Diagnostic::file_id`]. Local spans are converted with [`SourceMap::global_span`] and
+/// [`SourceMap::span_of`], global offsets are resolved with [`SourceMap::lookup`].
+///
+/// [`C1Lexer::for_file`]: crate::C1Lexer::for_file
+/// [`C1Parser::parse_file`]: crate::parser::C1Parser::parse_file
+/// [`Token::file`]: crate::Token::file
+/// [`Program::file`]: crate::ast::Program::file
+///
+/// # Examples
+/// ```
+/// use cb_3::diagnostic::Position;
+/// use cb_3::source_map::SourceMap;
+///
+/// let mut sources = SourceMap::new();
+/// let lib = sources.add("lib.c-1", "int one() {\n    return 1;\n}\n");
+/// let main = sources.add("main.c-1", "void main() {\n    printf(one());\n}\n");
+///
+/// let offset = sources.global(main, 25);
+/// let location = sources.lookup(offset).unwrap();
+/// assert_eq!(location.file, main);
+/// assert_eq!(location.position, Position { line: 2, column: 12 });
+/// assert_eq!(sources.describe(offset), "main.c-1:2:12");
+/// assert_eq!(sources.file(lib).name(), "lib.c-1");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    pub fn new() -> SourceMap {
+        SourceMap::default()
+    }
+
+    /// Add a source text with the given name and return its identifier
+    pub fn add(&mut self, name: impl Into<String>, text: impl Into<String>) -> FileId {
+        let text = text.into();
+        let start = self.files.last().map_or(0, |file| file.span().end + 1);
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(index, _)| index + 1))
+            .collect();
+        self.files.push(SourceFile {
+            name: name.into(),
+            text,
+            start,
+            line_starts,
+        });
+        FileId(self.files.len() - 1)
+    }
+
+    /// Return the file with the given identifier. Panics if the identifier belongs to another
+    /// source map.
+    pub fn file(&self, file: FileId) -> &SourceFile {
+        &self.files[file.0]
+    }
+
+    /// Return the identifiers and files in the order they were added
+    pub fn files(&self) -> impl Iterator<Item = (FileId, &SourceFile)> {
+        self.files
+            .iter()
+            .enumerate()
+            .map(|(index, file)| (FileId(index), file))
+    }
+
+    /// Return the file with the given name
+    pub fn find(&self, name: &str) -> Option<FileId> {
+        self.files()
+            .find(|(_, file)| file.name == name)
+            .map(|(id, _)| id)
+    }
+
+    /// Convert a byte offset in the text of a file to a global byte offset
+    pub fn global(&self, file: FileId, offset: usize) -> usize {
+        self.file(file).start + offset
+    }
+
+    /// Convert a byte range in the text of a file, e.g. the span of a token or a syntax tree node,
+    /// to global byte offsets
+    pub fn global_span(&self, file: FileId, span: Range<usize>) -> Range<usize> {
+        self.global(file, span.start)..self.global(file, span.end)
+    }
+
+    /// Return the global byte range that a diagnostic covers, or `None` if it does not belong to
+    /// a file of the source map
+    pub fn span_of(&self, diagnostic: &Diagnostic) -> Option<Range<usize>> {
+        let file = diagnostic.file_id?;
+        let text = self.files.get(file.0)?.text();
+        let span = diagnostic.start.offset(text)..diagnostic.end.offset(text);
+        Some(self.global_span(file, span))
+    }
+
+    /// Return the file that contains the global byte offset together with the offset in its text,
+    /// or `None` if the offset lies in no file
+    pub fn resolve(&self, offset: usize) -> Option<(FileId, usize)> {
+        let index = self
+            .files
+            .partition_point(|file| file.start <= offset)
+            .checked_sub(1)?;
+        let file = &self.files[index];
+        (offset <= file.span().end).then(|| (FileId(index), offset - file.start))
+    }
+
+    /// Compute the file, line and column of a global byte offset
+    pub fn lookup(&self, offset: usize) -> Option<Location> {
+        let (file, local) = self.resolve(offset)?;
+        Some(Location {
+            file,
+            position: self.file(file).position(local),
+        })
+    }
+
+    /// Describe a global byte offset as `name:line:column`, like the locations of diagnostics
+    pub fn describe(&self, offset: usize) -> String {
+        match self.lookup(offset) {
+            Some(location) => self.display(location).to_string(),
+            None => format!("<unknown offset {}>", offset),
+        }
+    }
+
+    /// Return a value that displays the location as `name:line:column`
+    pub fn display(&self, location: Location) -> impl fmt::Display + '_ {
+        DisplayLocation {
+            name: self.file(location.file).name(),
+            position: location.position,
+        }
+    }
+}
+
+struct DisplayLocation<'a> {
+    name: &'a str,
+    position: Position,
+}
+
+impl fmt::Display for DisplayLocation<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}",
+            self.name, self.position.line, self.position.column
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::checker;
+    use crate::diagnostic::{Diagnostic, Position};
+    use crate::parser::C1Parser;
+    use crate::source_map::SourceMap;
+    use crate::{C1Lexer, C1Token};
+
+    #[test]
+    fn offsets_are_resolved() {
+        let mut sources = SourceMap::new();
+        let a = sources.add("a", "x\ny");
+        let empty = sources.add("empty", "");
+        let b = sources.add("b", "äz");
+
+        assert_eq!(sources.file(a).span(), 0..3);
+        assert_eq!(sources.file(empty).span(), 4..4);
+        assert_eq!(sources.file(b).span(), 5..8);
+        assert_eq!(sources.resolve(0), Some((a, 0)));
+        // The end of a file belongs to the file, the next file starts after the gap
+        assert_eq!(sources.resolve(3), Some((a, 3)));
+        assert_eq!(sources.resolve(4), Some((empty, 0)));
+        assert_eq!(sources.resolve(5), Some((b, 0)));
+        assert_eq!(sources.resolve(9), None);
+        assert_eq!(sources.describe(9), "<unknown offset 9>");
+
+        let position = |offset| sources.lookup(offset).map(|location| location.position);
+        assert_eq!(position(2), Some(Position { line: 2, column: 1 }));
+        assert_eq!(position(3), Some(Position { line: 2, column: 2 }));
+        assert_eq!(position(7), Some(Position { line: 1, column: 2 }));
+        // An offset inside of 'ä' refers to it
+        assert_eq!(position(6), Some(Position { line: 1, column: 1 }));
+        assert_eq!(sources.describe(8), "b:1:3");
+        assert_eq!(sources.find("empty"), Some(empty));
+    }
+
+    #[test]
+    fn positions_agree_with_diagnostics() {
+        let text = "int f() {\n\treturn 1;\n}\n\n";
+        let mut sources = SourceMap::new();
+        let file = sources.add("f.c-1", text);
+        for offset in 0..=text.len() {
+            assert_eq!(
+                sources.file(file).position(offset),
+                Position::at(text, offset)
+            );
+        }
+    }
+
+    #[test]
+    fn locations_refer_to_files() {
+        let mut sources = SourceMap::new();
+        sources.add("a.c-1", "void a() {}");
+        let b = sources.add("b.c-1", "int b() {\n    return x $;\n}");
+
+        let program = C1Parser::parse_file(&sources, sources.find("a.c-1").unwrap()).unwrap();
+        assert_eq!(program.file, sources.find("a.c-1"));
+
+        let mut lexer = C1Lexer::for_file(&sources, b);
+        while lexer.current_token() != Some(C1Token::Error) {
+            lexer.eat();
+        }
+        let error = lexer.current_error().unwrap();
+        assert_eq!(error.file_id, Some(b));
+        assert_eq!(
+            error.to_string(),
+            "b.c-1:2:14: error[E0016]: Unexpected character '$'"
+        );
+        assert_eq!(
+            sources.describe(sources.span_of(&error).unwrap().start),
+            "b.c-1:2:14"
+        );
+
+        let diagnostics = checker::diagnostics_in_file(&sources, b);
+        assert!(diagnostics
+            .iter()
+            .all(|diagnostic| diagnostic.file_id == Some(b)));
+        let position = Position { line: 1, column: 1 };
+        assert_eq!(
+            sources.span_of(&Diagnostic::error("", position, position)),
+            None
+        );
+    }
+}
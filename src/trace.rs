@@ -0,0 +1,272 @@
+use crate::lexer::C1Token;
+use crate::parser::Rule;
+use serde::Serialize;
+use std::fmt;
+
+/// How the application of a grammar rule ended
+#[derive(Serialize, Debug, PartialEq, Eq, Clone)]
+#[serde(tag = "result", rename_all = "snake_case")]
+pub enum Outcome {
+    Success,
+    /// The rule was aborted by the syntax error with the given message
+    Failure {
+        message: String,
+    },
+}
+
+/// A step of a traced parse
+#[derive(Serialize, Debug, PartialEq, Eq, Clone)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum TraceEvent {
+    /// The parser starts to apply the rule
+    Enter { rule: Rule },
+    /// The rule chose between alternatives based on the current and the next token
+    Decision {
+        rule: Rule,
+        current: Option<C1Token>,
+        peek: Option<C1Token>,
+        alternative: String,
+    },
+    /// The parser consumed a token
+    Token {
+        token: C1Token,
+        text: String,
+        line: usize,
+    },
+    /// The application of the rule ended
+    Exit { rule: Rule, outcome: Outcome },
+}
+
+/// # Overview
+/// The record of a parse by [`C1Parser::parse_traced`]: every entry and exit of a grammar rule,
+/// every decision between alternatives with the tokens it was based on, and every consumed token.
+/// The events are well nested, after a syntax error all rules that were active are exited with a
+/// failure.
+///
+/// The trace is displayed as an indented tree, and can be exported as JSON Lines.
+///
+/// [`C1Parser::parse_traced`]: crate::parser::C1Parser::parse_traced
+///
+/// # Examples
+/// ```
+/// use cb_3::parser::C1Parser;
+///
+/// let (_, trace) = C1Parser::parse_traced("void f() { g(); }");
+/// let tree = trace.to_string();
+/// assert!(tree.contains(
+///     "        statement\n          Identifier LeftParenthesis => function_call\n"
+/// ));
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Trace {
+    pub events: Vec<TraceEvent>,
+    /// The rules that have been entered but not exited yet
+    open: Vec<Rule>,
+}
+
+impl Trace {
+    pub(crate) fn enter(&mut self, rule: Rule) {
+        self.open.push(rule);
+        self.events.push(TraceEvent::Enter { rule });
+    }
+
+    /// Record a decision of the innermost active rule
+    pub(crate) fn decision(
+        &mut self,
+        current: Option<C1Token>,
+        peek: Option<C1Token>,
+        alternative: impl Into<String>,
+    ) {
+        if let Some(&rule) = self.open.last() {
+            self.events.push(TraceEvent::Decision {
+                rule,
+                current,
+                peek,
+                alternative: alternative.into(),
+            });
+        }
+    }
+
+    pub(crate) fn token(&mut self, token: C1Token, text: &str, line: usize) {
+        self.events.push(TraceEvent::Token {
+            token,
+            text: text.to_owned(),
+            line,
+        });
+    }
+
+    pub(crate) fn exit(&mut self, rule: Rule) {
+        self.open.pop();
+        self.events.push(TraceEvent::Exit {
+            rule,
+            outcome: Outcome::Success,
+        });
+    }
+
+    /// Exit all active rules because of the syntax error with the given message
+    pub(crate) fn fail(&mut self, message: &str) {
+        while let Some(rule) = self.open.pop() {
+            self.events.push(TraceEvent::Exit {
+                rule,
+                outcome: Outcome::Failure {
+                    message: message.to_owned(),
+                },
+            });
+        }
+    }
+
+    /// Serialize the events as JSON Lines, i.e. one JSON object per line
+    /// ```
+    /// use cb_3::parser::C1Parser;
+    ///
+    /// let (_, trace) = C1Parser::parse_traced("int");
+    /// let json = trace.to_json_lines();
+    /// assert!(json.starts_with("{\"event\":\"enter\",\"rule\":\"program\"}\n"));
+    /// assert!(json.contains(
+    ///     "{\"event\":\"decision\",\"rule\":\"type\",\"current\":\"KwInt\",\"peek\":null,\"alternative\":\"int\"}\n"
+    /// ));
+    /// ```
+    pub fn to_json_lines(&self) -> String {
+        self.events
+            .iter()
+            .map(|event| {
+                serde_json::to_string(event).expect("events are always serializable") + "\n"
+            })
+            .collect()
+    }
+}
+
+/// The trace is displayed as a tree with two spaces of indentation per active rule
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let token = |token: &Option<C1Token>| match token {
+            Some(token) => format!("{:?}", token),
+            None => String::from("EOF"),
+        };
+        let mut depth = 0;
+        for event in &self.events {
+            if let TraceEvent::Exit { .. } = event {
+                depth -= 1;
+            }
+            write!(f, "{}", "  ".repeat(depth))?;
+            match event {
+                TraceEvent::Enter { rule } => {
+                    writeln!(f, "{}", rule.name())?;
+                    depth += 1;
+                }
+                TraceEvent::Decision {
+                    current,
+                    peek,
+                    alternative,
+                    ..
+                } => writeln!(f, "{} {} => {}", token(current), token(peek), alternative)?,
+                TraceEvent::Token { token, text, line } => {
+                    writeln!(f, "eat {:?} {:?} (line {})", token, text, line)?
+                }
+                TraceEvent::Exit {
+                    rule,
+                    outcome: Outcome::Success,
+                } => writeln!(f, "{}: ok", rule.name())?,
+                TraceEvent::Exit {
+                    rule,
+                    outcome: Outcome::Failure { message },
+                } => writeln!(f, "{}: failed: {}", rule.name(), message)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lexer::C1Token;
+    use crate::parser::{C1Parser, Rule};
+    use crate::trace::{Outcome, TraceEvent};
+
+    #[test]
+    fn indented_tree() {
+        let (result, trace) = C1Parser::parse_traced("int f() {\n  return -x;\n}");
+        assert!(result.is_ok());
+        let tree = trace.to_string();
+        let expected = "\
+program
+  KwInt Identifier => function_definition
+  function_definition
+    type
+      KwInt Identifier => int
+      eat KwInt \"int\" (line 1)
+    type: ok
+    eat Identifier \"f\" (line 1)
+    eat LeftParenthesis \"(\" (line 1)
+    eat RightParenthesis \")\" (line 1)
+    eat LeftBrace \"{\" (line 1)
+    statement_list
+      KwReturn Minus => block
+      block
+        KwReturn Minus => statement
+        statement
+          KwReturn Minus => return_statement
+          return_statement
+            eat KwReturn \"return\" (line 2)
+            Minus Identifier => assignment
+            assignment
+              Minus Identifier => expr
+              expr
+                simp_expr
+                  Minus Identifier => \"-\" term
+                  eat Minus \"-\" (line 2)
+                  term
+                    factor
+                      Identifier Semicolon => <ID>
+                      eat Identifier \"x\" (line 2)
+                    factor: ok
+                    Semicolon RightBrace => end
+                  term: ok
+                  Semicolon RightBrace => end
+                simp_expr: ok
+                Semicolon RightBrace => end
+              expr: ok
+            assignment: ok
+          return_statement: ok
+          eat Semicolon \";\" (line 2)
+        statement: ok
+      block: ok
+      RightBrace EOF => end
+    statement_list: ok
+    eat RightBrace \"}\" (line 3)
+  function_definition: ok
+  EOF EOF => end
+program: ok
+";
+        assert_eq!(tree, expected);
+    }
+
+    #[test]
+    fn syntax_errors_exit_all_rules() {
+        let (result, trace) = C1Parser::parse_traced("void f() { x = ; }");
+        let message = result.unwrap_err().message;
+        assert!(trace.events.contains(&TraceEvent::Decision {
+            rule: Rule::Factor,
+            current: Some(C1Token::Semicolon),
+            peek: Some(C1Token::RightBrace),
+            alternative: String::from("error"),
+        }));
+        let failures: Vec<Rule> = trace
+            .events
+            .iter()
+            .filter_map(|event| match event {
+                TraceEvent::Exit {
+                    rule,
+                    outcome: Outcome::Failure { message: failure },
+                } => {
+                    assert_eq!(failure, &message);
+                    Some(*rule)
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(failures.first(), Some(&Rule::Factor));
+        assert_eq!(failures.last(), Some(&Rule::Program));
+        assert_eq!(failures.len(), 11);
+    }
+}
@@ -0,0 +1,22 @@
+mod common;
+
+use cb_3::checker;
+use cb_3::differential;
+use std::fs;
+
+#[test]
+fn backends_agree_on_all_programs() {
+    let backends = differential::backends();
+    let paths = common::programs();
+    assert!(!paths.is_empty());
+    for path in paths {
+        let text = fs::read_to_string(&path).unwrap();
+        // Invalid programs are covered by the golden files
+        if !checker::diagnostics(&text).is_empty() {
+            continue;
+        }
+        if let Err(discrepancy) = differential::compare(&text, &backends) {
+            panic!("{}: {}", path.display(), discrepancy);
+        }
+    }
+}
@@ -0,0 +1,21 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Return the C(-1) programs in `tests/data` and its subdirectories, in a stable order
+pub fn programs() -> Vec<PathBuf> {
+    let mut programs = Vec::new();
+    collect(Path::new("tests/data"), &mut programs);
+    programs.sort();
+    programs
+}
+
+fn collect(directory: &Path, programs: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(directory).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            collect(&path, programs);
+        } else if path.extension().is_some_and(|extension| extension == "c-1") {
+            programs.push(path);
+        }
+    }
+}
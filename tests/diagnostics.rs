@@ -0,0 +1,91 @@
+use cb_3::checker;
+use cb_3::diagnostic::{to_json, Position};
+use cb_3::error_code::ErrorCode;
+use cb_3::parser::C1Parser;
+
+#[test]
+fn missing_semicolon_is_located() {
+    let diagnostics = C1Parser::diagnostics("void main() {\n\tx = 1\n\ty = 2;\n}");
+    assert_eq!(diagnostics.len(), 1);
+    let diagnostic = &diagnostics[0];
+    assert_eq!(diagnostic.start, Position { line: 3, column: 2 });
+    assert_eq!(diagnostic.end, Position { line: 3, column: 3 });
+    assert_eq!(diagnostic.expected, vec!["';'"]);
+    assert_eq!(diagnostic.fixes.len(), 1);
+    assert_eq!(diagnostic.fixes[0].start, Position { line: 2, column: 7 });
+    assert_eq!(diagnostic.fixes[0].replacement, ";");
+}
+
+#[test]
+fn error_at_end_of_file() {
+    let diagnostics = C1Parser::diagnostics("int foo() {\n");
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].start, Position { line: 2, column: 1 });
+    assert!(diagnostics[0].message.ends_with("Reached EOF"));
+}
+
+#[test]
+fn valid_program_has_no_diagnostics() {
+    let diagnostics = C1Parser::diagnostics("void main() { printf(1); }");
+    assert!(diagnostics.is_empty());
+    assert_eq!(to_json(&diagnostics), "[]");
+}
+
+#[test]
+fn identifier_statements_need_assign_or_call() {
+    for statement in ["x + 1;", "x 1;", "x;"] {
+        let text = format!("void main() {{\n\t{}\n}}", statement);
+        let error = C1Parser::parse(&text).unwrap_err();
+        assert_eq!(error.code, Some(ErrorCode::InvalidIdentifierStatement));
+        assert_eq!(error.start, Position { line: 2, column: 2 });
+    }
+}
+
+#[test]
+fn lexical_errors_are_reported_precisely() {
+    let error = C1Parser::parse("void main() {\n\tx = 1 $ 2;\n}").unwrap_err();
+    assert_eq!(error.code, Some(ErrorCode::UnexpectedCharacter));
+    assert_eq!(error.message, "Unexpected character '$'");
+    assert_eq!(error.start, Position { line: 2, column: 8 });
+
+    let diagnostics = checker::diagnostics("void main() {\n\tx = 1e;\n\tprintf(x) `;\n}");
+    let codes: Vec<Option<ErrorCode>> = diagnostics.iter().map(|d| d.code).collect();
+    assert_eq!(
+        codes,
+        vec![
+            Some(ErrorCode::MalformedFloat),
+            Some(ErrorCode::UnexpectedCharacter)
+        ]
+    );
+}
+
+/// Extract the indented code block that follows the given heading in an explanation
+fn example<'a>(explanation: &'a str, heading: &str) -> String {
+    explanation
+        .split(heading)
+        .nth(1)
+        .unwrap()
+        .lines()
+        .skip(1)
+        .take_while(|line| line.is_empty() || line.starts_with("    "))
+        .collect::<Vec<&'a str>>()
+        .join("\n")
+}
+
+#[test]
+fn explained_examples_match_their_code() {
+    for error_code in ErrorCode::ALL {
+        // A missing '=' is reported as InvalidIdentifierStatement, see its explanation
+        if error_code == ErrorCode::ExpectedAssign {
+            continue;
+        }
+        let erroneous = example(error_code.explanation(), "Erroneous example:");
+        let diagnostics = checker::diagnostics(&erroneous);
+        assert_eq!(diagnostics.len(), 1, "{}", erroneous);
+        assert_eq!(diagnostics[0].code, Some(error_code), "{}", diagnostics[0]);
+
+        let corrected = example(error_code.explanation(), "Corrected example:");
+        let diagnostics = checker::diagnostics(&corrected);
+        assert!(diagnostics.is_empty(), "{}: {:?}", error_code, diagnostics);
+    }
+}
@@ -0,0 +1,108 @@
+//! Golden-file tests: every program `tests/data/**/*.c-1` is lexed, parsed, checked and run, and
+//! the results are compared with the files next to it:
+//!
+//! - `.tokens`: every token with its line, column and text,
+//! - `.ast`: the dump of the syntax tree, if the program can be parsed,
+//! - `.diagnostics`: the syntax or semantic errors, if there are any,
+//! - `.stdout`: the output of the program, if an execution backend is available.
+//!
+//! To add a case, add the program and run `BLESS=1 cargo test --test golden`, which writes the
+//! expected files from the current results. Review them before committing.
+
+mod common;
+
+use cb_3::checker;
+use cb_3::differential;
+use cb_3::parser::C1Parser;
+use cb_3::tokenize;
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+fn tokens(text: &str) -> String {
+    let mut output = String::new();
+    for token in tokenize(text) {
+        let (line, column) = (token.line, token.column);
+        writeln!(
+            output,
+            "{}:{} {:?} {:?}",
+            line, column, token.kind, token.text
+        )
+        .unwrap();
+    }
+    output
+}
+
+fn diagnostics(text: &str) -> Option<String> {
+    let diagnostics = checker::diagnostics(text);
+    (!diagnostics.is_empty()).then(|| {
+        diagnostics
+            .iter()
+            .map(|diagnostic| format!("{}\n", diagnostic))
+            .collect()
+    })
+}
+
+/// Run the program with the first available backend. Programs with errors are not run.
+fn stdout(text: &str) -> Option<String> {
+    if !checker::diagnostics(text).is_empty() {
+        return None;
+    }
+    let backend = differential::backends().into_iter().next()?;
+    Some(match backend.run(text) {
+        Ok(execution) => execution.stdout,
+        Err(error) => format!("{} failed: {}\n", backend.name(), error),
+    })
+}
+
+/// Compare the actual output with the expected file, or update the file in bless mode. `None`
+/// means that there must not be an expected file. Returns a description of the mismatch.
+fn check(path: &Path, extension: &str, actual: Option<String>, bless: bool) -> Option<String> {
+    let expected_path = path.with_extension(extension);
+    let expected = fs::read_to_string(&expected_path).ok();
+    if expected == actual {
+        return None;
+    }
+    if bless {
+        match actual {
+            Some(actual) => fs::write(&expected_path, actual).unwrap(),
+            None => fs::remove_file(&expected_path).unwrap(),
+        }
+        return None;
+    }
+    Some(match (expected, actual) {
+        (None, _) => format!("{} is missing", expected_path.display()),
+        (_, None) => format!("{} is not expected", expected_path.display()),
+        (Some(expected), Some(actual)) => format!(
+            "{} differs\n--- expected\n{}--- actual\n{}",
+            expected_path.display(),
+            expected,
+            actual
+        ),
+    })
+}
+
+#[test]
+fn golden_files() {
+    let bless = env::var_os("BLESS").is_some();
+    let mut failures = Vec::new();
+    for path in common::programs() {
+        let text = fs::read_to_string(&path).unwrap();
+        let ast = C1Parser::parse_ast(&text)
+            .ok()
+            .map(|program| program.dump());
+        failures.extend(check(&path, "tokens", Some(tokens(&text)), bless));
+        failures.extend(check(&path, "ast", ast, bless));
+        failures.extend(check(&path, "diagnostics", diagnostics(&text), bless));
+        // Without a backend the expected output cannot be checked
+        if !differential::backends().is_empty() {
+            failures.extend(check(&path, "stdout", stdout(&text), bless));
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "{}\n\nRun `BLESS=1 cargo test --test golden` to update the expected files",
+        failures.join("\n")
+    );
+}
@@ -0,0 +1,204 @@
+use cb_3::lsp::{read_message, write_message, Server};
+use serde_json::{json, Value};
+use std::io::BufReader;
+
+const URI: &str = "file:///beispiel.c-1";
+
+/// Run the server on the given messages and return everything it sent back
+fn run(messages: &[Value]) -> Vec<Value> {
+    let mut input = Vec::new();
+    for message in messages {
+        write_message(&mut input, message).unwrap();
+    }
+    let mut output = Vec::new();
+    Server::new().run(input.as_slice(), &mut output).unwrap();
+
+    let mut reader = BufReader::new(output.as_slice());
+    let mut responses = Vec::new();
+    while let Some(message) = read_message(&mut reader).unwrap() {
+        responses.push(message.unwrap());
+    }
+    responses
+}
+
+fn open(text: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/didOpen",
+        "params": { "textDocument": { "uri": URI, "languageId": "c-1", "version": 1, "text": text } }
+    })
+}
+
+fn request(id: u64, method: &str, line: u64, character: u64) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "method": method,
+        "params": {
+            "textDocument": { "uri": URI },
+            "position": { "line": line, "character": character }
+        }
+    })
+}
+
+fn result(responses: &[Value], id: u64) -> &Value {
+    &responses
+        .iter()
+        .find(|response| response["id"] == json!(id))
+        .unwrap()["result"]
+}
+
+#[test]
+fn session() {
+    let text = include_str!("data/beispiel.c-1");
+    let responses = run(&[
+        json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}}),
+        json!({"jsonrpc": "2.0", "method": "initialized", "params": {}}),
+        open(text),
+        // blub in `printf(blub());` of main
+        request(2, "textDocument/definition", 27, 9),
+        // blub4 in `if (blub1 < blub4)`
+        request(3, "textDocument/hover", 5, 15),
+        // the float constant in blah
+        request(4, "textDocument/hover", 17, 11),
+        json!({"jsonrpc": "2.0", "id": 5, "method": "textDocument/documentSymbol", "params": {"textDocument": {"uri": URI}}}),
+        json!({"jsonrpc": "2.0", "id": 6, "method": "shutdown"}),
+        json!({"jsonrpc": "2.0", "method": "exit"}),
+        json!({"jsonrpc": "2.0", "id": 7, "method": "shutdown"}),
+    ]);
+
+    assert_eq!(
+        result(&responses, 1)["capabilities"]["hoverProvider"],
+        json!(true)
+    );
+    assert_eq!(responses[1]["method"], "textDocument/publishDiagnostics");
+    assert_eq!(responses[1]["params"]["diagnostics"], json!([]));
+    assert_eq!(
+        result(&responses, 2),
+        &json!({"uri": URI, "range": {"start": {"line": 0, "character": 4}, "end": {"line": 0, "character": 8}}})
+    );
+    assert_eq!(
+        result(&responses, 3)["contents"]["value"],
+        "```c\nint blub4\n```"
+    );
+    assert_eq!(
+        result(&responses, 4)["contents"]["value"],
+        "```c\nfloat\n```"
+    );
+
+    let symbols = result(&responses, 5).as_array().unwrap();
+    let names: Vec<&str> = symbols
+        .iter()
+        .map(|symbol| symbol["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, vec!["blub", "blah", "main"]);
+    assert_eq!(symbols[1]["detail"], "float blah()");
+    assert_eq!(symbols[1]["children"].as_array().unwrap().len(), 2);
+
+    assert_eq!(result(&responses, 6), &Value::Null);
+    // Nothing is handled after exit
+    assert_eq!(responses.len(), 7);
+}
+
+#[test]
+fn missing_content_length_is_reported() {
+    let mut input = b"Content-Type: application/json\r\n\r\n".to_vec();
+    write_message(
+        &mut input,
+        &json!({"jsonrpc": "2.0", "id": 1, "method": "shutdown"}),
+    )
+    .unwrap();
+    let mut output = Vec::new();
+    Server::new().run(input.as_slice(), &mut output).unwrap();
+
+    let mut reader = BufReader::new(output.as_slice());
+    let error = read_message(&mut reader).unwrap().unwrap().unwrap();
+    assert_eq!(error["error"]["code"], json!(-32700));
+    assert_eq!(error["id"], Value::Null);
+    // The server keeps running and answers the next message
+    let response = read_message(&mut reader).unwrap().unwrap().unwrap();
+    assert_eq!(response, json!({"jsonrpc": "2.0", "id": 1, "result": null}));
+}
+
+#[test]
+fn diagnostics_are_published() {
+    let responses = run(&[
+        open("void main() {\n  x = 1\n}"),
+        json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didChange",
+            "params": {
+                "textDocument": { "uri": URI, "version": 2 },
+                "contentChanges": [{ "text": "void main() {\n  printf(y);\n}" }]
+            }
+        }),
+        request(1, "textDocument/hover", 1, 9),
+        request(2, "textDocument/unknown", 0, 0),
+        request(3, "textDocument/completion", 1, 3),
+    ]);
+
+    let diagnostic = &responses[0]["params"]["diagnostics"][0];
+    assert_eq!(diagnostic["code"], "E0001");
+    assert_eq!(
+        diagnostic["range"]["start"],
+        json!({"line": 2, "character": 0})
+    );
+    let diagnostic = &responses[1]["params"]["diagnostics"][0];
+    assert_eq!(diagnostic["code"], "E0013");
+    assert_eq!(
+        diagnostic["range"]["start"],
+        json!({"line": 1, "character": 9})
+    );
+    assert_eq!(result(&responses, 1), &Value::Null);
+    assert_eq!(responses[3]["error"]["code"], json!(-32601));
+    assert_eq!(
+        result(&responses, 3),
+        &json!([{"label": "printf", "kind": 14}])
+    );
+}
+
+#[test]
+fn rename() {
+    let rename = |id: u64, line: u64, character: u64, new_name: &str| {
+        let mut request = request(id, "textDocument/rename", line, character);
+        request["params"]["newName"] = json!(new_name);
+        request
+    };
+    let responses = run(&[
+        open("int f() {\n  return f();\n}"),
+        rename(1, 1, 10, "g"),
+        rename(2, 1, 10, "return"),
+    ]);
+
+    let edits = &result(&responses, 1)["changes"][URI];
+    assert_eq!(edits.as_array().unwrap().len(), 2);
+    assert_eq!(
+        edits[1]["range"]["start"],
+        json!({"line": 1, "character": 9})
+    );
+    assert_eq!(edits[1]["newText"], "g");
+    assert_eq!(responses[2]["error"]["message"], "'return' is a keyword");
+}
+
+#[test]
+fn semantic_tokens() {
+    let responses = run(&[
+        open("int f() {\n  /* a\n b */ return f();\n}"),
+        json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "textDocument/semanticTokens/full",
+            "params": { "textDocument": { "uri": URI } }
+        }),
+    ]);
+    #[rustfmt::skip]
+    let expected = json!([
+        0, 0, 3, 1, 0, // int
+        0, 4, 1, 2, 0, // f
+        1, 2, 4, 7, 0, // /* a
+        1, 0, 5, 7, 0, //  b */
+        0, 6, 6, 0, 0, // return
+        0, 7, 1, 2, 0, // f
+    ]);
+    assert_eq!(result(&responses, 1)["data"], expected);
+}